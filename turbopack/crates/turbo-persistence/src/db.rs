@@ -2,7 +2,7 @@ use std::{
     any::{Any, TypeId},
     collections::HashSet,
     fs::{self, File, OpenOptions, ReadDir},
-    io::Write,
+    io::{BufReader, BufWriter, Read, Write},
     mem::{swap, transmute, MaybeUninit},
     path::{Path, PathBuf},
     sync::{
@@ -100,11 +100,21 @@ struct TrackedStats {
     miss_global: std::sync::atomic::AtomicU64,
 }
 
+/// Magic bytes at the start of an exported database.
+const EXPORT_MAGIC: &[u8; 4] = b"TPEX";
+/// Version of the export format. Needs to be bumped when the format changes.
+const EXPORT_VERSION: u32 = 1;
+/// The zstd compression level used for exports.
+const EXPORT_COMPRESSION_LEVEL: i32 = 3;
+
 /// TurboPersistence is a persistent key-value store. It is limited to a single writer at a time
 /// using a single write batch. It allows for concurrent reads.
 pub struct TurboPersistence {
     /// The path to the directory where the database is stored
     path: PathBuf,
+    /// If true, the database is opened read-only. It will never modify the directory and all
+    /// write operations fail.
+    read_only: bool,
     /// The inner state of the database. Writing will update that.
     inner: RwLock<Inner>,
     /// A cache for the last WriteBatch. It is used to avoid reallocation of buffers for the
@@ -138,8 +148,20 @@ impl TurboPersistence {
     /// properly. Cleanup only requires to read a few bytes from a few files and to delete
     /// files, so it's fast.
     pub fn open(path: PathBuf) -> Result<Self> {
+        Self::open_with_mode(path, false)
+    }
+
+    /// Open a TurboPersistence database at the given path in read-only mode.
+    /// This will never modify the directory, so no cleanup is performed. Files of an unfinished
+    /// write or compaction are ignored. All write operations will fail.
+    pub fn open_read_only(path: PathBuf) -> Result<Self> {
+        Self::open_with_mode(path, true)
+    }
+
+    fn open_with_mode(path: PathBuf, read_only: bool) -> Result<Self> {
         let mut db = Self {
             path,
+            read_only,
             inner: RwLock::new(Inner {
                 static_sorted_files: Vec::new(),
                 current_sequence_number: 0,
@@ -181,6 +203,7 @@ impl TurboPersistence {
                 if !self
                     .load_directory(entries)
                     .context("Loading persistence directory failed")?
+                    && !self.read_only
                 {
                     self.init_directory()
                         .context("Initializing persistence directory failed")?;
@@ -188,7 +211,10 @@ impl TurboPersistence {
                 Ok(())
             }
             Err(e) => {
-                if e.kind() == std::io::ErrorKind::NotFound {
+                if e.kind() == std::io::ErrorKind::NotFound && self.read_only {
+                    // An empty database
+                    Ok(())
+                } else if e.kind() == std::io::ErrorKind::NotFound {
                     self.create_and_init_directory()
                         .context("Creating and initializing persistence directory failed")?;
                     Ok(())
@@ -244,7 +270,9 @@ impl TurboPersistence {
                     continue;
                 }
                 if seq > current {
-                    fs::remove_file(&path)?;
+                    if !self.read_only {
+                        fs::remove_file(&path)?;
+                    }
                 } else {
                     match ext {
                         "sst" => {
@@ -260,12 +288,14 @@ impl TurboPersistence {
                                 let blob_file = self.path.join(format!("{:08}.blob", seq));
                                 for path in [sst_file, blob_file] {
                                     if fs::exists(&path)? {
-                                        fs::remove_file(path)?;
+                                        if !self.read_only {
+                                            fs::remove_file(path)?;
+                                        }
                                         no_existing_files = false;
                                     }
                                 }
                             }
-                            if no_existing_files {
+                            if no_existing_files && !self.read_only {
                                 fs::remove_file(&path)?;
                             }
                         }
@@ -360,6 +390,114 @@ impl TurboPersistence {
         Ok(ArcSlice::from(buffer))
     }
 
+    /// Writes a consistent copy of the database into a single zstd compressed file, which can be
+    /// restored with [`TurboPersistence::import`]. Commits are blocked while exporting.
+    pub fn export(&self, target: &Path) -> Result<()> {
+        // Holding the read lock prevents commits, so the set of files doesn't change.
+        let inner = self.inner.read();
+        let current = inner.current_sequence_number;
+        let mut files = Vec::new();
+        for entry in fs::read_dir(&self.path)? {
+            let path = entry?.path();
+            if !matches!(
+                path.extension().and_then(|s| s.to_str()),
+                Some("sst" | "blob" | "del")
+            ) {
+                continue;
+            }
+            let seq: u32 = path
+                .file_stem()
+                .context("File has no file stem")?
+                .to_str()
+                .context("File stem is not valid utf-8")?
+                .parse()?;
+            // Files with a higher sequence number are not committed yet
+            if seq <= current {
+                files.push(path);
+            }
+        }
+
+        let file = File::create(target)
+            .with_context(|| format!("Unable to create export file {}", target.display()))?;
+        let mut encoder =
+            zstd::stream::Encoder::new(BufWriter::new(file), EXPORT_COMPRESSION_LEVEL)?;
+        encoder.write_all(EXPORT_MAGIC)?;
+        encoder.write_u32::<BE>(EXPORT_VERSION)?;
+        encoder.write_u32::<BE>(current)?;
+        encoder.write_u32::<BE>(files.len().try_into()?)?;
+        for path in files {
+            let name = path
+                .file_name()
+                .and_then(|s| s.to_str())
+                .context("File name is not valid utf-8")?;
+            encoder.write_u16::<BE>(name.len().try_into()?)?;
+            encoder.write_all(name.as_bytes())?;
+            let content = fs::read(&path)?;
+            encoder.write_u64::<BE>(content.len() as u64)?;
+            encoder.write_all(&content)?;
+        }
+        encoder.finish()?.flush()?;
+        drop(inner);
+        Ok(())
+    }
+
+    /// Creates a database at the given path from a file written by [`TurboPersistence::export`]
+    /// and opens it. The directory must not contain a database yet.
+    pub fn import(source: &Path, path: PathBuf) -> Result<Self> {
+        if fs::exists(path.join("CURRENT"))? {
+            bail!("{} already contains a database", path.display());
+        }
+        fs::create_dir_all(&path)?;
+        let file = File::open(source)
+            .with_context(|| format!("Unable to open export file {}", source.display()))?;
+        let mut decoder = zstd::stream::Decoder::new(BufReader::new(file))?;
+        let mut magic = [0; 4];
+        decoder.read_exact(&mut magic)?;
+        if &magic != EXPORT_MAGIC {
+            bail!("{} is not an exported database", source.display());
+        }
+        let version = decoder.read_u32::<BE>()?;
+        if version != EXPORT_VERSION {
+            bail!("Unsupported export format version {version}");
+        }
+        let current = decoder.read_u32::<BE>()?;
+        let count = decoder.read_u32::<BE>()?;
+        for _ in 0..count {
+            let name_len = decoder.read_u16::<BE>()? as usize;
+            let mut name = vec![0; name_len];
+            decoder.read_exact(&mut name)?;
+            let name = String::from_utf8(name)?;
+            // Only accept plain database file names to never write outside of the directory
+            if Path::new(&name).file_name().and_then(|s| s.to_str()) != Some(name.as_str())
+                || !matches!(
+                    Path::new(&name).extension().and_then(|s| s.to_str()),
+                    Some("sst" | "blob" | "del")
+                )
+            {
+                bail!("Unexpected file {name:?} in export");
+            }
+            let len = decoder.read_u64::<BE>()?;
+            let mut file = File::create(path.join(&name))?;
+            let copied = std::io::copy(&mut (&mut decoder).take(len), &mut file)?;
+            if copied != len {
+                bail!("Export is truncated");
+            }
+            file.sync_all()?;
+        }
+        // The CURRENT file is written last, so an interrupted import is not picked up as a valid
+        // database.
+        let mut current_file = File::create(path.join("CURRENT"))?;
+        current_file.write_u32::<BE>(current)?;
+        current_file.sync_all()?;
+        drop(current_file);
+        Self::open(path)
+    }
+
+    /// Returns true if the database was opened read-only.
+    pub fn is_read_only(&self) -> bool {
+        self.read_only
+    }
+
     /// Returns true if the database is empty.
     pub fn is_empty(&self) -> bool {
         self.inner.read().static_sorted_files.is_empty()
@@ -372,6 +510,9 @@ impl TurboPersistence {
     pub fn write_batch<K: StoreKey + Send + Sync + 'static, const FAMILIES: usize>(
         &self,
     ) -> Result<WriteBatch<K, FAMILIES>> {
+        if self.read_only {
+            bail!("Unable to write to a database that is opened read-only");
+        }
         if self
             .active_write_operation
             .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
@@ -494,6 +635,9 @@ impl TurboPersistence {
     /// need to be read to find a key. It also limits the maximum number of SST files that are
     /// merged at once, which is the main factor for the runtime of the compaction.
     pub fn compact(&self, max_coverage: f32, max_merge_sequence: usize) -> Result<()> {
+        if self.read_only {
+            bail!("Unable to compact a database that is opened read-only");
+        }
         if self
             .active_write_operation
             .compare_exchange(false, true, Ordering::AcqRel, Ordering::Acquire)
@@ -448,3 +448,70 @@ fn persist_changes() -> Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn read_only() -> Result<()> {
+    let tempdir = tempfile::tempdir()?;
+    let path = tempdir.path().join("db");
+
+    {
+        // Opening a missing database read-only must not create it
+        let db = TurboPersistence::open_read_only(path.clone())?;
+        assert!(db.is_empty());
+        assert!(db.write_batch::<Vec<u8>, 1>().is_err());
+        db.shutdown()?;
+        assert!(!path.exists());
+    }
+
+    {
+        let db = TurboPersistence::open(path.clone())?;
+        let b = db.write_batch::<Vec<u8>, 1>()?;
+        b.put(0, vec![1, 2, 3], vec![42].into())?;
+        db.commit_write_batch(b)?;
+        db.shutdown()?;
+    }
+
+    {
+        let db = TurboPersistence::open_read_only(path.clone())?;
+        assert!(db.is_read_only());
+        assert_eq!(db.get(0, &vec![1u8, 2, 3])?.as_deref(), Some(&[42][..]));
+        assert!(db.write_batch::<Vec<u8>, 1>().is_err());
+        assert!(db.compact(0.0, usize::MAX).is_err());
+        db.shutdown()?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn export_import() -> Result<()> {
+    let tempdir = tempfile::tempdir()?;
+    let path = tempdir.path().join("db");
+    let export = tempdir.path().join("export");
+
+    {
+        let db = TurboPersistence::open(path.clone())?;
+        for value in [1u8, 2] {
+            let b = db.write_batch::<Vec<u8>, 1>()?;
+            for key in 0..100u8 {
+                b.put(0, vec![key], vec![value].into())?;
+            }
+            db.commit_write_batch(b)?;
+        }
+        db.export(&export)?;
+        db.shutdown()?;
+    }
+
+    {
+        let db = TurboPersistence::import(&export, tempdir.path().join("imported"))?;
+        for key in 0..100u8 {
+            assert_eq!(db.get(0, &vec![key])?.as_deref(), Some(&[2][..]));
+        }
+        db.shutdown()?;
+    }
+
+    // Importing into an existing database fails
+    assert!(TurboPersistence::import(&export, path).is_err());
+
+    Ok(())
+}
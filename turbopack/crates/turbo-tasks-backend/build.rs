@@ -1,5 +1,7 @@
 use turbo_tasks_build::generate_register;
 
 fn main() {
+    // The snapshot synchronization can be model checked with `--cfg loom`
+    println!("cargo::rustc-check-cfg=cfg(loom)");
     generate_register();
 }
@@ -0,0 +1,75 @@
+use std::{
+    hash::{Hash, Hasher},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use rustc_hash::FxHasher;
+use smallvec::SmallVec;
+use turbo_tasks::{registry, FxDashMap, SharedReference, TypedSharedReference, ValueTypeId};
+
+/// Only values with a serialized size up to this limit are interned. Larger values are rarely
+/// identical and would make the interner hold on to a lot of memory.
+const MAX_INTERNED_SIZE: usize = 64;
+
+/// Upper bound for the number of interned values. When it's reached, the interner starts over
+/// with an empty map. Values that were interned before stay shared.
+const MAX_INTERNED_ENTRIES: usize = 64 * 1024;
+
+/// Deduplicates equal cell contents in memory, so that e.g. many empty vecs produced by different
+/// tasks share a single allocation.
+///
+/// Values are compared with the `PartialEq` implementation of their value type. Value types
+/// without one (`eq = "manual"`) are never interned. The serialized representation is only used
+/// to find candidates and to skip large values. Equal values that serialize differently are not
+/// deduplicated, but values are never deduplicated unless they are equal.
+///
+/// This only deduplicates in memory. Cells are persisted as part of the data of their task, so
+/// the persistent cache still stores every cell on its own.
+pub struct CellInterner {
+    map: FxDashMap<(ValueTypeId, u64), SmallVec<[SharedReference; 1]>>,
+    len: AtomicUsize,
+}
+
+impl CellInterner {
+    pub fn new() -> Self {
+        Self {
+            map: FxDashMap::default(),
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    /// Returns a previously interned value that is equal to `value`, or interns `value` when
+    /// there is none yet.
+    pub fn intern(&self, ty: ValueTypeId, value: SharedReference) -> SharedReference {
+        let value_type = registry::get_value_type(ty);
+        if !value_type.has_eq() {
+            return value;
+        }
+        let typed = TypedSharedReference(ty, value);
+        let Ok(bytes) = pot::to_vec(&typed) else {
+            return typed.1;
+        };
+        if bytes.len() > MAX_INTERNED_SIZE {
+            return typed.1;
+        }
+        let value = typed.1;
+        let mut hasher = FxHasher::default();
+        bytes.hash(&mut hasher);
+        let key = (ty, hasher.finish());
+
+        if self.len.load(Ordering::Relaxed) >= MAX_INTERNED_ENTRIES {
+            self.map.clear();
+            self.len.store(0, Ordering::Relaxed);
+        }
+        let mut candidates = self.map.entry(key).or_default();
+        if let Some(existing) = candidates
+            .iter()
+            .find(|existing| value_type.is_eq(&*existing.0, &*value.0) == Some(true))
+        {
+            return existing.clone();
+        }
+        candidates.push(value.clone());
+        self.len.fetch_add(1, Ordering::Relaxed);
+        value
+    }
+}
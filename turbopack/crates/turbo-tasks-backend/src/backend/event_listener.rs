@@ -0,0 +1,67 @@
+use std::time::Duration;
+
+use turbo_tasks::{backend::CachedTaskType, TaskId};
+
+/// Where a task was found when looking it up by its task type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskCacheLookup {
+    /// The task already exists in memory.
+    Memory,
+    /// The task was restored from the persistent cache.
+    PersistentCache,
+    /// The task didn't exist yet and was created.
+    Miss,
+}
+
+/// The progress of writing a snapshot.
+#[derive(Debug, Clone, Copy)]
+pub struct SnapshotProgress {
+    /// The number of updates that were written so far.
+    pub processed: usize,
+    /// The number of updates in the snapshot.
+    pub total: usize,
+    /// The number of bytes that were written so far.
+    pub bytes_written: u64,
+}
+
+/// Observes the health of the backend, e.g. for telemetry or to show the state of the persistent
+/// cache in a UI. All methods have empty default implementations.
+///
+/// Callbacks are called synchronously from the backend, partly on hot paths. Implementations
+/// should be cheap and must not call into turbo-tasks.
+pub trait BackendEventListener: Send + Sync {
+    /// A snapshot of the modified data is about to be persisted.
+    fn snapshot_started(&self) {}
+
+    /// Some updates of the snapshot were written. Called repeatedly, possibly from multiple
+    /// threads, until all updates are written.
+    fn snapshot_progress(&self, _progress: SnapshotProgress) {}
+
+    /// A snapshot was persisted successfully. `new_data` is false when there was nothing to
+    /// persist.
+    fn snapshot_finished(&self, _duration: Duration, _new_data: bool) {}
+
+    /// The persistent cache was compacted while idle, which reclaims the space of outdated
+    /// entries.
+    fn compaction_finished(&self, _duration: Duration, _reclaimed_bytes: u64) {}
+
+    /// A task was looked up by its task type.
+    fn task_cache_lookup(&self, _task_type: &CachedTaskType, _result: TaskCacheLookup) {}
+
+    /// Persisting failed, e.g. because the disk is full. Persisting is disabled after the first
+    /// failure and the backend continues in memory-only mode.
+    fn persistence_error(&self, _error: &anyhow::Error) {}
+
+    /// A task starts executing.
+    fn task_execution_started(&self, _task: TaskId) {}
+
+    /// A task execution completed. `duration` excludes the time the task was waiting.
+    fn task_execution_finished(&self, _task: TaskId, _duration: Duration) {}
+
+    /// A task became dirty and will be executed again when it's active.
+    fn task_invalidated(&self, _task: TaskId) {}
+
+    /// The background consistency check found corrupted or inconsistent persisted data of a
+    /// task, see [`crate::BackendOptions::consistency_check_interval`].
+    fn consistency_problem(&self, _task: TaskId, _problem: &str) {}
+}
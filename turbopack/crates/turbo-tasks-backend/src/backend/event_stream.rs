@@ -0,0 +1,137 @@
+use std::time::Duration;
+
+use futures::{stream, Stream};
+use tokio::sync::broadcast;
+use turbo_tasks::{backend::CachedTaskType, registry, TaskId};
+
+use crate::backend::event_listener::{BackendEventListener, SnapshotProgress, TaskCacheLookup};
+
+/// A structured event of the backend, see [`BackendEventStream`].
+#[derive(Debug, Clone)]
+pub enum BackendEvent {
+    TaskExecutionStarted {
+        task: TaskId,
+    },
+    TaskExecutionFinished {
+        task: TaskId,
+        duration: Duration,
+    },
+    TaskInvalidated {
+        task: TaskId,
+    },
+    TaskCacheLookup {
+        /// The global name of the function of the task.
+        function: &'static str,
+        result: TaskCacheLookup,
+    },
+    SnapshotStarted,
+    SnapshotProgress(SnapshotProgress),
+    SnapshotFinished {
+        duration: Duration,
+        new_data: bool,
+    },
+    CompactionFinished {
+        duration: Duration,
+        reclaimed_bytes: u64,
+    },
+    PersistenceError {
+        message: String,
+    },
+    ConsistencyProblem {
+        task: TaskId,
+        problem: String,
+    },
+    /// The subscriber didn't keep up and missed this number of events.
+    Lagged {
+        missed: u64,
+    },
+}
+
+/// A [`BackendEventListener`] that broadcasts all events to async subscribers, e.g. to show live
+/// build insight in a devtools panel. Pass it as [`crate::BackendOptions::event_listener`].
+///
+/// Events are buffered up to a fixed capacity per subscriber. Subscribers that don't keep up
+/// receive a [`BackendEvent::Lagged`] event instead of the oldest events, so a slow UI never
+/// blocks the backend.
+pub struct BackendEventStream {
+    sender: broadcast::Sender<BackendEvent>,
+}
+
+impl BackendEventStream {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    /// Returns a stream of all events that happen after subscribing.
+    pub fn subscribe(&self) -> impl Stream<Item = BackendEvent> + Send + 'static {
+        stream::unfold(self.sender.subscribe(), |mut receiver| async move {
+            let event = match receiver.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(missed)) => BackendEvent::Lagged { missed },
+                Err(broadcast::error::RecvError::Closed) => return None,
+            };
+            Some((event, receiver))
+        })
+    }
+
+    fn send(&self, event: impl FnOnce() -> BackendEvent) {
+        // Skip constructing the event when nobody is listening
+        if self.sender.receiver_count() > 0 {
+            let _ = self.sender.send(event());
+        }
+    }
+}
+
+impl BackendEventListener for BackendEventStream {
+    fn snapshot_started(&self) {
+        self.send(|| BackendEvent::SnapshotStarted);
+    }
+
+    fn snapshot_progress(&self, progress: SnapshotProgress) {
+        self.send(|| BackendEvent::SnapshotProgress(progress));
+    }
+
+    fn snapshot_finished(&self, duration: Duration, new_data: bool) {
+        self.send(|| BackendEvent::SnapshotFinished { duration, new_data });
+    }
+
+    fn compaction_finished(&self, duration: Duration, reclaimed_bytes: u64) {
+        self.send(|| BackendEvent::CompactionFinished {
+            duration,
+            reclaimed_bytes,
+        });
+    }
+
+    fn task_cache_lookup(&self, task_type: &CachedTaskType, result: TaskCacheLookup) {
+        self.send(|| BackendEvent::TaskCacheLookup {
+            function: registry::get_function_global_name(task_type.fn_type),
+            result,
+        });
+    }
+
+    fn persistence_error(&self, error: &anyhow::Error) {
+        self.send(|| BackendEvent::PersistenceError {
+            message: format!("{error:?}"),
+        });
+    }
+
+    fn task_execution_started(&self, task: TaskId) {
+        self.send(|| BackendEvent::TaskExecutionStarted { task });
+    }
+
+    fn task_execution_finished(&self, task: TaskId, duration: Duration) {
+        self.send(|| BackendEvent::TaskExecutionFinished { task, duration });
+    }
+
+    fn task_invalidated(&self, task: TaskId) {
+        self.send(|| BackendEvent::TaskInvalidated { task });
+    }
+
+    fn consistency_problem(&self, task: TaskId, problem: &str) {
+        self.send(|| BackendEvent::ConsistencyProblem {
+            task,
+            problem: problem.to_string(),
+        });
+    }
+}
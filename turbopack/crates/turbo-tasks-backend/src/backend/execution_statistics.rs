@@ -0,0 +1,60 @@
+use std::{sync::OnceLock, time::Duration};
+
+use turbo_tasks::{registry, FunctionId, FxDashMap};
+
+/// Aggregated executions of a single function.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct FunctionExecutionStatistics {
+    /// The number of completed executions.
+    pub executions: u64,
+    /// The summed duration of all executions.
+    pub total_duration: Duration,
+    /// The highest memory usage reported by a single execution.
+    pub max_memory_usage: usize,
+}
+
+/// Collects execution statistics per function once enabled. Disabled by default, since it costs
+/// a task type lookup for every completed execution.
+#[derive(Default)]
+pub struct ExecutionStatistics {
+    inner: OnceLock<FxDashMap<FunctionId, FunctionExecutionStatistics>>,
+}
+
+impl ExecutionStatistics {
+    pub fn enable(&self) {
+        self.inner.get_or_init(FxDashMap::default);
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.inner.get().is_some()
+    }
+
+    pub fn record(&self, function_id: FunctionId, duration: Duration, memory_usage: usize) {
+        let Some(inner) = self.inner.get() else {
+            return;
+        };
+        let mut stats = inner.entry(function_id).or_default();
+        stats.executions += 1;
+        stats.total_duration += duration;
+        stats.max_memory_usage = stats.max_memory_usage.max(memory_usage);
+    }
+
+    /// Returns the statistics of all executed functions by their global name, sorted by total
+    /// duration, longest first.
+    pub fn get(&self) -> Vec<(&'static str, FunctionExecutionStatistics)> {
+        let Some(inner) = self.inner.get() else {
+            return Vec::new();
+        };
+        let mut stats = inner
+            .iter()
+            .map(|entry| {
+                (
+                    registry::get_function_global_name(*entry.key()),
+                    *entry.value(),
+                )
+            })
+            .collect::<Vec<_>>();
+        stats.sort_by(|(_, a), (_, b)| b.total_duration.cmp(&a.total_duration));
+        stats
+    }
+}
@@ -0,0 +1,95 @@
+use std::{
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+use turbo_tasks::{FxDashMap, TaskId};
+
+/// How often a single task was invalidated, see [`InvalidationStatistics::top`].
+#[derive(Debug, Clone)]
+pub struct TaskInvalidationChurn {
+    pub task: TaskId,
+    /// The description of the task, e.g. its function name and arguments.
+    pub description: String,
+    /// The number of invalidations since the statistics were enabled.
+    pub invalidations: u64,
+    /// The average number of invalidations per minute since the statistics were enabled.
+    pub invalidations_per_minute: f64,
+    /// The time since the last invalidation of the task.
+    pub since_last_invalidation: Duration,
+}
+
+struct TaskInvalidations {
+    count: u64,
+    last: Instant,
+}
+
+struct Inner {
+    enabled_at: Instant,
+    tasks: FxDashMap<TaskId, TaskInvalidations>,
+}
+
+/// Counts invalidations per task once enabled, to find tasks that are invalidated over and over,
+/// e.g. by a noisy file watcher. Disabled by default, since it costs a map update for every
+/// invalidation.
+#[derive(Default)]
+pub struct InvalidationStatistics {
+    inner: OnceLock<Inner>,
+}
+
+impl InvalidationStatistics {
+    pub fn enable(&self) {
+        self.inner.get_or_init(|| Inner {
+            enabled_at: Instant::now(),
+            tasks: FxDashMap::default(),
+        });
+    }
+
+    pub fn record(&self, task_id: TaskId) {
+        let Some(inner) = self.inner.get() else {
+            return;
+        };
+        let now = Instant::now();
+        inner
+            .tasks
+            .entry(task_id)
+            .and_modify(|invalidations| {
+                invalidations.count += 1;
+                invalidations.last = now;
+            })
+            .or_insert(TaskInvalidations {
+                count: 1,
+                last: now,
+            });
+    }
+
+    /// Returns the `limit` most invalidated tasks, the most invalidated first.
+    pub fn top(
+        &self,
+        limit: usize,
+        describe: impl Fn(TaskId) -> String,
+    ) -> Vec<TaskInvalidationChurn> {
+        let Some(inner) = self.inner.get() else {
+            return Vec::new();
+        };
+        let mut tasks = inner
+            .tasks
+            .iter()
+            .map(|entry| (*entry.key(), entry.count, entry.last))
+            .collect::<Vec<_>>();
+        tasks.sort_by(|(_, a, _), (_, b, _)| b.cmp(a));
+        tasks.truncate(limit);
+        // Avoid a rate above the count for tasks invalidated right after enabling
+        let minutes = inner.enabled_at.elapsed().as_secs_f64().max(60.0) / 60.0;
+        tasks
+            .into_iter()
+            .map(|(task, count, last)| TaskInvalidationChurn {
+                task,
+                description: describe(task),
+                invalidations: count,
+                invalidations_per_minute: count as f64 / minutes,
+                since_last_invalidation: last.elapsed(),
+            })
+            .collect()
+    }
+}
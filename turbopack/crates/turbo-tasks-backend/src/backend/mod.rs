@@ -1,10 +1,20 @@
+mod cell_interner;
 mod dynamic_storage;
+mod event_listener;
+mod event_stream;
+mod execution_statistics;
+mod invalidation_statistics;
 mod operation;
+mod operation_statistics;
 mod persisted_storage_log;
+mod snapshot_diff;
+mod snapshot_gate;
 mod storage;
+#[cfg(feature = "verify_graph")]
+mod verify_graph;
+mod wait_graph;
 
 use std::{
-    borrow::Cow,
     future::Future,
     hash::BuildHasherDefault,
     mem::take,
@@ -16,65 +26,107 @@ use std::{
     thread::available_parallelism,
 };
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use auto_hash_map::{AutoMap, AutoSet};
-use parking_lot::{Condvar, Mutex};
+use parking_lot::Mutex;
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
 use rustc_hash::{FxHashMap, FxHashSet, FxHasher};
 use smallvec::smallvec;
-use tokio::time::{Duration, Instant};
+use tokio::{
+    runtime::RuntimeFlavor,
+    task::block_in_place,
+    time::{Duration, Instant},
+};
 use turbo_tasks::{
     backend::{
         Backend, BackendJobId, CachedTaskType, CellContent, TaskExecutionSpec, TransientTaskRoot,
-        TransientTaskType, TypedCellContent,
+        TransientTaskType, TurboTasksPanic, TypedCellContent,
     },
     event::{Event, EventListener},
     registry,
     task_statistics::TaskStatisticsApi,
+    turbo_tasks_scope,
     util::IdFactoryWithReuse,
     CellId, FunctionId, FxDashMap, RawVc, ReadCellOptions, ReadConsistency, SessionId, TaskId,
     TraitTypeId, TurboTasksBackendApi, ValueTypeId, TRANSIENT_TASK_BIT,
 };
 
-pub use self::{operation::AnyOperation, storage::TaskDataCategory};
+pub use self::{
+    event_listener::{BackendEventListener, SnapshotProgress, TaskCacheLookup},
+    event_stream::{BackendEvent, BackendEventStream},
+    execution_statistics::FunctionExecutionStatistics,
+    invalidation_statistics::TaskInvalidationChurn,
+    operation::AnyOperation,
+    operation_statistics::LatencyHistogram,
+    snapshot_diff::{ChangedTask, SnapshotDiff},
+    storage::TaskDataCategory,
+};
 #[cfg(feature = "trace_task_dirty")]
 use crate::backend::operation::TaskDirtyCause;
 use crate::{
     backend::{
+        cell_interner::CellInterner,
+        execution_statistics::ExecutionStatistics,
+        invalidation_statistics::InvalidationStatistics,
         operation::{
-            connect_children, get_aggregation_number, is_root_node, prepare_new_children,
-            AggregatedDataUpdate, AggregationUpdateJob, AggregationUpdateQueue,
-            CleanupOldEdgesOperation, ConnectChildOperation, ExecuteContext, ExecuteContextImpl,
-            Operation, OutdatedEdge, TaskGuard,
+            connect_children, get_aggregation_number, is_aggregating_node, is_root_node,
+            prepare_new_children, AggregatedDataUpdate, AggregationUpdateJob,
+            AggregationUpdateQueue, CleanupOldEdgesOperation, ConnectChildOperation,
+            ExecuteContext, ExecuteContextImpl, Operation, OutdatedEdge, TaskGuard,
         },
+        operation_statistics::OperationStatistics,
         persisted_storage_log::PersistedStorageLog,
+        snapshot_gate::SnapshotGate,
         storage::{get, get_many, get_mut, get_mut_or_insert_with, iter_many, remove, Storage},
+        wait_graph::WaitGraph,
     },
-    backing_storage::BackingStorage,
+    backing_storage::{BackingStorage, InvalidationKeyUpdates, RestoreStatistics},
     data::{
         ActivenessState, AggregationNumber, CachedDataItem, CachedDataItemKey, CachedDataItemType,
         CachedDataItemValue, CachedDataItemValueRef, CachedDataUpdate, CellRef, CollectibleRef,
         CollectiblesRef, DirtyState, InProgressCellState, InProgressState, InProgressStateInner,
-        OutputValue, RootType,
+        OutputValue, RootType, TaskError,
     },
     utils::{bi_map::BiMap, chunked_vec::ChunkedVec, ptr_eq_arc::PtrEqArc, sharded::Sharded},
 };
 
 const BACKEND_JOB_INITIAL_SNAPSHOT: BackendJobId = unsafe { BackendJobId::new_unchecked(1) };
 const BACKEND_JOB_FOLLOW_UP_SNAPSHOT: BackendJobId = unsafe { BackendJobId::new_unchecked(2) };
-
-const SNAPSHOT_REQUESTED_BIT: usize = 1 << (usize::BITS - 1);
-
-struct SnapshotRequest {
-    snapshot_requested: bool,
-    suspended_operations: FxHashSet<PtrEqArc<AnyOperation>>,
+const BACKEND_JOB_COMPACTION: BackendJobId = unsafe { BackendJobId::new_unchecked(3) };
+const BACKEND_JOB_PREFETCH: BackendJobId = unsafe { BackendJobId::new_unchecked(4) };
+const BACKEND_JOB_REMOVE_ORPHANED_DATA: BackendJobId = unsafe { BackendJobId::new_unchecked(5) };
+const BACKEND_JOB_CONSISTENCY_CHECK: BackendJobId = unsafe { BackendJobId::new_unchecked(6) };
+const BACKEND_JOB_FLUSH_INVALIDATIONS: BackendJobId = unsafe { BackendJobId::new_unchecked(7) };
+
+/// The number of persisted tasks that are checked per run of the consistency check.
+const CONSISTENCY_CHECK_SAMPLE_SIZE: usize = 1000;
+
+/// When fewer persistent task ids are left, persisting is disabled, so the remaining ids last for
+/// the rest of the session. Creating even more new persistent tasks in that session panics.
+const PERSISTED_TASK_ID_RESERVE: u64 = 1 << 20;
+
+/// Describes a finished snapshot.
+#[derive(Debug, Clone, Copy)]
+pub struct SnapshotInfo {
+    /// When the modified data was captured. Modifications after that are not part of the
+    /// snapshot.
+    pub captured_at: Instant,
+    /// How long capturing and writing the snapshot took.
+    pub duration: Duration,
+    /// The number of new task cache entries that were written.
+    pub task_cache_entries: usize,
+    /// The number of modified task data items that were written.
+    pub task_data_updates: usize,
+    /// The number of invalidation key registrations and states that were written.
+    pub invalidation_key_updates: usize,
 }
 
-impl SnapshotRequest {
-    fn new() -> Self {
-        Self {
-            snapshot_requested: false,
-            suspended_operations: FxHashSet::default(),
-        }
+impl SnapshotInfo {
+    /// Returns false when there was nothing to persist.
+    pub fn has_new_data(&self) -> bool {
+        self.task_cache_entries > 0
+            || self.task_data_updates > 0
+            || self.invalidation_key_updates > 0
     }
 }
 
@@ -108,6 +160,98 @@ pub enum StorageMode {
     ReadWrite,
 }
 
+/// Retries failed or panicked tasks before their error is stored as output. This helps with
+/// transient failures, e.g. a file that is temporarily locked by another process.
+///
+/// Only the task that raised an error is retried. Tasks that fail because they read the error of
+/// a failed upstream task store that error right away.
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// The number of times a task is executed again after it failed.
+    pub max_retries: u32,
+    /// The delay before the first retry. It doubles with every further retry.
+    pub backoff: Duration,
+}
+
+/// The persistent task id space of the cache is nearly exhausted. Reported as persistence error,
+/// since persisting is disabled to keep the remaining ids for the current session. The cache
+/// needs to be deleted to start with a fresh id space.
+#[derive(Debug, Clone, Copy)]
+pub struct TaskIdSpaceExhausted {
+    /// The number of persistent task ids that were left.
+    pub remaining: u64,
+}
+
+impl std::fmt::Display for TaskIdSpaceExhausted {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The persistent task id space is nearly exhausted ({} ids left). Persisting is \
+             disabled, delete the cache to reset it.",
+            self.remaining
+        )
+    }
+}
+
+impl std::error::Error for TaskIdSpaceExhausted {}
+
+/// Tunes the aggregation graph, which trades the cost of propagating changes through the graph
+/// against the memory used for aggregated data.
+///
+/// The threshold at which a task becomes an aggregating node is not configurable, since the
+/// persisted graph relies on it.
+#[derive(Debug, Clone, Copy)]
+pub struct AggregationOptions {
+    /// How much the aggregation number of a child is increased above the aggregation number of a
+    /// leaf parent. Higher values make children reach the aggregating threshold faster, which
+    /// creates more aggregating nodes with fewer followers each.
+    pub base_aggregation_number_increment: u32,
+    /// The limit of (uppers - 1) * followers of a task before its aggregation number is
+    /// increased to place it in a bigger aggregation group. Lower values reduce the number of
+    /// updates per change, but create more aggregated data.
+    pub max_uppers_follower_product: usize,
+}
+
+/// Bounds of the interval between snapshots. The interval shrinks from `max` towards `min` the
+/// more updates are pending, so large rebuilds are persisted sooner. When nothing is pending, the
+/// snapshot is skipped.
+#[derive(Debug, Clone, Copy)]
+pub struct SnapshotInterval {
+    /// The interval when a lot of updates are pending.
+    pub min: Duration,
+    /// The interval when only a few updates are pending.
+    pub max: Duration,
+    /// The number of pending updates at which the interval reaches `min`.
+    pub pending_updates_for_min: usize,
+}
+
+impl SnapshotInterval {
+    fn for_pending_updates(&self, pending_updates: usize) -> Duration {
+        let ratio = (pending_updates as f64 / self.pending_updates_for_min.max(1) as f64).min(1.0);
+        self.max
+            .saturating_sub(self.max.saturating_sub(self.min).mul_f64(ratio))
+    }
+}
+
+impl Default for SnapshotInterval {
+    fn default() -> Self {
+        Self {
+            min: Duration::from_secs(5),
+            max: Duration::from_secs(30),
+            pending_updates_for_min: 1_000_000,
+        }
+    }
+}
+
+impl Default for AggregationOptions {
+    fn default() -> Self {
+        Self {
+            base_aggregation_number_increment: 3,
+            max_uppers_follower_product: 31,
+        }
+    }
+}
+
 pub struct BackendOptions {
     /// Enables dependency tracking.
     ///
@@ -130,6 +274,78 @@ pub struct BackendOptions {
 
     /// Enables the backing storage.
     pub storage_mode: Option<StorageMode>,
+
+    /// Compares new cell content with the existing content before invalidating readers.
+    ///
+    /// When enabled: Cells that are recomputed with content equal to the old content don't
+    /// invalidate dependent tasks. Contents are compared with the `PartialEq` implementation of
+    /// their value type, values of types with `eq = "manual"` are always considered as changed.
+    /// This costs an additional comparison for every cell update of a dirty task.
+    pub cell_equality_check: bool,
+
+    /// Deduplicates identical small cell contents in memory.
+    ///
+    /// When enabled: Equal small cell values share a single allocation. This costs an additional
+    /// serialization and comparison for every cell update. The persistent cache is not
+    /// deduplicated.
+    pub cell_interning: bool,
+
+    /// Observes snapshots, compactions, task cache lookups and persistence errors.
+    ///
+    /// Persisting is disabled after the first error, e.g. because the disk is full, and the
+    /// backend continues in memory-only mode. See [`TurboTasksBackend::is_memory_only`].
+    pub event_listener: Option<Arc<dyn BackendEventListener>>,
+
+    /// Retries failed or panicked tasks. Disabled by default.
+    pub retry_policy: Option<RetryPolicy>,
+
+    /// The number of the most frequently accessed tasks that are restored from the backing
+    /// storage in the background on startup, so the first requests don't need to read them from
+    /// disk one by one. Which tasks are accessed is recorded in every session. 0 disables it.
+    pub prefetch_hot_tasks: usize,
+
+    /// Tunes the aggregation graph for very large or very deep task graphs.
+    pub aggregation: AggregationOptions,
+
+    /// The number of shards of the task storage, the task cache and the persistence logs. More
+    /// shards reduce lock contention with many threads, but cost memory and make snapshots
+    /// iterate more shards. Rounded up to a power of two.
+    ///
+    /// Defaults to 64 shards per available CPU core.
+    pub shard_amount: Option<usize>,
+
+    /// Reports operations that are still in flight when a snapshot waits longer than this for
+    /// all operations to complete or suspend. The report is repeated every time the threshold
+    /// elapses again.
+    ///
+    /// When enabled: Every operation records the current tracing span, so the report can show
+    /// where the stuck operations were started. Disabled by default.
+    pub snapshot_stall_threshold: Option<Duration>,
+
+    /// Cell contents of task executions that took less than this are not persisted. They are
+    /// recomputed when read after a restart, which is cheaper than writing and restoring them.
+    /// The task itself, its output and its edges are still persisted. Disabled by default.
+    pub persistence_cost_threshold: Option<Duration>,
+
+    /// Bounds of the interval between follow-up snapshots, which adapts to the number of pending
+    /// updates.
+    pub snapshot_interval: SnapshotInterval,
+
+    /// The number of new task cache entries at which they are persisted ahead of the next
+    /// snapshot, so creating a lot of tasks doesn't keep all entries in memory until then. Only
+    /// applies when the backing storage can persist them separately.
+    pub task_cache_flush_threshold: usize,
+
+    /// Interval of a background job that checks a sample of the persisted tasks for corrupted or
+    /// inconsistent data while idle. Problems are reported to the event listener. Disabled by
+    /// default.
+    pub consistency_check_interval: Option<Duration>,
+
+    /// Collects invalidations of external resources, e.g. file watcher events, for this long
+    /// after the first one and applies them as a single operation. This avoids independent
+    /// invalidation cascades when hundreds of files change at once, e.g. on a git checkout.
+    /// Disabled by default.
+    pub invalidation_debounce: Option<Duration>,
 }
 
 impl Default for BackendOptions {
@@ -139,6 +355,19 @@ impl Default for BackendOptions {
             children_tracking: true,
             active_tracking: true,
             storage_mode: Some(StorageMode::ReadWrite),
+            cell_equality_check: false,
+            cell_interning: false,
+            event_listener: None,
+            retry_policy: None,
+            prefetch_hot_tasks: 0,
+            aggregation: AggregationOptions::default(),
+            shard_amount: None,
+            snapshot_stall_threshold: None,
+            persistence_cost_threshold: None,
+            snapshot_interval: SnapshotInterval::default(),
+            task_cache_flush_threshold: 100_000,
+            consistency_check_interval: None,
+            invalidation_debounce: None,
         }
     }
 }
@@ -157,37 +386,76 @@ struct TurboTasksBackendInner<B: BackingStorage> {
     transient_task_id_factory: IdFactoryWithReuse<TaskId>,
 
     persisted_task_cache_log: Option<TaskCacheLog>,
+    /// The number of entries in [`Self::persisted_task_cache_log`], so that it doesn't need to
+    /// be counted over all shards.
+    persisted_task_cache_log_len: AtomicUsize,
     task_cache: BiMap<Arc<CachedTaskType>, TaskId>,
     transient_tasks: FxDashMap<TaskId, Arc<TransientTask>>,
 
     persisted_storage_data_log: Option<PersistedStorageLog>,
     persisted_storage_meta_log: Option<PersistedStorageLog>,
+    /// Tasks that are invalidated by an external key, e.g. the path of a watched file.
+    invalidation_keys: FxDashMap<String, FxHashSet<TaskId>>,
+    /// The keys in `invalidation_keys` per task, so they can be dropped when the task executes
+    /// again.
+    task_invalidation_keys: FxDashMap<TaskId, FxHashSet<String>>,
+    /// Invalidation keys of persistent tasks registered or removed since the last snapshot.
+    persisted_invalidation_key_log: Option<Mutex<InvalidationKeyUpdates>>,
     storage: Storage,
-
-    /// Number of executing operations + Highest bit is set when snapshot is
-    /// requested. When that bit is set, operations should pause until the
-    /// snapshot is completed. When the bit is set and in progress counter
-    /// reaches zero, `operations_completed_when_snapshot_requested` is
-    /// triggered.
-    in_progress_operations: AtomicUsize,
-
-    snapshot_request: Mutex<SnapshotRequest>,
-    /// Condition Variable that is triggered when `in_progress_operations`
-    /// reaches zero while snapshot is requested. All operations are either
-    /// completed or suspended.
-    operations_suspended: Condvar,
-    /// Condition Variable that is triggered when a snapshot is completed and
-    /// operations can continue.
-    snapshot_completed: Condvar,
+    cell_interner: Option<CellInterner>,
+
+    /// Lets operations complete or suspend while a snapshot is taken.
+    snapshot_gate: SnapshotGate<PtrEqArc<AnyOperation>>,
+    /// The operations in progress by operation id, when
+    /// [`BackendOptions::snapshot_stall_threshold`] is set.
+    in_flight_operations: Option<FxDashMap<u64, InFlightOperation>>,
+    next_operation_id: AtomicU64,
     /// The timestamp of the last started snapshot since [`Self::start_time`].
     last_snapshot: AtomicU64,
+    /// The number of snapshots that started to capture the modified data. Snapshot requests that
+    /// arrive while a snapshot is running use it to detect that a later snapshot covered them.
+    snapshots_started: AtomicU64,
+    /// Serializes snapshots and holds the result of the last one, so coalesced requests can
+    /// share it.
+    last_snapshot_result: Mutex<Option<SnapshotInfo>>,
+    /// Notified when a snapshot has finished, successfully or not.
+    snapshot_finished_event: Event,
+
+    /// Set when persisting failed. No further snapshots are written after that.
+    persistence_failed: AtomicBool,
+    /// Set when the persisted cache was cleared. Its data is only removed on the next start, so
+    /// nothing is restored from it anymore.
+    cache_cleared: AtomicBool,
 
     stopping: AtomicBool,
     stopping_event: Event,
     idle_start_event: Event,
     idle_end_event: Event,
+    /// Set once the warm-start metrics were reported, which happens when the first idle period
+    /// starts.
+    restore_statistics_reported: AtomicBool,
+    /// Invalidations that wait for the end of the debounce window, see
+    /// [`BackendOptions::invalidation_debounce`].
+    pending_invalidations: Mutex<Vec<TaskId>>,
 
     task_statistics: TaskStatisticsApi,
+    execution_statistics: ExecutionStatistics,
+    invalidation_statistics: InvalidationStatistics,
+    operation_statistics: OperationStatistics,
+
+    /// The span of the invalidation that made a task dirty or stale, so the next execution can
+    /// link to it.
+    #[cfg(feature = "trace_task_dirty")]
+    task_dirty_spans: FxDashMap<TaskId, tracing::Span>,
+
+    /// The number of retries of tasks that failed with the retry policy enabled.
+    task_retries: FxDashMap<TaskId, u32>,
+
+    /// Which executing tasks wait for the output of which tasks, to detect dependency cycles.
+    output_waits: WaitGraph,
+
+    /// The persistent tasks that were accessed in this session, when prefetching is enabled.
+    accessed_tasks: Option<Mutex<FxHashSet<TaskId>>>,
 
     backing_storage: B,
 }
@@ -199,16 +467,207 @@ impl<B: BackingStorage> TurboTasksBackend<B> {
             backing_storage,
         )))
     }
+
+    /// Invalidates all tasks of the given function, including tasks that are only persisted, e.g.
+    /// when their cached results are suspect. The tasks are recomputed when they are read again,
+    /// which invalidates dependent tasks when the result changes.
+    pub fn invalidate_function(
+        &self,
+        fn_type: FunctionId,
+        turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) {
+        self.0
+            .invalidate_tasks_matching(|task_type| task_type.fn_type == fn_type, turbo_tasks);
+    }
+
+    /// Invalidates all tasks whose task type matches the predicate, including tasks that are only
+    /// persisted. The predicate can look at the function and the arguments, e.g. to match tasks
+    /// with a path prefix in the debug output of their arguments after an untracked config file
+    /// changed. The tasks are recomputed when they are read again, which replaces their cached
+    /// results in memory and on disk and invalidates dependent tasks when the result changes.
+    pub fn invalidate_tasks_matching(
+        &self,
+        predicate: impl Fn(&CachedTaskType) -> bool,
+        turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) {
+        self.0.invalidate_tasks_matching(predicate, turbo_tasks);
+    }
+
+    /// Records the state of the external resource behind `key`, e.g. the modification time and
+    /// size of a file when it was read. It's persisted with the next snapshot and compared by
+    /// [`Self::invalidate_changed_keys`] after a restart.
+    pub fn set_invalidation_key_state(&self, key: impl Into<String>, state: Vec<u8>) {
+        self.0.set_invalidation_key_state(key.into(), state);
+    }
+
+    /// Detects changes that happened while the process was not running. `has_changed` is called
+    /// with every key and its persisted state, and the tasks of changed keys are invalidated.
+    /// This should be called on startup, before the cached results are used.
+    pub fn invalidate_changed_keys(
+        &self,
+        has_changed: impl Fn(&str, &[u8]) -> bool,
+        turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) {
+        self.0.invalidate_changed_keys(&has_changed, turbo_tasks);
+    }
+
+    /// Invalidates all tasks registered for `key` with [`turbo_tasks::register_invalidation_key`],
+    /// including tasks that are only persisted. Registrations of persistent tasks are persisted,
+    /// so keys can be invalidated after a restart before the tasks are restored. The
+    /// registrations are consumed, the tasks register the key again when they re-execute.
+    pub fn invalidate_by_key(&self, key: &str, turbo_tasks: &dyn TurboTasksBackendApi<Self>) {
+        self.0.invalidate_by_key(key, turbo_tasks);
+    }
+
+    /// Starts collecting execution statistics per function, see [`Self::execution_statistics`].
+    pub fn enable_execution_statistics(&self) {
+        self.0.execution_statistics.enable();
+    }
+
+    /// Returns the number of executions, the total duration and the maximum memory usage per
+    /// function since the statistics were enabled. The functions with the longest total duration
+    /// come first.
+    pub fn execution_statistics(&self) -> Vec<(&'static str, FunctionExecutionStatistics)> {
+        self.0.execution_statistics.get()
+    }
+
+    /// Starts counting invalidations per task, see [`Self::invalidation_churn`].
+    pub fn enable_invalidation_statistics(&self) {
+        self.0.invalidation_statistics.enable();
+    }
+
+    /// Returns the `limit` tasks that were invalidated most often since the invalidation
+    /// statistics were enabled, e.g. to find a task that a noisy file watcher invalidates over
+    /// and over.
+    pub fn invalidation_churn(&self, limit: usize) -> Vec<TaskInvalidationChurn> {
+        self.0
+            .invalidation_statistics
+            .top(limit, |task_id| self.0.get_task_description(task_id))
+    }
+
+    /// Starts recording latency histograms per operation, see [`Self::operation_statistics`].
+    pub fn enable_operation_statistics(&self) {
+        self.0.operation_statistics.enable();
+    }
+
+    /// Returns the latency histograms of graph operations (e.g. "connect child", "invalidate")
+    /// and of waiting for snapshots since the statistics were enabled. The operations with the
+    /// longest total duration come first.
+    pub fn operation_statistics(&self) -> Vec<(&'static str, LatencyHistogram)> {
+        self.0.operation_statistics.get()
+    }
+
+    /// Returns what was read from the persistent cache since startup, e.g. to tell how much work a
+    /// warm start reused. These numbers are also reported as a tracing event when the backend
+    /// becomes idle for the first time.
+    pub fn restore_statistics(&self) -> RestoreStatistics {
+        self.0.backing_storage.restore_statistics()
+    }
+
+    /// Marks the persisted cache for deletion on the next start in one atomic step, e.g. for a
+    /// "Clear cache and restart" action, instead of deleting the cache directory while it's in
+    /// use. The tasks in memory are kept, since they are still referenced. Nothing is restored
+    /// from or persisted to the cache for the rest of the session, and the next start begins with
+    /// an empty cache.
+    pub fn clear_cache(&self) -> Result<()> {
+        self.0.clear_cache()
+    }
+
+    /// Returns the children of a task, i.e. the tasks it called during its last execution.
+    pub fn task_children(
+        &self,
+        task_id: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) -> Vec<TaskId> {
+        self.0.task_children(task_id, turbo_tasks)
+    }
+
+    /// Returns the tasks that read the output, cells or collectibles of a task and are
+    /// invalidated when they change.
+    pub fn task_dependents(
+        &self,
+        task_id: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) -> Vec<TaskId> {
+        self.0.task_dependents(task_id, turbo_tasks)
+    }
+
+    /// Returns the tasks whose output, cells or collectibles a task read during its last
+    /// execution.
+    pub fn task_dependencies(
+        &self,
+        task_id: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) -> Vec<TaskId> {
+        self.0.task_dependencies(task_id, turbo_tasks)
+    }
+
+    /// Returns the dirty tasks with their descriptions, i.e. tasks that were invalidated and not
+    /// recomputed yet. With a `root`, only dirty tasks in the subgraph of the root are returned,
+    /// otherwise all dirty tasks in memory.
+    pub fn dirty_tasks(
+        &self,
+        root: Option<TaskId>,
+        turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) -> Vec<(TaskId, String)> {
+        self.0.dirty_tasks(root, turbo_tasks)
+    }
+
+    /// Returns true when the cache is not written to the backing storage. This is either the
+    /// case when persisting is disabled by the options, or when persisting failed before.
+    pub fn is_memory_only(&self) -> bool {
+        !self.0.should_persist()
+    }
+
+    /// Waits until the next snapshot has finished, without requesting one. Resolves immediately
+    /// when no snapshots are written anymore, e.g. because persisting failed.
+    pub fn next_snapshot(&self) -> impl Future<Output = ()> {
+        // Listen before checking, so that persisting failing in between still wakes the listener
+        let listener = self.0.snapshot_finished_event.listen();
+        let should_persist = self.0.should_persist();
+        async move {
+            if should_persist {
+                listener.await;
+            }
+        }
+    }
+
+    /// Returns the tasks that were added or changed since the last snapshot, i.e. what the next
+    /// snapshot would write. Useful to find tasks that keep changing while nothing should happen.
+    pub fn pending_snapshot_diff(&self) -> SnapshotDiff {
+        self.0.pending_snapshot_diff()
+    }
+
+    /// Persists all data that was modified before this call and resolves when it's durably
+    /// written. Must be awaited within a turbo-tasks context, since persisting serializes task
+    /// data.
+    pub fn flush(&self) -> impl Future<Output = Result<SnapshotInfo>> {
+        let inner = self.0.clone();
+        async move {
+            if !inner.should_persist() {
+                bail!("Persisting is disabled or failed before");
+            }
+            let info = turbo_tasks::spawn_blocking(move || inner.snapshot()).await;
+            info.context("Persisting failed, the backend continues in memory-only mode")
+        }
+    }
 }
 
 impl<B: BackingStorage> TurboTasksBackendInner<B> {
     pub fn new(mut options: BackendOptions, backing_storage: B) -> Self {
-        let shard_amount =
-            (available_parallelism().map_or(4, |v| v.get()) * 64).next_power_of_two();
+        let shard_amount = options.shard_amount.map_or_else(
+            || (available_parallelism().map_or(4, |v| v.get()) * 64).next_power_of_two(),
+            // DashMap requires at least two shards
+            |shard_amount| shard_amount.max(2).next_power_of_two(),
+        );
         let need_log = matches!(options.storage_mode, Some(StorageMode::ReadWrite));
         if !options.dependency_tracking {
             options.active_tracking = false;
         }
+        let cell_interner = options.cell_interning.then(CellInterner::new);
+        let accessed_tasks = (need_log && options.prefetch_hot_tasks > 0).then(Default::default);
+        let in_flight_operations =
+            (need_log && options.snapshot_stall_threshold.is_some()).then(FxDashMap::default);
         Self {
             options,
             start_time: Instant::now(),
@@ -222,21 +681,42 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                 u32::MAX as u64,
             ),
             persisted_task_cache_log: need_log.then(|| Sharded::new(shard_amount)),
-            task_cache: BiMap::new(),
+            persisted_task_cache_log_len: AtomicUsize::new(0),
+            task_cache: BiMap::with_shard_amount(shard_amount),
             transient_tasks: FxDashMap::default(),
             persisted_storage_data_log: need_log.then(|| PersistedStorageLog::new(shard_amount)),
             persisted_storage_meta_log: need_log.then(|| PersistedStorageLog::new(shard_amount)),
-            storage: Storage::new(),
-            in_progress_operations: AtomicUsize::new(0),
-            snapshot_request: Mutex::new(SnapshotRequest::new()),
-            operations_suspended: Condvar::new(),
-            snapshot_completed: Condvar::new(),
+            invalidation_keys: FxDashMap::default(),
+            task_invalidation_keys: FxDashMap::default(),
+            persisted_invalidation_key_log: need_log.then(Default::default),
+            storage: Storage::new(shard_amount),
+            cell_interner,
+            snapshot_gate: SnapshotGate::new(),
+            in_flight_operations,
+            next_operation_id: AtomicU64::new(0),
             last_snapshot: AtomicU64::new(0),
+            snapshots_started: AtomicU64::new(0),
+            last_snapshot_result: Mutex::new(None),
+            snapshot_finished_event: Event::new(|| {
+                "TurboTasksBackend::snapshot_finished_event".to_string()
+            }),
+            persistence_failed: AtomicBool::new(false),
+            cache_cleared: AtomicBool::new(false),
             stopping: AtomicBool::new(false),
             stopping_event: Event::new(|| "TurboTasksBackend::stopping_event".to_string()),
             idle_start_event: Event::new(|| "TurboTasksBackend::idle_start_event".to_string()),
             idle_end_event: Event::new(|| "TurboTasksBackend::idle_end_event".to_string()),
+            restore_statistics_reported: AtomicBool::new(false),
+            pending_invalidations: Mutex::new(Vec::new()),
             task_statistics: TaskStatisticsApi::default(),
+            execution_statistics: ExecutionStatistics::default(),
+            invalidation_statistics: InvalidationStatistics::default(),
+            operation_statistics: OperationStatistics::default(),
+            #[cfg(feature = "trace_task_dirty")]
+            task_dirty_spans: FxDashMap::default(),
+            task_retries: FxDashMap::default(),
+            output_waits: WaitGraph::default(),
+            accessed_tasks,
             backing_storage,
         }
     }
@@ -269,64 +749,73 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
     }
 
     fn suspending_requested(&self) -> bool {
-        self.should_persist()
-            && (self.in_progress_operations.load(Ordering::Relaxed) & SNAPSHOT_REQUESTED_BIT) != 0
+        self.should_persist() && self.snapshot_gate.snapshot_requested()
     }
 
-    fn operation_suspend_point(&self, suspend: impl FnOnce() -> AnyOperation) {
+    /// `in_flight_id` is the id of the operation in
+    /// [`TurboTasksBackendInner::in_flight_operations`], if it's tracked.
+    fn operation_suspend_point(
+        &self,
+        in_flight_id: Option<u64>,
+        suspend: impl FnOnce() -> AnyOperation,
+    ) {
         #[cold]
         fn operation_suspend_point_cold<B: BackingStorage>(
             this: &TurboTasksBackendInner<B>,
+            in_flight_id: Option<u64>,
             suspend: impl FnOnce() -> AnyOperation,
         ) {
             let operation = Arc::new(suspend());
-            let mut snapshot_request = this.snapshot_request.lock();
-            if snapshot_request.snapshot_requested {
-                snapshot_request
-                    .suspended_operations
-                    .insert(operation.clone().into());
-                let value = this.in_progress_operations.fetch_sub(1, Ordering::AcqRel) - 1;
-                assert!((value & SNAPSHOT_REQUESTED_BIT) != 0);
-                if value == SNAPSHOT_REQUESTED_BIT {
-                    this.operations_suspended.notify_all();
-                }
-                this.snapshot_completed
-                    .wait_while(&mut snapshot_request, |snapshot_request| {
-                        snapshot_request.snapshot_requested
-                    });
-                this.in_progress_operations.fetch_add(1, Ordering::AcqRel);
-                snapshot_request
-                    .suspended_operations
-                    .remove(&operation.into());
-            }
+            this.set_operation_suspended(in_flight_id, true);
+            this.operation_statistics.suspend(|| {
+                block_for_snapshot(|| this.snapshot_gate.suspend_operation(operation.into()))
+            });
+            this.set_operation_suspended(in_flight_id, false);
         }
 
         if self.suspending_requested() {
-            operation_suspend_point_cold(self, suspend);
+            operation_suspend_point_cold(self, in_flight_id, suspend);
+        }
+    }
+
+    fn set_operation_suspended(&self, in_flight_id: Option<u64>, suspended: bool) {
+        if let (Some(id), Some(in_flight_operations)) = (in_flight_id, &self.in_flight_operations) {
+            if let Some(mut operation) = in_flight_operations.get_mut(&id) {
+                operation.suspended = suspended;
+            }
         }
     }
 
     pub(crate) fn start_operation(&self) -> OperationGuard<'_, B> {
         if !self.should_persist() {
-            return OperationGuard { backend: None };
-        }
-        let fetch_add = self.in_progress_operations.fetch_add(1, Ordering::AcqRel);
-        if (fetch_add & SNAPSHOT_REQUESTED_BIT) != 0 {
-            let mut snapshot_request = self.snapshot_request.lock();
-            if snapshot_request.snapshot_requested {
-                let value = self.in_progress_operations.fetch_sub(1, Ordering::AcqRel) - 1;
-                if value == SNAPSHOT_REQUESTED_BIT {
-                    self.operations_suspended.notify_all();
-                }
-                self.snapshot_completed
-                    .wait_while(&mut snapshot_request, |snapshot_request| {
-                        snapshot_request.snapshot_requested
-                    });
-                self.in_progress_operations.fetch_add(1, Ordering::AcqRel);
-            }
+            return OperationGuard {
+                backend: None,
+                in_flight_id: None,
+            };
+        }
+        if self.snapshot_gate.snapshot_requested() {
+            let _timer = self.operation_statistics.timer("blocked by snapshot");
+            block_for_snapshot(|| self.snapshot_gate.start_operation());
+        } else {
+            self.snapshot_gate.start_operation();
         }
+        let in_flight_id = self
+            .in_flight_operations
+            .as_ref()
+            .map(|in_flight_operations| {
+                let id = self.next_operation_id.fetch_add(1, Ordering::Relaxed);
+                in_flight_operations.insert(
+                    id,
+                    InFlightOperation {
+                        span: tracing::Span::current(),
+                        suspended: false,
+                    },
+                );
+                id
+            });
         OperationGuard {
             backend: Some(self),
+            in_flight_id,
         }
     }
 
@@ -339,12 +828,108 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         .as_ref()
     }
 
+    /// The log of new task cache entries, or `None` when nothing is persisted anymore. The logs
+    /// are only drained by snapshots, so they must not grow after persisting stopped.
+    fn task_cache_log_to_persist(&self) -> Option<&TaskCacheLog> {
+        self.persisted_task_cache_log
+            .as_ref()
+            .filter(|_| self.should_persist())
+    }
+
+    /// The log of invalidation key changes, or `None` when nothing is persisted anymore.
+    fn invalidation_key_log_to_persist(&self) -> Option<&Mutex<InvalidationKeyUpdates>> {
+        self.persisted_invalidation_key_log
+            .as_ref()
+            .filter(|_| self.should_persist())
+    }
+
+    /// The number of updates that the next snapshot would persist. Not a consistent count while
+    /// operations are in progress.
+    fn pending_persisted_updates(&self) -> usize {
+        let task_cache_entries = self.persisted_task_cache_log_len.load(Ordering::Relaxed);
+        let task_data_updates = [
+            &self.persisted_storage_meta_log,
+            &self.persisted_storage_data_log,
+        ]
+        .into_iter()
+        .flatten()
+        .map(|log| log.len())
+        .sum::<usize>();
+        let invalidation_key_updates = self
+            .persisted_invalidation_key_log
+            .as_ref()
+            .map_or(0, |log| log.lock().len());
+        task_cache_entries + task_data_updates + invalidation_key_updates
+    }
+
     fn should_persist(&self) -> bool {
         matches!(self.options.storage_mode, Some(StorageMode::ReadWrite))
+            && !self.persistence_failed.load(Ordering::Relaxed)
+    }
+
+    /// Switches to memory-only mode after persisting failed. The data written since the last
+    /// successful snapshot is lost, so later snapshots would leave the storage in an
+    /// inconsistent state.
+    fn disable_persistence(&self, err: &anyhow::Error) {
+        self.stop_persisting();
+        if let Some(event_listener) = &self.options.event_listener {
+            event_listener.persistence_error(err);
+        }
+    }
+
+    /// Switches to memory-only mode and drops the updates that weren't persisted yet.
+    fn stop_persisting(&self) {
+        self.persistence_failed.store(true, Ordering::Relaxed);
+        // There won't be a next snapshot anymore
+        self.snapshot_finished_event.notify(usize::MAX);
+        drop(self.take_task_cache_log());
+        for log in [
+            &self.persisted_storage_meta_log,
+            &self.persisted_storage_data_log,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            drop(log.take());
+        }
+        if let Some(log) = &self.persisted_invalidation_key_log {
+            drop(take(&mut *log.lock()));
+        }
+    }
+
+    fn clear_cache(&self) -> Result<()> {
+        // Snapshots must not write while or after the data is removed
+        let _last_snapshot_result = self.last_snapshot_result.lock();
+        self.backing_storage.clear()?;
+        self.cache_cleared.store(true, Ordering::Relaxed);
+        self.stop_persisting();
+        Ok(())
+    }
+
+    /// Returns an id for a new persistent task.
+    ///
+    /// Before the persistent id space runs out, persisting is disabled and reported as
+    /// [`TaskIdSpaceExhausted`], so the reserved ids last for the rest of the session. Persistent
+    /// tasks never get transient ids, since e.g. the check that persistent tasks don't call
+    /// transient functions relies on the id.
+    fn new_persisted_task_id(&self) -> TaskId {
+        let remaining = self.persisted_task_id_factory.remaining();
+        if remaining < PERSISTED_TASK_ID_RESERVE && self.should_persist() {
+            self.disable_persistence(&anyhow::Error::new(TaskIdSpaceExhausted { remaining }));
+        }
+        self.persisted_task_id_factory
+            .try_get()
+            .unwrap_or_else(|| panic!("{}", TaskIdSpaceExhausted { remaining: 0 }))
+    }
+
+    fn emit_task_cache_lookup(&self, task_type: &CachedTaskType, result: TaskCacheLookup) {
+        if let Some(event_listener) = &self.options.event_listener {
+            event_listener.task_cache_lookup(task_type, result);
+        }
     }
 
     fn should_restore(&self) -> bool {
-        self.options.storage_mode.is_some()
+        self.options.storage_mode.is_some() && !self.cache_cleared.load(Ordering::Relaxed)
     }
 
     fn should_track_dependencies(&self) -> bool {
@@ -359,6 +944,14 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         self.options.children_tracking
     }
 
+    fn should_check_cell_equality(&self) -> bool {
+        self.options.cell_equality_check
+    }
+
+    fn aggregation_options(&self) -> AggregationOptions {
+        self.options.aggregation
+    }
+
     fn track_cache_hit(&self, task_type: &CachedTaskType) {
         self.task_statistics
             .map(|stats| stats.increment_cache_hit(task_type.fn_type));
@@ -370,19 +963,29 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
     }
 }
 
+/// An operation in progress, for the snapshot stall report.
+struct InFlightOperation {
+    /// The span the operation was started in.
+    span: tracing::Span,
+    /// The operation is suspended and doesn't keep the snapshot waiting.
+    suspended: bool,
+}
+
 pub(crate) struct OperationGuard<'a, B: BackingStorage> {
     backend: Option<&'a TurboTasksBackendInner<B>>,
+    /// The key in [`TurboTasksBackendInner::in_flight_operations`].
+    in_flight_id: Option<u64>,
 }
 
 impl<B: BackingStorage> Drop for OperationGuard<'_, B> {
     fn drop(&mut self) {
         if let Some(backend) = self.backend {
-            let fetch_sub = backend
-                .in_progress_operations
-                .fetch_sub(1, Ordering::AcqRel);
-            if fetch_sub - 1 == SNAPSHOT_REQUESTED_BIT {
-                backend.operations_suspended.notify_all();
+            if let (Some(id), Some(in_flight_operations)) =
+                (self.in_flight_id, &backend.in_flight_operations)
+            {
+                in_flight_operations.remove(&id);
             }
+            backend.snapshot_gate.finish_operation();
         }
     }
 }
@@ -546,6 +1149,8 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         }
 
         if let Some(value) = check_in_progress(self, &task, reader) {
+            drop(task);
+            self.check_output_wait_cycle(reader, task_id)?;
             return value;
         }
 
@@ -554,10 +1159,14 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                 OutputValue::Cell(cell) => Some(Ok(Ok(RawVc::TaskCell(cell.task, cell.cell)))),
                 OutputValue::Output(task) => Some(Ok(Ok(RawVc::TaskOutput(*task)))),
                 OutputValue::Error | OutputValue::Panic => {
-                    get!(task, Error).map(|error| Err(error.clone().into()))
+                    get!(task, Error).map(|error| Err(anyhow::Error::new(TaskError::clone(error))))
                 }
             };
             if let Some(result) = result {
+                if let Some(reader) = reader {
+                    // A previous read might have waited for the output
+                    self.output_waits.remove_wait(reader, task_id);
+                }
                 if self.should_track_dependencies() {
                     if let Some(reader) = reader {
                         let _ = task.add(CachedDataItem::OutputDependent {
@@ -599,10 +1208,30 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
             CachedDataItem::new_scheduled_with_listener(self.get_task_desc_fn(task_id), note);
         task.add_new(item);
         turbo_tasks.schedule(task_id);
+        drop(task);
+        self.check_output_wait_cycle(reader, task_id)?;
 
         Ok(Err(listener))
     }
 
+    /// Records that `reader` waits for the output of `task_id` and fails when the reader
+    /// transitively waits for its own output, which would never resolve.
+    fn check_output_wait_cycle(&self, reader: Option<TaskId>, task_id: TaskId) -> Result<()> {
+        let Some(reader) = reader else {
+            return Ok(());
+        };
+        let Some(cycle) = self.output_waits.add_wait(reader, task_id) else {
+            return Ok(());
+        };
+        let mut message =
+            "Dependency cycle detected, the task transitively awaits its own output:".to_string();
+        for (i, task) in cycle.into_iter().enumerate() {
+            let prefix = if i == 0 { "" } else { "awaits " };
+            message.push_str(&format!("\n  {prefix}{}", self.get_task_description(task)));
+        }
+        bail!(message)
+    }
+
     fn try_read_task_cell(
         &self,
         task_id: TaskId,
@@ -786,48 +1415,144 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         let task_type = self.lookup_task_type(task_id);
         move || {
             task_type.as_ref().map_or_else(
-                || format!("{task_id:?} transient"),
+                || describe_unknown_task(task_id),
                 |task_type| format!("{task_id:?} {task_type}"),
             )
         }
     }
 
-    fn snapshot(&self) -> Option<(Instant, bool)> {
-        debug_assert!(self.should_persist());
-        let mut snapshot_request = self.snapshot_request.lock();
-        snapshot_request.snapshot_requested = true;
-        let active_operations = self
-            .in_progress_operations
-            .fetch_or(SNAPSHOT_REQUESTED_BIT, Ordering::Relaxed);
-        if active_operations != 0 {
-            self.operations_suspended
-                .wait_while(&mut snapshot_request, |_| {
-                    self.in_progress_operations.load(Ordering::Relaxed) != SNAPSHOT_REQUESTED_BIT
-                });
+    /// Persists the data that was modified before this call. Requests that arrive while a
+    /// snapshot is running are coalesced: they wait for it and share a single follow-up snapshot,
+    /// instead of suspending the operations once per request.
+    fn snapshot(&self) -> Option<SnapshotInfo> {
+        let started = self.snapshots_started.load(Ordering::Acquire);
+        let mut last_snapshot_result = self.last_snapshot_result.lock();
+        if !self.should_persist() {
+            return None;
         }
-        let suspended_operations = snapshot_request
-            .suspended_operations
-            .iter()
+        if self.snapshots_started.load(Ordering::Acquire) != started {
+            // Another snapshot started capturing after this request and has finished, since no
+            // snapshot is running while we hold the lock
+            return *last_snapshot_result;
+        }
+        let result = self.take_snapshot();
+        *last_snapshot_result = result;
+        drop(last_snapshot_result);
+        self.snapshot_finished_event.notify(usize::MAX);
+        result
+    }
+
+    fn should_flush_task_cache_log(&self) -> bool {
+        self.persisted_task_cache_log.is_some()
+            && self.backing_storage.can_save_task_cache()
+            && self.persisted_task_cache_log_len.load(Ordering::Relaxed)
+                >= self.options.task_cache_flush_threshold
+    }
+
+    /// Takes the logged task cache entries.
+    fn take_task_cache_log(&self) -> Vec<ChunkedVec<(Arc<CachedTaskType>, TaskId)>> {
+        let Some(log) = &self.persisted_task_cache_log else {
+            return Vec::new();
+        };
+        let entries = log.take(|i| i);
+        let len = entries.iter().map(|shard| shard.len()).sum();
+        self.persisted_task_cache_log_len
+            .fetch_sub(len, Ordering::Relaxed);
+        entries
+    }
+
+    /// Persists the logged task cache entries ahead of the next snapshot. Task cache entries are
+    /// only appended and tasks without persisted data are recomputed, so they can be persisted
+    /// before the data of their tasks.
+    fn flush_task_cache_log(&self) {
+        if self.persisted_task_cache_log.is_none() {
+            return;
+        }
+        // Snapshots update the next free task id as well, so they must not run concurrently
+        let _last_snapshot_result = self.last_snapshot_result.lock();
+        if !self.should_persist() {
+            return;
+        }
+        let task_cache_updates = self.take_task_cache_log();
+        if let Err(err) = self.backing_storage.save_task_cache(task_cache_updates) {
+            println!("Persisting failed: {:?}", err);
+            self.disable_persistence(&err);
+        }
+    }
+
+    /// Logs the operations that keep a snapshot waiting. An operation that never reaches a
+    /// suspend point blocks all other operations until it completes.
+    #[cold]
+    fn report_snapshot_stall(&self, in_progress: usize, waiting: Duration) {
+        println!(
+            "Snapshot is waiting for {in_progress} operations to complete or suspend for {:.1}s",
+            waiting.as_secs_f64()
+        );
+        let Some(in_flight_operations) = &self.in_flight_operations else {
+            return;
+        };
+        for entry in in_flight_operations.iter() {
+            let (id, operation) = entry.pair();
+            if operation.suspended {
+                continue;
+            }
+            match operation.span.metadata() {
+                Some(metadata) => println!(
+                    "  operation {id} started in span {} ({}:{})",
+                    metadata.name(),
+                    metadata.file().unwrap_or("unknown"),
+                    metadata.line().unwrap_or(0)
+                ),
+                None => println!("  operation {id} started outside of a span"),
+            }
+        }
+    }
+
+    fn take_snapshot(&self) -> Option<SnapshotInfo> {
+        debug_assert!(self.should_persist());
+        let span = tracing::info_span!(
+            "snapshot",
+            task_cache_entries = tracing::field::Empty,
+            task_data_updates = tracing::field::Empty
+        )
+        .entered();
+        let start = Instant::now();
+        if let Some(event_listener) = &self.options.event_listener {
+            event_listener.snapshot_started();
+        }
+        // Operations are blocked from the request until the snapshot is finished
+        let pause_span = tracing::info_span!(
+            "snapshot pause",
+            suspended_operations = tracing::field::Empty
+        )
+        .entered();
+        let suspended_operations = self
+            .snapshot_gate
+            .request_snapshot(
+                || {
+                    self.snapshots_started.fetch_add(1, Ordering::AcqRel);
+                },
+                self.options.snapshot_stall_threshold,
+                |in_progress, waiting| self.report_snapshot_stall(in_progress, waiting),
+            )
+            .into_iter()
             .map(|op| op.arc().clone())
             .collect::<Vec<_>>();
-        drop(snapshot_request);
         fn take_from_log(log: &Option<PersistedStorageLog>) -> Vec<ChunkedVec<CachedDataUpdate>> {
             log.as_ref().map(|l| l.take()).unwrap_or_default()
         }
         let persisted_storage_meta_log = take_from_log(&self.persisted_storage_meta_log);
         let persisted_storage_data_log = take_from_log(&self.persisted_storage_data_log);
-        let persisted_task_cache_log = self
-            .persisted_task_cache_log
+        let persisted_task_cache_log = self.take_task_cache_log();
+        let persisted_invalidation_key_log = self
+            .persisted_invalidation_key_log
             .as_ref()
-            .map(|l| l.take(|i| i))
+            .map(|l| take(&mut *l.lock()))
             .unwrap_or_default();
-        let mut snapshot_request = self.snapshot_request.lock();
-        snapshot_request.snapshot_requested = false;
-        self.in_progress_operations
-            .fetch_sub(SNAPSHOT_REQUESTED_BIT, Ordering::Relaxed);
-        self.snapshot_completed.notify_all();
         let snapshot_time = Instant::now();
-        drop(snapshot_request);
+        self.snapshot_gate.finish_snapshot();
+        pause_span.record("suspended_operations", suspended_operations.len());
+        drop(pause_span);
 
         // TODO track which items are persisting
         // TODO This is very inefficient, maybe the BackingStorage could compute that since it need
@@ -842,25 +1567,49 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         //     }
         // }
 
-        let mut new_items = false;
-
-        fn shards_empty<T>(shards: &[ChunkedVec<T>]) -> bool {
-            shards.iter().all(|shard| shard.is_empty())
+        fn shards_len<T>(shards: &[ChunkedVec<T>]) -> usize {
+            shards.iter().map(|shard| shard.len()).sum()
         }
 
-        if !shards_empty(&persisted_task_cache_log)
-            || !shards_empty(&persisted_storage_meta_log)
-            || !shards_empty(&persisted_storage_data_log)
-        {
-            new_items = true;
+        let mut info = SnapshotInfo {
+            captured_at: snapshot_time,
+            duration: Duration::ZERO,
+            task_cache_entries: shards_len(&persisted_task_cache_log),
+            task_data_updates: shards_len(&persisted_storage_meta_log)
+                + shards_len(&persisted_storage_data_log),
+            invalidation_key_updates: persisted_invalidation_key_log.tasks.len()
+                + persisted_invalidation_key_log.states.len(),
+        };
+
+        span.record("task_cache_entries", info.task_cache_entries);
+        span.record("task_data_updates", info.task_data_updates);
+
+        if info.has_new_data() {
+            let total = info.task_cache_entries + info.task_data_updates;
+            let processed = AtomicUsize::new(0);
+            let bytes_written = AtomicU64::new(0);
+            let progress = |items: usize, bytes: u64| {
+                let processed = processed.fetch_add(items, Ordering::Relaxed) + items;
+                let bytes_written = bytes_written.fetch_add(bytes, Ordering::Relaxed) + bytes;
+                if let Some(event_listener) = &self.options.event_listener {
+                    event_listener.snapshot_progress(SnapshotProgress {
+                        processed,
+                        total,
+                        bytes_written,
+                    });
+                }
+            };
             if let Err(err) = self.backing_storage.save_snapshot(
                 self.session_id,
                 suspended_operations,
                 persisted_task_cache_log,
                 persisted_storage_meta_log,
                 persisted_storage_data_log,
+                persisted_invalidation_key_log,
+                &progress,
             ) {
                 println!("Persisting failed: {:?}", err);
+                self.disable_persistence(&err);
                 return None;
             }
         }
@@ -873,7 +1622,45 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         //         .finish_persisting_items(count);
         // }
 
-        Some((snapshot_time, new_items))
+        info.duration = start.elapsed();
+        if let Some(event_listener) = &self.options.event_listener {
+            event_listener.snapshot_finished(info.duration, info.has_new_data());
+        }
+
+        Some(info)
+    }
+
+    /// Records that a persistent task was accessed in this session, which makes it a candidate for
+    /// prefetching in the next session.
+    pub(crate) fn record_task_access(&self, task_id: TaskId) {
+        if let Some(accessed_tasks) = &self.accessed_tasks {
+            accessed_tasks.lock().insert(task_id);
+        }
+    }
+
+    /// Restores the data of a persistent task ahead of its first access.
+    fn prefetch_task_data(&self, task_id: TaskId) {
+        for category in TaskDataCategory::All {
+            if self
+                .storage
+                .access_mut(task_id)
+                .persistance_state()
+                .is_restored(category)
+            {
+                continue;
+            }
+            // Safety: No transaction is passed.
+            let items = unsafe { self.backing_storage.lookup_data(None, task_id, category) };
+            let mut task = self.storage.access_mut(task_id);
+            if !task.persistance_state().is_restored(category) {
+                for item in items {
+                    task.add(item);
+                }
+                let persistance_state = task.persistance_state_mut();
+                persistance_state.set_restored(category);
+                persistance_state.set_prefetched();
+            }
+        }
     }
 
     fn startup(&self, turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>) {
@@ -890,9 +1677,20 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
             }
         }
 
+        if self.should_restore() && self.options.prefetch_hot_tasks > 0 {
+            turbo_tasks.schedule_backend_background_job(BACKEND_JOB_PREFETCH);
+        }
+
         if self.should_persist() {
             // Schedule the snapshot job
             turbo_tasks.schedule_backend_background_job(BACKEND_JOB_INITIAL_SNAPSHOT);
+            // Schedule the compaction job
+            turbo_tasks.schedule_backend_background_job(BACKEND_JOB_COMPACTION);
+            // Check for data that a crash left behind once the initial work is done
+            turbo_tasks.schedule_backend_background_job(BACKEND_JOB_REMOVE_ORPHANED_DATA);
+            if self.options.consistency_check_interval.is_some() {
+                turbo_tasks.schedule_backend_background_job(BACKEND_JOB_CONSISTENCY_CHECK);
+            }
         }
     }
 
@@ -902,6 +1700,23 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
     }
 
     fn stop(&self) {
+        // Persist the changes since the last snapshot. All background jobs have finished at this
+        // point, so this doesn't race with the snapshot job.
+        if self.should_persist() {
+            let _span = tracing::trace_span!("final snapshot").entered();
+            self.snapshot();
+        }
+        if let Some(accessed_tasks) = &self.accessed_tasks {
+            let accessed_tasks = take(&mut *accessed_tasks.lock()).into_iter().collect();
+            // Keep more tasks than are prefetched, so tasks that become hot can catch up
+            let limit = self.options.prefetch_hot_tasks.saturating_mul(2);
+            if let Err(err) = self
+                .backing_storage
+                .save_task_accesses(accessed_tasks, limit)
+            {
+                println!("Saving task accesses failed: {err:?}");
+            }
+        }
         if let Err(err) = self.backing_storage.shutdown() {
             println!("Shutting down failed: {}", err);
         }
@@ -909,6 +1724,25 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
 
     fn idle_start(&self) {
         self.idle_start_event.notify(usize::MAX);
+        if self.should_restore()
+            && !self
+                .restore_statistics_reported
+                .swap(true, Ordering::Relaxed)
+        {
+            // The initial work is done, so this is what the warm start restored
+            let statistics = self.backing_storage.restore_statistics();
+            tracing::info!(
+                restored_task_data = statistics.restored_task_data,
+                restored_task_cache_entries = statistics.restored_task_cache_entries,
+                bytes_read = statistics.bytes_read,
+                duration_ms = statistics.duration.as_millis() as u64,
+                discarded = statistics.discarded,
+                discarded_cells = statistics.discarded_cells,
+                "restored from persistent cache"
+            );
+        }
+        #[cfg(feature = "verify_graph")]
+        self.verify_graph();
     }
 
     fn idle_end(&self) {
@@ -923,11 +1757,11 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
     ) -> TaskId {
         if let Some(task_id) = self.task_cache.lookup_forward(&task_type) {
             self.track_cache_hit(&task_type);
+            self.emit_task_cache_lookup(&task_type, TaskCacheLookup::Memory);
             self.connect_child(parent_task, task_id, turbo_tasks);
             return task_id;
         }
 
-        self.track_cache_miss(&task_type);
         let tx = self
             .should_restore()
             .then(|| self.backing_storage.start_read_transaction())
@@ -938,11 +1772,15 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                 self.backing_storage
                     .forward_lookup_task_cache(tx.as_ref(), &task_type)
             } {
+                self.track_cache_hit(&task_type);
+                self.emit_task_cache_lookup(&task_type, TaskCacheLookup::PersistentCache);
                 let _ = self.task_cache.try_insert(Arc::new(task_type), task_id);
                 task_id
             } else {
+                self.track_cache_miss(&task_type);
+                self.emit_task_cache_lookup(&task_type, TaskCacheLookup::Miss);
                 let task_type = Arc::new(task_type);
-                let task_id = self.persisted_task_id_factory.get();
+                let task_id = self.new_persisted_task_id();
                 let task_id = if let Err(existing_task_id) =
                     self.task_cache.try_insert(task_type.clone(), task_id)
                 {
@@ -954,8 +1792,13 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                 } else {
                     task_id
                 };
-                if let Some(log) = &self.persisted_task_cache_log {
-                    log.lock(task_id).push((task_type, task_id));
+                if let Some(log) = self.task_cache_log_to_persist() {
+                    let mut shard = log.lock(task_id);
+                    shard.push((task_type, task_id));
+                    // Counted while the shard is locked, so taking the log never sees an entry
+                    // that isn't counted yet
+                    self.persisted_task_cache_log_len
+                        .fetch_add(1, Ordering::Relaxed);
                 }
                 task_id
             }
@@ -983,11 +1826,13 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         }
         if let Some(task_id) = self.task_cache.lookup_forward(&task_type) {
             self.track_cache_hit(&task_type);
+            self.emit_task_cache_lookup(&task_type, TaskCacheLookup::Memory);
             self.connect_child(parent_task, task_id, turbo_tasks);
             return task_id;
         }
 
         self.track_cache_miss(&task_type);
+        self.emit_task_cache_lookup(&task_type, TaskCacheLookup::Miss);
         let task_type = Arc::new(task_type);
         let task_id = self.transient_task_id_factory.get();
         if let Err(existing_task_id) = self.task_cache.try_insert(task_type, task_id) {
@@ -998,56 +1843,315 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
             self.connect_child(parent_task, existing_task_id, turbo_tasks);
             return existing_task_id;
         }
-
-        self.connect_child(parent_task, task_id, turbo_tasks);
-
-        task_id
+
+        self.connect_child(parent_task, task_id, turbo_tasks);
+
+        task_id
+    }
+
+    fn invalidate_task(
+        &self,
+        task_id: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
+    ) {
+        if !self.should_track_dependencies() {
+            panic!("Dependency tracking is disabled so invalidation is not allowed");
+        }
+        if self.options.invalidation_debounce.is_some() {
+            let mut pending_invalidations = self.pending_invalidations.lock();
+            let first = pending_invalidations.is_empty();
+            pending_invalidations.push(task_id);
+            drop(pending_invalidations);
+            if first {
+                // A foreground job, so turbo-tasks doesn't become idle before the invalidations
+                // are applied
+                turbo_tasks.schedule_backend_foreground_job(BACKEND_JOB_FLUSH_INVALIDATIONS);
+            }
+            return;
+        }
+        operation::InvalidateOperation::run(
+            smallvec![task_id],
+            #[cfg(feature = "trace_task_dirty")]
+            TaskDirtyCause::Invalidator,
+            self.execute_context(turbo_tasks),
+        );
+    }
+
+    fn invalidate_tasks(
+        &self,
+        tasks: &[TaskId],
+        turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
+    ) {
+        if !self.should_track_dependencies() {
+            panic!("Dependency tracking is disabled so invalidation is not allowed");
+        }
+        operation::InvalidateOperation::run(
+            tasks.iter().copied().collect(),
+            #[cfg(feature = "trace_task_dirty")]
+            TaskDirtyCause::Unknown,
+            self.execute_context(turbo_tasks),
+        );
+    }
+
+    fn invalidate_tasks_set(
+        &self,
+        tasks: &AutoSet<TaskId, BuildHasherDefault<FxHasher>, 2>,
+        turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
+    ) {
+        if !self.should_track_dependencies() {
+            panic!("Dependency tracking is disabled so invalidation is not allowed");
+        }
+        operation::InvalidateOperation::run(
+            tasks.iter().copied().collect(),
+            #[cfg(feature = "trace_task_dirty")]
+            TaskDirtyCause::Unknown,
+            self.execute_context(turbo_tasks),
+        );
+    }
+
+    fn invalidate_tasks_matching(
+        &self,
+        predicate: impl Fn(&CachedTaskType) -> bool,
+        turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
+    ) {
+        if !self.should_track_dependencies() {
+            panic!("Dependency tracking is disabled so invalidation is not allowed");
+        }
+        let mut tasks = self
+            .task_cache
+            .iter()
+            .filter(|entry| predicate(entry.key()))
+            .map(|entry| *entry.value())
+            .collect::<FxHashSet<_>>();
+        if self.should_restore() {
+            tasks.extend(self.backing_storage.persisted_tasks_matching(&predicate));
+        }
+        operation::InvalidateOperation::run(
+            tasks.into_iter().collect(),
+            #[cfg(feature = "trace_task_dirty")]
+            TaskDirtyCause::Invalidator,
+            self.execute_context(turbo_tasks),
+        );
+    }
+
+    fn task_children(
+        &self,
+        task_id: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
+    ) -> Vec<TaskId> {
+        let mut ctx = self.execute_context(turbo_tasks);
+        let task = ctx.task(task_id, TaskDataCategory::All);
+        get_many!(task, Child { task } => task)
+    }
+
+    fn task_dependents(
+        &self,
+        task_id: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
+    ) -> Vec<TaskId> {
+        let mut ctx = self.execute_context(turbo_tasks);
+        let task = ctx.task(task_id, TaskDataCategory::All);
+        let dependents: FxHashSet<TaskId> = iter_many!(task, OutputDependent { task } => task)
+            .chain(iter_many!(task, CellDependent { task, cell: _ } => task))
+            .chain(iter_many!(task, CollectiblesDependent { task, collectible_type: _ } => task))
+            .collect();
+        dependents.into_iter().collect()
+    }
+
+    fn task_dependencies(
+        &self,
+        task_id: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
+    ) -> Vec<TaskId> {
+        let mut ctx = self.execute_context(turbo_tasks);
+        let task = ctx.task(task_id, TaskDataCategory::All);
+        let dependencies: FxHashSet<TaskId> =
+            iter_many!(task, OutputDependency { target } => target)
+                .chain(iter_many!(task, CellDependency { target } => target.task))
+                .chain(iter_many!(task, CollectiblesDependency { target } => target.task))
+                .collect();
+        dependencies.into_iter().collect()
+    }
+
+    fn dirty_tasks(
+        &self,
+        root: Option<TaskId>,
+        turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
+    ) -> Vec<(TaskId, String)> {
+        let session_id = self.session_id;
+        let mut dirty_tasks = Vec::new();
+        if let Some(root) = root {
+            // Aggregating nodes know which of the tasks they aggregate are dirty or contain dirty
+            // tasks, so only the dirty parts of the subgraph are visited
+            let mut ctx = self.execute_context(turbo_tasks);
+            let mut visited = FxHashSet::default();
+            let mut queue = vec![root];
+            visited.insert(root);
+            while let Some(task_id) = queue.pop() {
+                let task = ctx.task(task_id, TaskDataCategory::All);
+                if get!(task, Dirty).is_some_and(|dirty| dirty.get(session_id)) {
+                    dirty_tasks.push(task_id);
+                }
+                let next = if is_aggregating_node(get_aggregation_number(&task)) {
+                    get_many!(
+                        task,
+                        AggregatedDirtyContainer { task } count if count.get(session_id) > 0 => task
+                    )
+                } else {
+                    get_many!(task, Child { task } => task)
+                };
+                queue.extend(next.into_iter().filter(|&task_id| visited.insert(task_id)));
+            }
+        } else {
+            self.storage.for_each(|task_id, task| {
+                if get!(task, Dirty).is_some_and(|dirty| dirty.get(session_id)) {
+                    dirty_tasks.push(task_id);
+                }
+            });
+        }
+        dirty_tasks.sort_unstable();
+        dirty_tasks
+            .into_iter()
+            .map(|task_id| (task_id, self.get_task_description(task_id)))
+            .collect()
+    }
+
+    fn register_invalidation_key(&self, task_id: TaskId, key: String) {
+        let is_new = self
+            .invalidation_keys
+            .entry(key.clone())
+            .or_default()
+            .insert(task_id);
+        if !is_new {
+            return;
+        }
+        self.task_invalidation_keys
+            .entry(task_id)
+            .or_default()
+            .insert(key.clone());
+        if !task_id.is_transient() {
+            if let Some(log) = self.invalidation_key_log_to_persist() {
+                log.lock().tasks.insert((key, task_id), true);
+            }
+        }
+    }
+
+    /// Drops the invalidation key registrations of a task before it executes. The execution
+    /// registers the keys it still depends on again.
+    fn clear_invalidation_keys(&self, task_id: TaskId) {
+        let Some((_, keys)) = self.task_invalidation_keys.remove(&task_id) else {
+            return;
+        };
+        for key in &keys {
+            if let dashmap::mapref::entry::Entry::Occupied(mut entry) =
+                self.invalidation_keys.entry(key.clone())
+            {
+                entry.get_mut().remove(&task_id);
+                if entry.get().is_empty() {
+                    entry.remove();
+                }
+            }
+        }
+        if !task_id.is_transient() {
+            if let Some(log) = self.invalidation_key_log_to_persist() {
+                let mut log = log.lock();
+                for key in keys {
+                    log.tasks.insert((key, task_id), false);
+                }
+            }
+        }
+    }
+
+    /// Removes all registrations of `key`, in memory and persisted, and returns the registered
+    /// tasks.
+    fn take_invalidation_key(&self, key: &str) -> FxHashSet<TaskId> {
+        let mut tasks = self
+            .invalidation_keys
+            .remove(key)
+            .map(|(_, tasks)| tasks)
+            .unwrap_or_default();
+        for task_id in &tasks {
+            if let Some(mut keys) = self.task_invalidation_keys.get_mut(task_id) {
+                keys.remove(key);
+            }
+        }
+        if self.should_restore() {
+            tasks.extend(self.backing_storage.lookup_invalidation_key(key));
+        }
+        if let Some(log) = self.invalidation_key_log_to_persist() {
+            let mut log = log.lock();
+            for &task_id in &tasks {
+                if !task_id.is_transient() {
+                    log.tasks.insert((key.to_string(), task_id), false);
+                }
+            }
+        }
+        tasks
     }
 
-    fn invalidate_task(
-        &self,
-        task_id: TaskId,
-        turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
-    ) {
-        if !self.should_track_dependencies() {
-            panic!("Dependency tracking is disabled so invalidation is not allowed");
+    fn set_invalidation_key_state(&self, key: String, state: Vec<u8>) {
+        if let Some(log) = self.invalidation_key_log_to_persist() {
+            log.lock().states.insert(key, Some(state));
         }
-        operation::InvalidateOperation::run(
-            smallvec![task_id],
-            #[cfg(feature = "trace_task_dirty")]
-            TaskDirtyCause::Invalidator,
-            self.execute_context(turbo_tasks),
-        );
     }
 
-    fn invalidate_tasks(
+    fn invalidate_changed_keys(
         &self,
-        tasks: &[TaskId],
+        has_changed: &dyn Fn(&str, &[u8]) -> bool,
         turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
     ) {
-        if !self.should_track_dependencies() {
-            panic!("Dependency tracking is disabled so invalidation is not allowed");
+        if !self.should_restore() {
+            return;
+        }
+        let changed_keys = self
+            .backing_storage
+            .invalidation_key_states()
+            .into_iter()
+            .filter(|(key, state)| has_changed(key, state))
+            .map(|(key, _)| key)
+            .collect::<Vec<_>>();
+        if changed_keys.is_empty() {
+            return;
+        }
+        let mut tasks = FxHashSet::default();
+        for key in &changed_keys {
+            tasks.extend(self.take_invalidation_key(key));
+        }
+        // The outdated states are removed. The state is recorded again when the invalidated
+        // tasks read the resource.
+        if let Some(log) = self.invalidation_key_log_to_persist() {
+            let mut log = log.lock();
+            for key in changed_keys {
+                log.states.entry(key).or_insert(None);
+            }
+        }
+        if tasks.is_empty() {
+            return;
         }
         operation::InvalidateOperation::run(
-            tasks.iter().copied().collect(),
+            tasks.into_iter().collect(),
             #[cfg(feature = "trace_task_dirty")]
-            TaskDirtyCause::Unknown,
+            TaskDirtyCause::Invalidator,
             self.execute_context(turbo_tasks),
         );
     }
 
-    fn invalidate_tasks_set(
+    fn invalidate_by_key(
         &self,
-        tasks: &AutoSet<TaskId, BuildHasherDefault<FxHasher>, 2>,
+        key: &str,
         turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
     ) {
         if !self.should_track_dependencies() {
             panic!("Dependency tracking is disabled so invalidation is not allowed");
         }
+        let tasks = self.take_invalidation_key(key);
+        if tasks.is_empty() {
+            return;
+        }
         operation::InvalidateOperation::run(
-            tasks.iter().copied().collect(),
+            tasks.into_iter().collect(),
             #[cfg(feature = "trace_task_dirty")]
-            TaskDirtyCause::Unknown,
+            TaskDirtyCause::Invalidator,
             self.execute_context(turbo_tasks),
         );
     }
@@ -1067,7 +2171,7 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
 
     fn get_task_description(&self, task_id: TaskId) -> std::string::String {
         self.lookup_task_type(task_id).map_or_else(
-            || format!("{task_id:?} transient"),
+            || describe_unknown_task(task_id),
             |task_type| task_type.to_string(),
         )
     }
@@ -1188,8 +2292,9 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                 }
             }
         }
+        self.clear_invalidation_keys(task_id);
 
-        let (span, future) = match task_type {
+        let (span, mut future) = match task_type {
             TaskType::Cached(task_type) => {
                 let CachedTaskType { fn_type, this, arg } = &*task_type;
                 (
@@ -1207,27 +2312,121 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                 (span, future)
             }
         };
+        #[cfg(feature = "trace_task_dirty")]
+        if let Some((_, dirty_span)) = self.task_dirty_spans.remove(&task_id) {
+            span.follows_from(&dirty_span);
+        }
+        if let Some(backoff) = self.retry_backoff(task_id) {
+            future = Box::pin(async move {
+                tokio::time::sleep(backoff).await;
+                future.await
+            });
+        }
+        if let Some(event_listener) = &self.options.event_listener {
+            event_listener.task_execution_started(task_id);
+        }
         Some(TaskExecutionSpec { future, span })
     }
 
+    /// Tasks of functions marked as `memory_only` never keep their cell contents persisted.
+    fn is_memory_only_task(&self, task_id: TaskId) -> bool {
+        self.task_cache
+            .lookup_reverse(&task_id)
+            .is_some_and(|task_type| {
+                registry::get_function(task_type.fn_type)
+                    .function_meta
+                    .memory_only
+            })
+    }
+
+    /// Returns the delay before executing a task that is retried after a failure.
+    fn retry_backoff(&self, task_id: TaskId) -> Option<Duration> {
+        let retry_policy = self.options.retry_policy.as_ref()?;
+        let retries = *self.task_retries.get(&task_id)?;
+        Some(
+            retry_policy
+                .backoff
+                .saturating_mul(2u32.saturating_pow(retries - 1)),
+        )
+    }
+
+    /// Marks a failed task as stale, so it's executed again instead of storing the error, when
+    /// the retry policy allows that.
+    fn try_retry_failed_task(
+        &self,
+        task_id: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
+    ) -> bool {
+        let Some(retry_policy) = &self.options.retry_policy else {
+            return false;
+        };
+        let retries = self
+            .task_retries
+            .get(&task_id)
+            .map_or(0, |retries| *retries);
+        if retries >= retry_policy.max_retries {
+            return false;
+        }
+        let mut ctx = self.execute_context(turbo_tasks);
+        let mut task = ctx.task(task_id, TaskDataCategory::Data);
+        let Some(InProgressState::InProgress(box InProgressStateInner {
+            stale, once_task, ..
+        })) = get_mut!(task, InProgress)
+        else {
+            return false;
+        };
+        // Once tasks can't be executed again
+        if *once_task {
+            return false;
+        }
+        *stale = true;
+        drop(task);
+        self.task_retries.insert(task_id, retries + 1);
+        true
+    }
+
     fn task_execution_result(
         &self,
         task_id: TaskId,
-        result: Result<Result<RawVc>, Option<Cow<'static, str>>>,
+        result: Result<Result<RawVc>, TurboTasksPanic>,
         turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
     ) {
+        let should_retry = match &result {
+            Ok(Ok(_)) => false,
+            // Errors that were read from failed upstream tasks are retried by these tasks. Retrying
+            // this task would only read the same error again.
+            Ok(Err(err)) => !err.chain().any(|cause| cause.is::<TaskError>()),
+            Err(_) => true,
+        };
+        if should_retry && self.try_retry_failed_task(task_id, turbo_tasks) {
+            return;
+        }
+        if self.options.retry_policy.is_some() {
+            self.task_retries.remove(&task_id);
+        }
         operation::UpdateOutputOperation::run(task_id, result, self.execute_context(turbo_tasks));
     }
 
     fn task_execution_completed(
         &self,
         task_id: TaskId,
-        _duration: Duration,
-        _memory_usage: usize,
+        duration: Duration,
+        memory_usage: usize,
         cell_counters: &AutoMap<ValueTypeId, u32, BuildHasherDefault<FxHasher>, 8>,
         stateful: bool,
         turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
     ) -> bool {
+        self.output_waits.remove_reader(task_id);
+        if let Some(event_listener) = &self.options.event_listener {
+            event_listener.task_execution_finished(task_id, duration);
+        }
+        if self.execution_statistics.is_enabled() {
+            if let Some(task_type) = self.task_cache.lookup_reverse(&task_id) {
+                self.execution_statistics
+                    .record(task_type.fn_type, duration, memory_usage);
+            }
+        }
+
         // Task completion is a 4 step process:
         // 1. Remove old edges (dependencies, collectibles, children, cells) and update the
         //    aggregation number of the task and the new children.
@@ -1296,6 +2495,7 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         // mark the task as completed, so dependent tasks can continue working
         if !*marked_as_completed {
             *marked_as_completed = true;
+            self.output_waits.remove_target(task_id);
             done_event.notify(usize::MAX);
         }
 
@@ -1338,7 +2538,13 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
 
         // Prepare all new children
         if has_children {
-            prepare_new_children(task_id, &mut task, &new_children, &mut queue);
+            prepare_new_children(
+                task_id,
+                &mut task,
+                &new_children,
+                self.options.aggregation.base_aggregation_number_increment,
+                &mut queue,
+            );
         }
 
         // Filter actual new children
@@ -1581,11 +2787,91 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         task.shrink_to_fit(CachedDataItemType::CellDependency);
         task.shrink_to_fit(CachedDataItemType::OutputDependency);
         task.shrink_to_fit(CachedDataItemType::CollectiblesDependency);
+        if self.should_persist()
+            && (self
+                .options
+                .persistence_cost_threshold
+                .is_some_and(|threshold| duration < threshold)
+                || self.is_memory_only_task(task_id))
+        {
+            task.remove_persisted_cell_data();
+        }
         drop(task);
 
         false
     }
 
+    fn task_execution_canceled(
+        &self,
+        task_id: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
+    ) {
+        self.output_waits.remove_reader(task_id);
+        if self.options.retry_policy.is_some() {
+            // The next execution is not a retry
+            self.task_retries.remove(&task_id);
+        }
+        let mut ctx = self.execute_context(turbo_tasks);
+        let mut task = ctx.task(task_id, TaskDataCategory::All);
+        let Some(in_progress) = remove!(task, InProgress) else {
+            return;
+        };
+        let InProgressState::InProgress(box InProgressStateInner {
+            done_event,
+            mut new_children,
+            ..
+        }) = in_progress
+        else {
+            task.add_new(CachedDataItem::InProgress { value: in_progress });
+            return;
+        };
+
+        // The output and the dirty flag are untouched, so the task stays dirty with its previous
+        // output and will be scheduled again by the next reader. Cells that were computed by
+        // this execution are unknown, so all waiting cell readers need to retry.
+        let in_progress_cells = task
+            .extract_if(CachedDataItemType::InProgressCell, |_, value| {
+                if let CachedDataItemValueRef::InProgressCell { value } = value {
+                    value.event.notify(usize::MAX);
+                }
+                true
+            })
+            .collect::<Vec<_>>();
+
+        // Remove old children from new_children to leave only the children that had their
+        // active count increased
+        for task in iter_many!(task, Child { task } => task) {
+            new_children.remove(&task);
+        }
+        drop(task);
+        drop(in_progress_cells);
+
+        self.output_waits.remove_target(task_id);
+        done_event.notify(usize::MAX);
+
+        if !new_children.is_empty() {
+            AggregationUpdateQueue::run(
+                AggregationUpdateJob::DecreaseActiveCounts {
+                    task_ids: new_children.into_iter().collect(),
+                },
+                &mut ctx,
+            );
+        }
+    }
+
+    fn get_backend_job_description(&self, id: BackendJobId) -> String {
+        match id {
+            BACKEND_JOB_INITIAL_SNAPSHOT => "initial snapshot".to_string(),
+            BACKEND_JOB_FOLLOW_UP_SNAPSHOT => "follow-up snapshot".to_string(),
+            BACKEND_JOB_COMPACTION => "compaction".to_string(),
+            BACKEND_JOB_PREFETCH => "prefetch hot tasks".to_string(),
+            BACKEND_JOB_REMOVE_ORPHANED_DATA => "remove orphaned data".to_string(),
+            BACKEND_JOB_CONSISTENCY_CHECK => "consistency check".to_string(),
+            BACKEND_JOB_FLUSH_INVALIDATIONS => "flush invalidations".to_string(),
+            _ => format!("unknown backend job {id}"),
+        }
+    }
+
     fn run_backend_job<'a>(
         self: &'a Arc<Self>,
         id: BackendJobId,
@@ -1599,16 +2885,20 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                 let mut last_snapshot = self.start_time + Duration::from_millis(last_snapshot);
                 loop {
                     const FIRST_SNAPSHOT_WAIT: Duration = Duration::from_secs(60);
-                    const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(30);
                     const IDLE_TIMEOUT: Duration = Duration::from_secs(2);
+                    const INTERVAL_REEVALUATION: Duration = Duration::from_secs(1);
 
-                    let time = if id == BACKEND_JOB_INITIAL_SNAPSHOT {
-                        FIRST_SNAPSHOT_WAIT
-                    } else {
-                        SNAPSHOT_INTERVAL
+                    let interval = || {
+                        if id == BACKEND_JOB_INITIAL_SNAPSHOT {
+                            FIRST_SNAPSHOT_WAIT
+                        } else {
+                            self.options
+                                .snapshot_interval
+                                .for_pending_updates(self.pending_persisted_updates())
+                        }
                     };
 
-                    let until = last_snapshot + time;
+                    let mut until = last_snapshot + interval();
                     if until > Instant::now() {
                         let mut stop_listener = self.stopping_event.listen();
                         if !self.stopping.load(Ordering::Acquire) {
@@ -1635,6 +2925,21 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                                     _ = tokio::time::sleep_until(until) => {
                                         break;
                                     },
+                                    _ = tokio::time::sleep(INTERVAL_REEVALUATION) => {
+                                        if self.should_flush_task_cache_log() {
+                                            let this = self.clone();
+                                            turbo_tasks::spawn_blocking(move || {
+                                                this.flush_task_cache_log()
+                                            })
+                                            .await;
+                                            if !self.should_persist() {
+                                                return;
+                                            }
+                                        }
+                                        // More updates might be pending by now, which shortens
+                                        // the interval
+                                        until = last_snapshot + interval();
+                                    },
                                     _ = tokio::time::sleep_until(idle_time) => {
                                         if turbo_tasks.is_idle() {
                                             break;
@@ -1645,11 +2950,19 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                         }
                     }
 
+                    if !self.stopping.load(Ordering::Acquire)
+                        && self.pending_persisted_updates() == 0
+                    {
+                        // Nothing to persist, wait for another interval
+                        last_snapshot = Instant::now();
+                        continue;
+                    }
+
                     let this = self.clone();
                     let snapshot = turbo_tasks::spawn_blocking(move || this.snapshot()).await;
-                    if let Some((snapshot_start, new_data)) = snapshot {
-                        last_snapshot = snapshot_start;
-                        if new_data {
+                    if let Some(info) = snapshot {
+                        last_snapshot = info.captured_at;
+                        if info.has_new_data() {
                             continue;
                         }
                         let last_snapshot = last_snapshot.duration_since(self.start_time);
@@ -1661,11 +2974,207 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
                         turbo_tasks.schedule_backend_background_job(BACKEND_JOB_FOLLOW_UP_SNAPSHOT);
                         return;
                     }
+                    if !self.should_persist() {
+                        // Persisting failed, the backend continues in memory-only mode
+                        return;
+                    }
+                }
+            } else if id == BACKEND_JOB_PREFETCH {
+                let this = self.clone();
+                turbo_tasks::spawn_blocking(move || {
+                    let hot_tasks = this
+                        .backing_storage
+                        .hot_tasks(this.options.prefetch_hot_tasks);
+                    let _span = tracing::trace_span!("prefetch hot tasks", tasks = hot_tasks.len())
+                        .entered();
+                    let turbo_tasks = turbo_tasks::turbo_tasks();
+                    let handle = tokio::runtime::Handle::current();
+                    hot_tasks.into_par_iter().for_each(|task_id| {
+                        if this.stopping.load(Ordering::Relaxed) {
+                            return;
+                        }
+                        let _guard = handle.enter();
+                        turbo_tasks_scope(turbo_tasks.clone(), || this.prefetch_task_data(task_id));
+                    });
+                })
+                .await;
+            } else if id == BACKEND_JOB_COMPACTION {
+                const COMPACTION_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+                let mut stop_listener = self.stopping_event.listen();
+                loop {
+                    if self.stopping.load(Ordering::Acquire) {
+                        return;
+                    }
+                    tokio::select! {
+                        _ = &mut stop_listener => return,
+                        _ = tokio::time::sleep(COMPACTION_INTERVAL) => {},
+                    }
+                    // Only compact while idle, to not compete with task execution
+                    if !self.wait_for_idle(turbo_tasks, &mut stop_listener).await {
+                        return;
+                    }
+                    if !self.should_persist() {
+                        return;
+                    }
+
+                    let this = self.clone();
+                    turbo_tasks::spawn_blocking(move || {
+                        let span = tracing::info_span!(
+                            "compact database",
+                            reclaimed_bytes = tracing::field::Empty
+                        )
+                        .entered();
+                        let start = Instant::now();
+                        match this.backing_storage.compact() {
+                            Ok(reclaimed_bytes) => {
+                                span.record("reclaimed_bytes", reclaimed_bytes);
+                                if let Some(event_listener) = &this.options.event_listener {
+                                    event_listener
+                                        .compaction_finished(start.elapsed(), reclaimed_bytes);
+                                }
+                            }
+                            Err(err) => {
+                                println!("Compacting the persistent cache failed: {err:?}");
+                            }
+                        }
+                    })
+                    .await;
+                }
+            } else if id == BACKEND_JOB_REMOVE_ORPHANED_DATA {
+                let mut stop_listener = self.stopping_event.listen();
+                if !self.wait_for_idle(turbo_tasks, &mut stop_listener).await {
+                    return;
+                }
+                let this = self.clone();
+                turbo_tasks::spawn_blocking(move || {
+                    let span = tracing::info_span!(
+                        "remove orphaned data",
+                        affected_tasks = tracing::field::Empty
+                    )
+                    .entered();
+                    if !this.should_persist() {
+                        return;
+                    }
+                    let candidates = match this.backing_storage.find_orphaned_data() {
+                        Ok(candidates) => candidates,
+                        Err(err) => {
+                            println!(
+                                "Finding orphaned data in the persistent cache failed: {err:?}"
+                            );
+                            return;
+                        }
+                    };
+                    if candidates.is_empty() {
+                        span.record("affected_tasks", 0);
+                        return;
+                    }
+                    // Snapshots must not write while the candidates are checked again and removed
+                    let _last_snapshot_result = this.last_snapshot_result.lock();
+                    if !this.should_persist() {
+                        return;
+                    }
+                    match this.backing_storage.remove_orphaned_data(candidates) {
+                        Ok(affected_tasks) => {
+                            span.record("affected_tasks", affected_tasks);
+                        }
+                        Err(err) => {
+                            println!(
+                                "Removing orphaned data from the persistent cache failed: {err:?}"
+                            );
+                        }
+                    }
+                })
+                .await;
+            } else if id == BACKEND_JOB_FLUSH_INVALIDATIONS {
+                if let Some(debounce) = self.options.invalidation_debounce {
+                    tokio::time::sleep(debounce).await;
+                }
+                let task_ids = take(&mut *self.pending_invalidations.lock());
+                if !task_ids.is_empty() {
+                    let _span = tracing::trace_span!("flush invalidations", tasks = task_ids.len())
+                        .entered();
+                    operation::InvalidateOperation::run(
+                        task_ids.into_iter().collect(),
+                        #[cfg(feature = "trace_task_dirty")]
+                        TaskDirtyCause::Invalidator,
+                        self.execute_context(turbo_tasks),
+                    );
+                }
+            } else if id == BACKEND_JOB_CONSISTENCY_CHECK {
+                let Some(interval) = self.options.consistency_check_interval else {
+                    return;
+                };
+                let mut stop_listener = self.stopping_event.listen();
+                loop {
+                    if self.stopping.load(Ordering::Acquire) {
+                        return;
+                    }
+                    tokio::select! {
+                        _ = &mut stop_listener => return,
+                        _ = tokio::time::sleep(interval) => {},
+                    }
+                    // Only check while idle, to not compete with task execution
+                    if !self.wait_for_idle(turbo_tasks, &mut stop_listener).await {
+                        return;
+                    }
+                    if !self.should_persist() {
+                        return;
+                    }
+
+                    let this = self.clone();
+                    turbo_tasks::spawn_blocking(move || {
+                        let span = tracing::trace_span!(
+                            "consistency check",
+                            problems = tracing::field::Empty
+                        )
+                        .entered();
+                        match this
+                            .backing_storage
+                            .verify_tasks(CONSISTENCY_CHECK_SAMPLE_SIZE)
+                        {
+                            Ok(problems) => {
+                                span.record("problems", problems.len());
+                                for (task_id, problem) in problems {
+                                    println!(
+                                        "Persistent cache consistency problem in {task_id}: \
+                                         {problem}"
+                                    );
+                                    if let Some(event_listener) = &this.options.event_listener {
+                                        event_listener.consistency_problem(task_id, &problem);
+                                    }
+                                }
+                            }
+                            Err(err) => {
+                                println!("Checking the persistent cache failed: {err:?}");
+                            }
+                        }
+                    })
+                    .await;
                 }
             }
         })
     }
 
+    /// Waits until no tasks are executing. Returns false when the backend is stopping before.
+    async fn wait_for_idle(
+        &self,
+        turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
+        stop_listener: &mut EventListener,
+    ) -> bool {
+        while !turbo_tasks.is_idle() {
+            let mut idle_start_listener = self.idle_start_event.listen();
+            if turbo_tasks.is_idle() {
+                break;
+            }
+            tokio::select! {
+                _ = &mut *stop_listener => return false,
+                _ = &mut idle_start_listener => {},
+            }
+        }
+        true
+    }
+
     fn try_read_own_task_cell_untracked(
         &self,
         task_id: TaskId,
@@ -1823,6 +3332,12 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         content: CellContent,
         turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
     ) {
+        let content = match (&self.cell_interner, content) {
+            (Some(interner), CellContent(Some(value))) => {
+                CellContent(Some(interner.intern(cell.type_id, value)))
+            }
+            (_, content) => content,
+        };
         operation::UpdateCellOperation::run(
             task_id,
             cell,
@@ -1853,11 +3368,11 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
 
     fn mark_own_task_as_finished(
         &self,
-        task: TaskId,
+        task_id: TaskId,
         turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
     ) {
         let mut ctx = self.execute_context(turbo_tasks);
-        let mut task = ctx.task(task, TaskDataCategory::Data);
+        let mut task = ctx.task(task_id, TaskDataCategory::Data);
         if let Some(InProgressState::InProgress(box InProgressStateInner {
             marked_as_completed,
             done_event,
@@ -1865,6 +3380,7 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         })) = get_mut!(task, InProgress)
         {
             *marked_as_completed = true;
+            self.output_waits.remove_target(task_id);
             done_event.notify(usize::MAX);
             // TODO this should remove the dirty state (also check session_dependent)
             // but this would break some assumptions for strongly consistent reads.
@@ -1899,6 +3415,19 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         ConnectChildOperation::run(parent_task, task, self.execute_context(turbo_tasks));
     }
 
+    fn connect_tasks(
+        &self,
+        tasks: &[TaskId],
+        parent_task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
+    ) {
+        ConnectChildOperation::run_batch(
+            parent_task,
+            tasks.iter().copied(),
+            self.execute_context(turbo_tasks),
+        );
+    }
+
     fn create_transient_task(&self, task_type: TransientTaskType) -> TaskId {
         let task_id = self.transient_task_id_factory.get();
         let root_type = match task_type {
@@ -1939,6 +3468,13 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
         task_id: TaskId,
         turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
     ) {
+        let is_once_task = self
+            .transient_tasks
+            .get(&task_id)
+            .is_some_and(|task_type| matches!(**task_type, TransientTask::Once(_)));
+        if is_once_task && self.release_once_task(task_id, turbo_tasks) {
+            return;
+        }
         let mut ctx = self.execute_context(turbo_tasks);
         let mut task = ctx.task(task_id, TaskDataCategory::All);
         let is_dirty = get!(task, Dirty).map_or(false, |dirty| dirty.get(self.session_id));
@@ -1958,6 +3494,58 @@ impl<B: BackingStorage> TurboTasksBackendInner<B> {
             root_state.all_clean_event.notify(usize::MAX);
         }
     }
+
+    /// Releases the execution state of a completed once task: its edges, its `Activeness` and its
+    /// transient task entry. A once task is never executed again, so it doesn't need to track its
+    /// children and dependencies once its output has been read. Returns false when the task is
+    /// still in progress.
+    ///
+    /// The task is not unloaded. Its output and cells stay in storage and its id is never reused,
+    /// since the value returned by `run_once` can still hold `Vc`s that point to the cells of the
+    /// task, and the task graph doesn't track when the last of them is dropped.
+    fn release_once_task(
+        &self,
+        task_id: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
+    ) -> bool {
+        let mut ctx = self.execute_context(turbo_tasks);
+        let mut task = ctx.task(task_id, TaskDataCategory::All);
+        if get!(task, InProgress).is_some() {
+            return false;
+        }
+        if let Some(root_state) = remove!(task, Activeness) {
+            root_state.all_clean_event.notify(usize::MAX);
+        }
+        let mut old_edges = Vec::new();
+        old_edges.extend(iter_many!(task, Child { task } => task).map(OutdatedEdge::Child));
+        old_edges.extend(iter_many!(
+            task,
+            Collectible { collectible } count => OutdatedEdge::Collectible(collectible, *count)
+        ));
+        old_edges.extend(iter_many!(
+            task,
+            CellDependency { target } => OutdatedEdge::CellDependency(target)
+        ));
+        old_edges.extend(iter_many!(
+            task,
+            OutputDependency { target } => OutdatedEdge::OutputDependency(target)
+        ));
+        old_edges.extend(iter_many!(
+            task,
+            CollectiblesDependency { target } => OutdatedEdge::CollectiblesDependency(target)
+        ));
+        drop(task);
+        if !old_edges.is_empty() {
+            CleanupOldEdgesOperation::run(
+                task_id,
+                old_edges,
+                AggregationUpdateQueue::new(),
+                &mut ctx,
+            );
+        }
+        self.transient_tasks.remove(&task_id);
+        true
+    }
 }
 
 impl<B: BackingStorage> Backend for TurboTasksBackend<B> {
@@ -2025,6 +3613,15 @@ impl<B: BackingStorage> Backend for TurboTasksBackend<B> {
         self.0.invalidate_serialization(task_id, turbo_tasks);
     }
 
+    fn register_invalidation_key(
+        &self,
+        task_id: TaskId,
+        key: String,
+        _turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) {
+        self.0.register_invalidation_key(task_id, key);
+    }
+
     fn get_task_description(&self, task: TaskId) -> std::string::String {
         self.0.get_task_description(task)
     }
@@ -2047,7 +3644,7 @@ impl<B: BackingStorage> Backend for TurboTasksBackend<B> {
     fn task_execution_result(
         &self,
         task_id: TaskId,
-        result: Result<Result<RawVc>, Option<Cow<'static, str>>>,
+        result: Result<Result<RawVc>, TurboTasksPanic>,
         turbo_tasks: &dyn TurboTasksBackendApi<Self>,
     ) {
         self.0.task_execution_result(task_id, result, turbo_tasks);
@@ -2056,22 +3653,34 @@ impl<B: BackingStorage> Backend for TurboTasksBackend<B> {
     fn task_execution_completed(
         &self,
         task_id: TaskId,
-        _duration: Duration,
-        _memory_usage: usize,
+        duration: Duration,
+        memory_usage: usize,
         cell_counters: &AutoMap<ValueTypeId, u32, BuildHasherDefault<FxHasher>, 8>,
         stateful: bool,
         turbo_tasks: &dyn TurboTasksBackendApi<Self>,
     ) -> bool {
         self.0.task_execution_completed(
             task_id,
-            _duration,
-            _memory_usage,
+            duration,
+            memory_usage,
             cell_counters,
             stateful,
             turbo_tasks,
         )
     }
 
+    fn task_execution_canceled(
+        &self,
+        task_id: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) {
+        self.0.task_execution_canceled(task_id, turbo_tasks)
+    }
+
+    fn get_backend_job_description(&self, id: BackendJobId) -> String {
+        self.0.get_backend_job_description(id)
+    }
+
     fn run_backend_job<'a>(
         &'a self,
         id: BackendJobId,
@@ -2214,6 +3823,15 @@ impl<B: BackingStorage> Backend for TurboTasksBackend<B> {
         self.0.connect_task(task, parent_task, turbo_tasks);
     }
 
+    fn connect_tasks(
+        &self,
+        tasks: &[TaskId],
+        parent_task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) {
+        self.0.connect_tasks(tasks, parent_task, turbo_tasks);
+    }
+
     fn create_transient_task(
         &self,
         task_type: TransientTaskType,
@@ -2231,6 +3849,28 @@ impl<B: BackingStorage> Backend for TurboTasksBackend<B> {
     }
 }
 
+/// Runs `f`, which blocks until a snapshot is completed. Operations are synchronous and can't
+/// await the snapshot, so on a worker of a multi-threaded tokio runtime the worker is handed off
+/// to another thread first. Otherwise all workers could block during a snapshot and starve the
+/// runtime.
+fn block_for_snapshot<R>(f: impl FnOnce() -> R) -> R {
+    let _span = tracing::info_span!("wait for snapshot").entered();
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => block_in_place(f),
+        _ => f(),
+    }
+}
+
+/// Describes a task without a task type. Transient root tasks have none. Persistent tasks
+/// should always have one, either in memory or in the backing storage.
+fn describe_unknown_task(task_id: TaskId) -> String {
+    if task_id.is_transient() {
+        format!("{task_id:?} transient")
+    } else {
+        format!("{task_id:?} (task type not found in memory or in the persistent cache)")
+    }
+}
+
 // from https://github.com/tokio-rs/tokio/blob/29cd6ec1ec6f90a7ee1ad641c03e0e00badbcb0e/tokio/src/time/instant.rs#L57-L63
 fn far_future() -> Instant {
     // Roughly 30 years from now.
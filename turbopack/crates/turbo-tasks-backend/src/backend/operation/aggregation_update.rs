@@ -35,7 +35,6 @@ use crate::{
 
 pub const LEAF_NUMBER: u32 = 16;
 const MAX_COUNT_BEFORE_YIELD: usize = 1000;
-const MAX_UPPERS_FOLLOWER_PRODUCT: usize = 31;
 
 type TaskIdVec = SmallVec<[TaskId; 4]>;
 
@@ -802,8 +801,18 @@ impl AggregationUpdateQueue {
                 }
                 AggregationUpdateJob::InnerOfUpperHasNewFollowers {
                     upper_id,
-                    new_follower_ids,
+                    mut new_follower_ids,
                 } => {
+                    if new_follower_ids.len() > MAX_COUNT_BEFORE_YIELD {
+                        // Split large lists to allow suspending in between
+                        let remaining = new_follower_ids.drain(MAX_COUNT_BEFORE_YIELD..).collect();
+                        self.jobs.push_front(AggregationUpdateJobItem::new(
+                            AggregationUpdateJob::InnerOfUpperHasNewFollowers {
+                                upper_id,
+                                new_follower_ids: remaining,
+                            },
+                        ));
+                    }
                     if new_follower_ids.len() == 1 {
                         self.inner_of_upper_has_new_follower(ctx, new_follower_ids[0], upper_id);
                     } else {
@@ -855,8 +864,18 @@ impl AggregationUpdateQueue {
                 }
                 AggregationUpdateJob::InnerOfUpperLostFollowers {
                     upper_id,
-                    lost_follower_ids,
+                    mut lost_follower_ids,
                 } => {
+                    if lost_follower_ids.len() > MAX_COUNT_BEFORE_YIELD {
+                        // Split large lists to allow suspending in between
+                        let remaining = lost_follower_ids.drain(MAX_COUNT_BEFORE_YIELD..).collect();
+                        self.jobs.push_front(AggregationUpdateJobItem::new(
+                            AggregationUpdateJob::InnerOfUpperLostFollowers {
+                                upper_id,
+                                lost_follower_ids: remaining,
+                            },
+                        ));
+                    }
                     self.inner_of_upper_lost_followers(ctx, lost_follower_ids, upper_id);
                 }
                 AggregationUpdateJob::AggregatedDataUpdate(box AggregatedDataUpdateJob {
@@ -866,10 +885,21 @@ impl AggregationUpdateQueue {
                     self.aggregated_data_update(upper_ids, ctx, update);
                 }
                 AggregationUpdateJob::InvalidateDueToCollectiblesChange {
-                    task_ids,
+                    mut task_ids,
                     #[cfg(feature = "trace_task_dirty")]
                     collectible_type,
                 } => {
+                    if task_ids.len() > MAX_COUNT_BEFORE_YIELD {
+                        // Split large lists to allow suspending in between
+                        let remaining = task_ids.drain(MAX_COUNT_BEFORE_YIELD..).collect();
+                        self.jobs.push_front(AggregationUpdateJobItem::new(
+                            AggregationUpdateJob::InvalidateDueToCollectiblesChange {
+                                task_ids: remaining,
+                                #[cfg(feature = "trace_task_dirty")]
+                                collectible_type,
+                            },
+                        ));
+                    }
                     for task_id in task_ids {
                         make_task_dirty(
                             task_id,
@@ -2135,10 +2165,11 @@ impl AggregationUpdateQueue {
             children_count
         };
         let upper_count = count!(task, Upper);
+        let max_uppers_follower_product = ctx.aggregation_options().max_uppers_follower_product;
         if upper_count <= 1
             || upper_count.saturating_sub(1) * follower_count
                 <= max(
-                    MAX_UPPERS_FOLLOWER_PRODUCT,
+                    max_uppers_follower_product,
                     aggregation_number.effective as usize,
                 )
         {
@@ -2234,6 +2265,7 @@ impl AggregationUpdateQueue {
 
 impl Operation for AggregationUpdateQueue {
     fn execute(mut self, ctx: &mut impl ExecuteContext) {
+        let _span = tracing::trace_span!("aggregation update").entered();
         loop {
             ctx.operation_suspend_point(&self);
             if self.process(ctx) {
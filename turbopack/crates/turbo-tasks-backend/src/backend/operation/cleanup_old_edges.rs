@@ -60,6 +60,7 @@ impl CleanupOldEdgesOperation {
         queue: AggregationUpdateQueue,
         ctx: &mut impl ExecuteContext,
     ) {
+        let _timer = ctx.operation_timer("cleanup old edges");
         CleanupOldEdgesOperation::RemoveEdges {
             task_id,
             outdated,
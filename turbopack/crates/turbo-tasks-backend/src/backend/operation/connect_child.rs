@@ -1,4 +1,5 @@
 use serde::{Deserialize, Serialize};
+use smallvec::SmallVec;
 use turbo_tasks::TaskId;
 
 use crate::{
@@ -24,16 +25,22 @@ pub enum ConnectChildOperation {
 }
 
 impl ConnectChildOperation {
-    pub fn run(parent_task_id: TaskId, child_task_id: TaskId, mut ctx: impl ExecuteContext) {
+    pub fn run(parent_task_id: TaskId, child_task_id: TaskId, ctx: impl ExecuteContext) {
+        Self::run_batch(parent_task_id, [child_task_id], ctx);
+    }
+
+    /// Connects many children at once. Compared to running the operation for every child, the
+    /// parent task is locked only once and a single aggregation update increases the active
+    /// counts of all children.
+    pub fn run_batch(
+        parent_task_id: TaskId,
+        child_task_ids: impl IntoIterator<Item = TaskId>,
+        mut ctx: impl ExecuteContext,
+    ) {
+        let _timer = ctx.operation_timer("connect child");
         if !ctx.should_track_children() {
-            let mut task = ctx.task(child_task_id, TaskDataCategory::All);
-            if !task.has_key(&CachedDataItemKey::Output {}) {
-                let description = ctx.get_task_desc_fn(child_task_id);
-                let should_schedule = task.add(CachedDataItem::new_scheduled(description));
-                drop(task);
-                if should_schedule {
-                    ctx.schedule(child_task_id);
-                }
+            for child_task_id in child_task_ids {
+                schedule_if_not_computed(child_task_id, &mut ctx);
             }
             return;
         }
@@ -45,41 +52,46 @@ impl ConnectChildOperation {
         };
 
         // Quick skip if the child was already connected before
-        if !new_children.insert(child_task_id) {
-            return;
-        }
-        if parent_task.has_key(&CachedDataItemKey::Child {
-            task: child_task_id,
-        }) {
-            // It is already connected, we can skip the rest
+        let child_task_ids: SmallVec<[TaskId; 4]> = child_task_ids
+            .into_iter()
+            .filter(|&child_task_id| new_children.insert(child_task_id))
+            .collect();
+        // Skip children that are already connected
+        let child_task_ids: SmallVec<[TaskId; 4]> = child_task_ids
+            .into_iter()
+            .filter(|&child_task_id| {
+                !parent_task.has_key(&CachedDataItemKey::Child {
+                    task: child_task_id,
+                })
+            })
+            .collect();
+        drop(parent_task);
+        if child_task_ids.is_empty() {
             return;
         }
-        drop(parent_task);
 
         let mut queue = AggregationUpdateQueue::new();
 
         // Handle the transient to persistent boundary by making the persistent task a root task
-        if parent_task_id.is_transient() && !child_task_id.is_transient() {
-            queue.push(AggregationUpdateJob::UpdateAggregationNumber {
-                task_id: child_task_id,
-                base_aggregation_number: u32::MAX,
-                distance: None,
-            });
+        if parent_task_id.is_transient() {
+            for &child_task_id in child_task_ids.iter() {
+                if !child_task_id.is_transient() {
+                    queue.push(AggregationUpdateJob::UpdateAggregationNumber {
+                        task_id: child_task_id,
+                        base_aggregation_number: u32::MAX,
+                        distance: None,
+                    });
+                }
+            }
         }
 
         if ctx.should_track_activeness() {
-            queue.push(AggregationUpdateJob::IncreaseActiveCount {
-                task: child_task_id,
+            queue.push(AggregationUpdateJob::IncreaseActiveCounts {
+                task_ids: child_task_ids,
             });
         } else {
-            let mut task = ctx.task(child_task_id, TaskDataCategory::All);
-            if !task.has_key(&CachedDataItemKey::Output {}) {
-                let description = ctx.get_task_desc_fn(child_task_id);
-                let should_schedule = task.add(CachedDataItem::new_scheduled(description));
-                drop(task);
-                if should_schedule {
-                    ctx.schedule(child_task_id);
-                }
+            for child_task_id in child_task_ids {
+                schedule_if_not_computed(child_task_id, &mut ctx);
             }
         }
 
@@ -90,6 +102,18 @@ impl ConnectChildOperation {
     }
 }
 
+fn schedule_if_not_computed(task_id: TaskId, ctx: &mut impl ExecuteContext) {
+    let mut task = ctx.task(task_id, TaskDataCategory::All);
+    if !task.has_key(&CachedDataItemKey::Output {}) {
+        let description = ctx.get_task_desc_fn(task_id);
+        let should_schedule = task.add(CachedDataItem::new_scheduled(description));
+        drop(task);
+        if should_schedule {
+            ctx.schedule(task_id);
+        }
+    }
+}
+
 impl Operation for ConnectChildOperation {
     fn execute(mut self, ctx: &mut impl ExecuteContext) {
         loop {
@@ -1,3 +1,5 @@
+use std::mem::take;
+
 use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
 use turbo_tasks::TaskId;
@@ -19,6 +21,10 @@ use crate::{
     },
 };
 
+/// Upper bound for the number of tasks that are made dirty between two suspend points. This
+/// keeps mass invalidations from blocking snapshots for a long time.
+const MAX_TASKS_BEFORE_SUSPEND: usize = 1000;
+
 #[derive(Serialize, Deserialize, Clone, Default)]
 #[allow(clippy::large_enum_variant)]
 pub enum InvalidateOperation {
@@ -26,6 +32,11 @@ pub enum InvalidateOperation {
         task_ids: SmallVec<[TaskId; 4]>,
         #[cfg(feature = "trace_task_dirty")]
         cause: TaskDirtyCause,
+        queue: AggregationUpdateQueue,
+        /// Tasks that were made dirty and need to be scheduled. They are scheduled together once
+        /// all tasks are dirty, so no task starts executing while its inputs are still being
+        /// invalidated.
+        scheduled: Vec<TaskId>,
     },
     AggregationUpdate {
         queue: AggregationUpdateQueue,
@@ -36,14 +47,20 @@ pub enum InvalidateOperation {
 
 impl InvalidateOperation {
     pub fn run(
-        task_ids: SmallVec<[TaskId; 4]>,
+        mut task_ids: SmallVec<[TaskId; 4]>,
         #[cfg(feature = "trace_task_dirty")] cause: TaskDirtyCause,
         mut ctx: impl ExecuteContext,
     ) {
+        let _timer = ctx.operation_timer("invalidate");
+        // Overlapping invalidations would visit the same tasks again
+        task_ids.sort_unstable();
+        task_ids.dedup();
         InvalidateOperation::MakeDirty {
             task_ids,
             #[cfg(feature = "trace_task_dirty")]
             cause,
+            queue: AggregationUpdateQueue::new(),
+            scheduled: Vec::new(),
         }
         .execute(&mut ctx)
     }
@@ -55,26 +72,36 @@ impl Operation for InvalidateOperation {
             ctx.operation_suspend_point(&self);
             match self {
                 InvalidateOperation::MakeDirty {
-                    task_ids,
+                    ref mut task_ids,
                     #[cfg(feature = "trace_task_dirty")]
                     cause,
+                    ref mut queue,
+                    ref mut scheduled,
                 } => {
-                    let mut queue = AggregationUpdateQueue::new();
-                    for task_id in task_ids {
-                        make_task_dirty(
+                    // Tasks are made dirty in chunks with suspend points in between
+                    let chunk_start = task_ids.len().saturating_sub(MAX_TASKS_BEFORE_SUSPEND);
+                    for task_id in task_ids.drain(chunk_start..) {
+                        if make_task_dirty_without_scheduling(
                             task_id,
                             #[cfg(feature = "trace_task_dirty")]
                             cause,
-                            &mut queue,
+                            queue,
                             ctx,
-                        );
+                        ) {
+                            scheduled.push(task_id);
+                        }
                     }
-                    if queue.is_empty() {
-                        self = InvalidateOperation::Done
-                    } else {
-                        self = InvalidateOperation::AggregationUpdate { queue }
+                    if task_ids.is_empty() {
+                        for task_id in take(scheduled) {
+                            ctx.schedule(task_id);
+                        }
+                        let queue = take(queue);
+                        if queue.is_empty() {
+                            self = InvalidateOperation::Done
+                        } else {
+                            self = InvalidateOperation::AggregationUpdate { queue }
+                        }
                     }
-                    continue;
                 }
                 InvalidateOperation::AggregationUpdate { ref mut queue } => {
                     if queue.process(ctx) {
@@ -172,13 +199,32 @@ pub fn make_task_dirty(
     queue: &mut AggregationUpdateQueue,
     ctx: &mut impl ExecuteContext,
 ) {
+    if make_task_dirty_without_scheduling(
+        task_id,
+        #[cfg(feature = "trace_task_dirty")]
+        cause,
+        queue,
+        ctx,
+    ) {
+        ctx.schedule(task_id);
+    }
+}
+
+/// Like [`make_task_dirty`], but returns true when the task needs to be scheduled instead of
+/// scheduling it.
+fn make_task_dirty_without_scheduling(
+    task_id: TaskId,
+    #[cfg(feature = "trace_task_dirty")] cause: TaskDirtyCause,
+    queue: &mut AggregationUpdateQueue,
+    ctx: &mut impl ExecuteContext,
+) -> bool {
     if ctx.is_once_task(task_id) {
-        return;
+        return false;
     }
 
     let mut task = ctx.task(task_id, TaskDataCategory::All);
 
-    make_task_dirty_internal(
+    mark_task_dirty(
         &mut task,
         task_id,
         true,
@@ -186,7 +232,7 @@ pub fn make_task_dirty(
         cause,
         queue,
         ctx,
-    );
+    )
 }
 
 pub fn make_task_dirty_internal(
@@ -197,18 +243,43 @@ pub fn make_task_dirty_internal(
     queue: &mut AggregationUpdateQueue,
     ctx: &impl ExecuteContext,
 ) {
+    if mark_task_dirty(
+        task,
+        task_id,
+        make_stale,
+        #[cfg(feature = "trace_task_dirty")]
+        cause,
+        queue,
+        ctx,
+    ) {
+        ctx.schedule(task_id);
+    }
+}
+
+/// Marks the task as dirty and as scheduled when it should be executed again. Returns true when
+/// the task needs to be scheduled.
+fn mark_task_dirty(
+    task: &mut impl TaskGuard,
+    task_id: TaskId,
+    make_stale: bool,
+    #[cfg(feature = "trace_task_dirty")] cause: TaskDirtyCause,
+    queue: &mut AggregationUpdateQueue,
+    ctx: &impl ExecuteContext,
+) -> bool {
     if make_stale {
         if let Some(InProgressState::InProgress(box InProgressStateInner { stale, .. })) =
             get_mut!(task, InProgress)
         {
             if !*stale {
                 #[cfg(feature = "trace_task_dirty")]
-                let _span = tracing::trace_span!(
+                let span = tracing::trace_span!(
                     "make task stale",
                     name = ctx.get_task_description(task_id),
                     cause = %TaskDirtyCauseInContext::new(&cause, ctx)
                 )
                 .entered();
+                #[cfg(feature = "trace_task_dirty")]
+                ctx.record_task_dirty_span(task_id, &span);
                 *stale = true;
             }
         }
@@ -232,7 +303,7 @@ pub fn make_task_dirty_internal(
             )
             .entered();
             // already dirty
-            return;
+            return false;
         }
         Some(CachedDataItemValue::Dirty {
             value: DirtyState {
@@ -256,12 +327,15 @@ pub fn make_task_dirty_internal(
     };
 
     #[cfg(feature = "trace_task_dirty")]
-    let _span = tracing::trace_span!(
+    let span = tracing::trace_span!(
         "make task dirty",
         name = ctx.get_task_description(task_id),
         cause = %TaskDirtyCauseInContext::new(&cause, ctx)
     )
     .entered();
+    #[cfg(feature = "trace_task_dirty")]
+    ctx.record_task_dirty_span(task_id, &span);
+    ctx.emit_task_invalidated(task_id);
 
     let should_schedule = if ctx.should_track_children() {
         let aggregated_update = dirty_container.update_with_dirty_state(&DirtyState {
@@ -280,8 +354,7 @@ pub fn make_task_dirty_internal(
 
     if should_schedule {
         let description = ctx.get_task_desc_fn(task_id);
-        if task.add(CachedDataItem::new_scheduled(description)) {
-            ctx.schedule(task_id);
-        }
+        return task.add(CachedDataItem::new_scheduled(description));
     }
+    false
 }
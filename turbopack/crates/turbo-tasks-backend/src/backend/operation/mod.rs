@@ -15,12 +15,12 @@ use std::{
 
 use either::Either;
 use serde::{Deserialize, Serialize};
-use turbo_tasks::{KeyValuePair, SessionId, TaskId, TurboTasksBackendApi};
+use turbo_tasks::{registry, KeyValuePair, SessionId, TaskId, TurboTasksBackendApi};
 
 use crate::{
     backend::{
-        storage::StorageWriteGuard, OperationGuard, TaskDataCategory, TransientTask,
-        TurboTasksBackend, TurboTasksBackendInner,
+        operation_statistics::OperationTimer, storage::StorageWriteGuard, AggregationOptions,
+        OperationGuard, TaskDataCategory, TransientTask, TurboTasksBackend, TurboTasksBackendInner,
     },
     backing_storage::BackingStorage,
     data::{
@@ -91,6 +91,14 @@ pub trait ExecuteContext<'e>: Sized {
     fn should_track_children(&self) -> bool;
     fn should_track_dependencies(&self) -> bool;
     fn should_track_activeness(&self) -> bool;
+    fn should_check_cell_equality(&self) -> bool;
+    fn aggregation_options(&self) -> AggregationOptions;
+    fn emit_task_invalidated(&self, task_id: TaskId);
+    /// Times an operation for the operation statistics until the timer is dropped.
+    fn operation_timer(&self, operation: &'static str) -> OperationTimer<'e>;
+    /// Remembers the span that made the task dirty, so the next execution links to it.
+    #[cfg(feature = "trace_task_dirty")]
+    fn record_task_dirty_span(&self, task_id: TaskId, span: &tracing::Span);
 }
 
 pub struct ParentRef<'a> {
@@ -107,6 +115,8 @@ where
     backend: &'e TurboTasksBackendInner<B>,
     turbo_tasks: &'e dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
     _operation_guard: Option<OperationGuard<'e, B>>,
+    /// The id of the outermost operation for the stall report, shared with nested contexts.
+    in_flight_id: Option<u64>,
     transaction: TransactionState<'e, 'tx, B>,
 }
 
@@ -118,10 +128,12 @@ where
         backend: &'e TurboTasksBackendInner<B>,
         turbo_tasks: &'e dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
     ) -> Self {
+        let operation_guard = backend.start_operation();
         Self {
             backend,
             turbo_tasks,
-            _operation_guard: Some(backend.start_operation()),
+            in_flight_id: operation_guard.in_flight_id,
+            _operation_guard: Some(operation_guard),
             parent: None,
             transaction: TransactionState::None,
         }
@@ -132,10 +144,12 @@ where
         transaction: Option<&'e B::ReadTransaction<'tx>>,
         turbo_tasks: &'e dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
     ) -> Self {
+        let operation_guard = backend.start_operation();
         Self {
             backend,
             turbo_tasks,
-            _operation_guard: Some(backend.start_operation()),
+            in_flight_id: operation_guard.in_flight_id,
+            _operation_guard: Some(operation_guard),
             parent: None,
             transaction: TransactionState::Borrowed(transaction),
         }
@@ -165,6 +179,7 @@ where
         task_id: TaskId,
         category: TaskDataCategory,
     ) -> Vec<CachedDataItem> {
+        self.backend.record_task_access(task_id);
         // Safety: `transaction` is a valid transaction from `self.backend.backing_storage`.
         unsafe {
             self.backend
@@ -184,6 +199,10 @@ where
 
     fn task(&mut self, task_id: TaskId, category: TaskDataCategory) -> impl TaskGuard + 'e {
         let mut task = self.backend.storage.access_mut(task_id);
+        if task.persistance_state().is_prefetched() {
+            task.persistance_state_mut().clear_prefetched();
+            self.backend.record_task_access(task_id);
+        }
         if !task.persistance_state().is_restored(category) {
             if task_id.is_transient() {
                 task.persistance_state_mut()
@@ -233,6 +252,12 @@ where
         category: TaskDataCategory,
     ) -> (impl TaskGuard + 'e, impl TaskGuard + 'e) {
         let (mut task1, mut task2) = self.backend.storage.access_pair_mut(task_id1, task_id2);
+        for (task, task_id) in [(&mut task1, task_id1), (&mut task2, task_id2)] {
+            if task.persistance_state().is_prefetched() {
+                task.persistance_state_mut().clear_prefetched();
+                self.backend.record_task_access(task_id);
+            }
+        }
         let is_restored1 = task1.persistance_state().is_restored(category);
         let is_restored2 = task2.persistance_state().is_restored(category);
         if !is_restored1 || !is_restored2 {
@@ -285,7 +310,7 @@ where
 
     fn operation_suspend_point<T: Clone + Into<AnyOperation>>(&mut self, op: &T) {
         if self.parent.is_some() {
-            self.backend.operation_suspend_point(|| {
+            self.backend.operation_suspend_point(self.in_flight_id, || {
                 let mut nested = Vec::new();
                 nested.push(op.clone().into());
                 let mut cur = self.parent.as_ref();
@@ -296,7 +321,8 @@ where
                 AnyOperation::Nested(nested)
             });
         } else {
-            self.backend.operation_suspend_point(|| op.clone().into());
+            self.backend
+                .operation_suspend_point(self.in_flight_id, || op.clone().into());
         }
     }
 
@@ -318,6 +344,7 @@ where
             backend: &'a TurboTasksBackendInner<B>,
             turbo_tasks: &'a dyn TurboTasksBackendApi<TurboTasksBackend<B>>,
             parent: ParentRef<'a>,
+            in_flight_id: Option<u64>,
             transaction: TransactionState<'a, '_, B>,
             run: impl FnOnce(&mut ExecuteContextImpl<'_, '_, B>),
         ) {
@@ -325,6 +352,7 @@ where
                 backend,
                 turbo_tasks,
                 _operation_guard: None,
+                in_flight_id,
                 parent: Some(parent),
                 transaction,
             };
@@ -337,6 +365,7 @@ where
                 op: &parent_op,
                 parent: &this.parent,
             },
+            self.in_flight_id,
             self.transaction.borrow(),
             run,
         );
@@ -362,6 +391,33 @@ where
     fn should_track_activeness(&self) -> bool {
         self.backend.should_track_activeness()
     }
+
+    fn should_check_cell_equality(&self) -> bool {
+        self.backend.should_check_cell_equality()
+    }
+
+    fn aggregation_options(&self) -> AggregationOptions {
+        self.backend.aggregation_options()
+    }
+
+    fn emit_task_invalidated(&self, task_id: TaskId) {
+        self.backend.invalidation_statistics.record(task_id);
+        if let Some(event_listener) = &self.backend.options.event_listener {
+            event_listener.task_invalidated(task_id);
+        }
+    }
+
+    fn operation_timer(&self, operation: &'static str) -> OperationTimer<'e> {
+        self.backend.operation_statistics.timer(operation)
+    }
+
+    #[cfg(feature = "trace_task_dirty")]
+    fn record_task_dirty_span(&self, task_id: TaskId, span: &tracing::Span) {
+        self.backend
+            .task_dirty_spans
+            .entry(task_id)
+            .or_insert_with(|| span.clone());
+    }
 }
 
 pub trait TaskGuard: Debug {
@@ -397,6 +453,9 @@ pub trait TaskGuard: Debug {
     where
         F: for<'a> FnMut(CachedDataItemKey, CachedDataItemValueRef<'a>) -> bool + 'l;
     fn invalidate_serialization(&mut self);
+    /// Removes the persisted cell contents of the task while keeping them in memory. Cells that
+    /// are missing after a restart are recomputed when read.
+    fn remove_persisted_cell_data(&mut self);
 }
 
 struct TaskGuardImpl<'a, B: BackingStorage> {
@@ -691,6 +750,37 @@ impl<B: BackingStorage> TaskGuard for TaskGuardImpl<'_, B> {
                 .add_persisting_items(count);
         }
     }
+
+    fn remove_persisted_cell_data(&mut self) {
+        if !self.backend.should_persist() || self.task_id.is_transient() {
+            return;
+        }
+        let mut count = 0;
+        let cell_data = self
+            .iter(CachedDataItemType::CellData)
+            .filter_map(|(key, value)| match (key, value) {
+                (
+                    CachedDataItemKey::CellData { cell },
+                    CachedDataItemValueRef::CellData { value },
+                ) if registry::get_value_type(value.0).is_serializable() => {
+                    count += 1;
+                    Some(CachedDataItem::CellData {
+                        cell,
+                        value: value.clone(),
+                    })
+                }
+                _ => None,
+            });
+        {
+            self.backend
+                .persisted_storage_log(TaskDataCategory::Data)
+                .unwrap()
+                .push_batch_remove(self.task_id, cell_data);
+            self.task
+                .persistance_state_mut()
+                .add_persisting_items(count);
+        }
+    }
 }
 
 macro_rules! impl_operation {
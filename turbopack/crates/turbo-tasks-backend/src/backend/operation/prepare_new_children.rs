@@ -10,12 +10,11 @@ use crate::backend::{
     },
 };
 
-const AGGREGATION_NUMBER_BUFFER_SPACE: u32 = 3;
-
 pub fn prepare_new_children(
     parent_task_id: TaskId,
     parent_task: &mut impl TaskGuard,
     new_children: &FxHashSet<TaskId>,
+    base_aggregation_number_increment: u32,
     queue: &mut AggregationUpdateQueue,
 ) {
     if new_children.is_empty() {
@@ -47,7 +46,7 @@ pub fn prepare_new_children(
     // be counting from the parent's aggregation number.
     if !is_aggregating_node(future_parent_aggregation) {
         let child_base_aggregation_number =
-            future_parent_aggregation + AGGREGATION_NUMBER_BUFFER_SPACE;
+            future_parent_aggregation + base_aggregation_number_increment;
         for &new_child in new_children.iter() {
             queue.push(AggregationUpdateJob::UpdateAggregationNumber {
                 task_id: new_child,
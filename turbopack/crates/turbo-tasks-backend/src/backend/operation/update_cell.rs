@@ -1,11 +1,11 @@
-use turbo_tasks::{backend::CellContent, CellId, TaskId};
+use turbo_tasks::{backend::CellContent, registry, CellId, TaskId, TypedSharedReference};
 
 #[cfg(feature = "trace_task_dirty")]
 use crate::backend::operation::invalidate::TaskDirtyCause;
 use crate::{
     backend::{
         operation::{ExecuteContext, InvalidateOperation, TaskGuard},
-        storage::{get_many, remove},
+        storage::{get, get_many, remove},
         TaskDataCategory,
     },
     data::{CachedDataItem, CachedDataItemKey},
@@ -15,11 +15,30 @@ pub struct UpdateCellOperation;
 
 impl UpdateCellOperation {
     pub fn run(task_id: TaskId, cell: CellId, content: CellContent, mut ctx: impl ExecuteContext) {
+        let _timer = ctx.operation_timer("update cell");
         let mut task = ctx.task(task_id, TaskDataCategory::All);
+
+        // We need to detect recomputation, because here the content has not actually changed (even
+        // if it's not equal to the old content, as not all values implement Eq). We have to
+        // assume that tasks are deterministic and pure.
+        let invalidate_dependents = ctx.should_track_dependencies()
+            && (task.has_key(&CachedDataItemKey::Dirty {})
+                ||
+                // This is a hack for the streaming hack. Stateful tasks are never recomputed, so this forces invalidation for them in case of this hack.
+                task.has_key(&CachedDataItemKey::Stateful {}));
+
+        let mut unchanged = false;
         let old_content = if let CellContent(Some(new_content)) = content {
+            let new_content = new_content.into_typed(cell.type_id);
+            // Dependents are only invalidated for recomputed cells, so only these are compared
+            if invalidate_dependents && ctx.should_check_cell_equality() {
+                if let Some(old_content) = get!(task, CellData { cell }) {
+                    unchanged = is_same_content(old_content, &new_content);
+                }
+            }
             task.insert(CachedDataItem::CellData {
                 cell,
-                value: new_content.into_typed(cell.type_id),
+                value: new_content,
             })
         } else {
             task.remove(&CachedDataItemKey::CellData { cell })
@@ -29,16 +48,7 @@ impl UpdateCellOperation {
             in_progress.event.notify(usize::MAX);
         }
 
-        // We need to detect recomputation, because here the content has not actually changed (even
-        // if it's not equal to the old content, as not all values implement Eq). We have to
-        // assume that tasks are deterministic and pure.
-
-        if ctx.should_track_dependencies()
-            && (task.has_key(&CachedDataItemKey::Dirty {})
-                ||
-                // This is a hack for the streaming hack. Stateful tasks are never recomputed, so this forces invalidation for them in case of this hack.
-                task.has_key(&CachedDataItemKey::Stateful {}))
-        {
+        if invalidate_dependents && !unchanged {
             let dependent = get_many!(
                 task,
                 CellDependent { cell: dependent_cell, task }
@@ -63,3 +73,18 @@ impl UpdateCellOperation {
         }
     }
 }
+
+/// Compares cell contents with the `PartialEq` implementation of their value type. Values don't
+/// necessarily implement `Eq` (e.g. `#[turbo_tasks::value(eq = "manual")]`), these are always
+/// considered as changed.
+fn is_same_content(old: &TypedSharedReference, new: &TypedSharedReference) -> bool {
+    if old == new {
+        return true;
+    }
+    if old.0 != new.0 {
+        return false;
+    }
+    registry::get_value_type(old.0)
+        .is_eq(&*old.1 .0, &*new.1 .0)
+        .unwrap_or(false)
+}
@@ -25,6 +25,7 @@ impl UpdateCollectibleOperation {
         mut count: i32,
         mut ctx: impl ExecuteContext,
     ) {
+        let _timer = ctx.operation_timer("update collectible");
         if !ctx.should_track_children() {
             // Collectibles are not supported without children tracking
             return;
@@ -1,8 +1,8 @@
-use std::{borrow::Cow, mem::take};
+use std::mem::take;
 
 use anyhow::{anyhow, Result};
 use serde::{Deserialize, Serialize};
-use turbo_tasks::{util::SharedError, RawVc, TaskId};
+use turbo_tasks::{backend::TurboTasksPanic, RawVc, TaskId};
 
 #[cfg(feature = "trace_task_dirty")]
 use crate::backend::operation::invalidate::TaskDirtyCause;
@@ -12,12 +12,12 @@ use crate::{
             invalidate::{make_task_dirty, make_task_dirty_internal},
             AggregationUpdateQueue, ExecuteContext, Operation, TaskGuard,
         },
-        storage::{get, get_many},
+        storage::{get, get_many, remove},
         TaskDataCategory,
     },
     data::{
         CachedDataItem, CachedDataItemKey, CellRef, InProgressState, InProgressStateInner,
-        OutputValue,
+        OutputValue, TaskError,
     },
 };
 
@@ -44,9 +44,10 @@ pub enum UpdateOutputOperation {
 impl UpdateOutputOperation {
     pub fn run(
         task_id: TaskId,
-        output: Result<Result<RawVc>, Option<Cow<'static, str>>>,
+        output: Result<Result<RawVc>, TurboTasksPanic>,
         mut ctx: impl ExecuteContext,
     ) {
+        let _timer = ctx.operation_timer("update output");
         let mut task = ctx.task(task_id, TaskDataCategory::Meta);
         let Some(InProgressState::InProgress(box InProgressStateInner {
             stale,
@@ -65,52 +66,71 @@ impl UpdateOutputOperation {
             .then(|| new_children.iter().copied().collect())
             .unwrap_or_default();
 
-        let old_error = task.remove(&CachedDataItemKey::Error {});
-        let current_output = get!(task, Output);
+        let old_error = remove!(task, Error);
+        let current_output = get!(task, Output).copied();
         let output_value = match output {
             Ok(Ok(RawVc::TaskOutput(output_task_id))) => {
-                if let Some(OutputValue::Output(current_task_id)) = current_output {
-                    if *current_task_id == output_task_id {
-                        return;
-                    }
+                if current_output == Some(OutputValue::Output(output_task_id)) {
+                    return;
                 }
                 OutputValue::Output(output_task_id)
             }
             Ok(Ok(RawVc::TaskCell(output_task_id, cell))) => {
-                if let Some(OutputValue::Cell(CellRef {
-                    task: current_task_id,
-                    cell: current_cell,
-                })) = current_output
-                {
-                    if *current_task_id == output_task_id && *current_cell == cell {
-                        return;
-                    }
-                }
-                OutputValue::Cell(CellRef {
+                let output_value = OutputValue::Cell(CellRef {
                     task: output_task_id,
                     cell,
-                })
+                });
+                if current_output == Some(output_value) {
+                    return;
+                }
+                output_value
             }
             Ok(Ok(RawVc::LocalOutput(_, _))) => {
                 panic!("LocalOutput must not be output of a task");
             }
             Ok(Err(err)) => {
-                task.insert(CachedDataItem::Error {
-                    value: SharedError::new(err.context(format!(
+                let error = TaskError::from_error(
+                    task_id,
+                    err.context(format!(
                         "Execution of {} failed",
                         ctx.get_task_description(task_id)
-                    ))),
+                    )),
+                );
+                let unchanged = current_output == Some(OutputValue::Error)
+                    && old_error
+                        .as_ref()
+                        .is_some_and(|old| is_same_error(old, &error));
+                task.insert(CachedDataItem::Error {
+                    value: Box::new(error),
                 });
+                if unchanged {
+                    // The task failed again with the same error. Dependent tasks would read the
+                    // same result, so we don't need to invalidate them.
+                    return;
+                }
                 OutputValue::Error
             }
             Err(panic) => {
+                // The backtrace of the panic is not available here and the one of this error would
+                // point to the backend, so none is recorded
+                let error = TaskError {
+                    task: task_id,
+                    error: anyhow!("Panic in {}: {}", ctx.get_task_description(task_id), panic)
+                        .into(),
+                    backtrace: None,
+                    upstream_tasks: Vec::new(),
+                    panic: Some(panic),
+                };
+                let unchanged = current_output == Some(OutputValue::Panic)
+                    && old_error
+                        .as_ref()
+                        .is_some_and(|old| is_same_error(old, &error));
                 task.insert(CachedDataItem::Error {
-                    value: SharedError::new(anyhow!(
-                        "Panic in {}: {:?}",
-                        ctx.get_task_description(task_id),
-                        panic
-                    )),
+                    value: Box::new(error),
                 });
+                if unchanged {
+                    return;
+                }
                 OutputValue::Panic
             }
         };
@@ -150,6 +170,17 @@ impl UpdateOutputOperation {
     }
 }
 
+/// Compares two errors by their messages, including the whole chain of causes, and by the failed
+/// upstream tasks. Errors are not comparable by value, but a task that fails with the same
+/// messages is considered unchanged.
+fn is_same_error(a: &TaskError, b: &TaskError) -> bool {
+    a.upstream_tasks == b.upstream_tasks
+        && a.error
+            .chain()
+            .map(|e| e.to_string())
+            .eq(b.error.chain().map(|e| e.to_string()))
+}
+
 impl Operation for UpdateOutputOperation {
     fn execute(mut self, ctx: &mut impl ExecuteContext) {
         loop {
@@ -0,0 +1,159 @@
+use std::{
+    cell::Cell,
+    sync::OnceLock,
+    time::{Duration, Instant},
+};
+
+use turbo_tasks::FxDashMap;
+
+/// The number of buckets of a [`LatencyHistogram`]. Bucket `i` counts latencies below `2^i`
+/// microseconds, the last bucket counts all longer latencies.
+const BUCKETS: usize = 24;
+
+/// The operation name under which the time suspended for snapshots is recorded.
+const SUSPENDED_OPERATION: &str = "suspended for snapshot";
+
+thread_local! {
+    /// The total time operations on this thread were suspended for snapshots. Operations run
+    /// synchronously on a single thread, so timers subtract what was added while they ran.
+    static SUSPENDED: Cell<Duration> = const { Cell::new(Duration::ZERO) };
+}
+
+/// Latencies of a single kind of operation in power-of-two buckets.
+#[derive(Debug, Default, Clone)]
+pub struct LatencyHistogram {
+    /// The number of recorded latencies.
+    pub count: u64,
+    /// The summed duration of all recorded latencies.
+    pub total: Duration,
+    /// The longest recorded latency.
+    pub max: Duration,
+    /// Bucket `i` counts the latencies below `2^i` microseconds that didn't fit into a smaller
+    /// bucket.
+    pub buckets: [u64; BUCKETS],
+}
+
+impl LatencyHistogram {
+    fn record(&mut self, latency: Duration) {
+        self.count += 1;
+        self.total += latency;
+        self.max = self.max.max(latency);
+        let micros = latency.as_micros().min(u64::MAX as u128) as u64;
+        let bucket = (u64::BITS - micros.leading_zeros()) as usize;
+        self.buckets[bucket.min(BUCKETS - 1)] += 1;
+    }
+
+    /// Returns an upper bound of the latency below which `percentile` (0.0 to 1.0) of the
+    /// recorded latencies are. It's the upper boundary of the bucket, capped at the maximum.
+    pub fn percentile(&self, percentile: f64) -> Duration {
+        let target = (self.count as f64 * percentile).ceil() as u64;
+        let mut seen = 0;
+        for (i, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= target && seen > 0 {
+                if i == BUCKETS - 1 {
+                    return self.max;
+                }
+                return Duration::from_micros(1 << i).min(self.max);
+            }
+        }
+        self.max
+    }
+}
+
+/// Collects latency histograms per operation once enabled. Disabled by default, since it costs a
+/// clock read and a map update for every operation.
+#[derive(Default)]
+pub struct OperationStatistics {
+    inner: OnceLock<FxDashMap<&'static str, LatencyHistogram>>,
+}
+
+impl OperationStatistics {
+    pub fn enable(&self) {
+        self.inner.get_or_init(FxDashMap::default);
+    }
+
+    /// Starts timing an operation. The latency is recorded when the timer is dropped. Time
+    /// spent in [`OperationStatistics::suspend`] meanwhile is not included.
+    pub fn timer(&self, operation: &'static str) -> OperationTimer<'_> {
+        OperationTimer {
+            inner: self
+                .inner
+                .get()
+                .map(|inner| (inner, operation, Instant::now(), SUSPENDED.get())),
+        }
+    }
+
+    /// Runs `f`, which suspends the current operation for a snapshot. The time is recorded on
+    /// its own and not counted for the operations in progress on this thread.
+    pub fn suspend<R>(&self, f: impl FnOnce() -> R) -> R {
+        let Some(inner) = self.inner.get() else {
+            return f();
+        };
+        let start = Instant::now();
+        let result = f();
+        let suspended = start.elapsed();
+        SUSPENDED.set(SUSPENDED.get() + suspended);
+        inner
+            .entry(SUSPENDED_OPERATION)
+            .or_default()
+            .record(suspended);
+        result
+    }
+
+    /// Returns the histograms of all recorded operations, sorted by total duration, longest
+    /// first.
+    pub fn get(&self) -> Vec<(&'static str, LatencyHistogram)> {
+        let Some(inner) = self.inner.get() else {
+            return Vec::new();
+        };
+        let mut stats = inner
+            .iter()
+            .map(|entry| (*entry.key(), entry.value().clone()))
+            .collect::<Vec<_>>();
+        stats.sort_by(|(_, a), (_, b)| b.total.cmp(&a.total));
+        stats
+    }
+}
+
+pub struct OperationTimer<'a> {
+    inner: Option<(
+        &'a FxDashMap<&'static str, LatencyHistogram>,
+        &'static str,
+        Instant,
+        Duration,
+    )>,
+}
+
+impl Drop for OperationTimer<'_> {
+    fn drop(&mut self) {
+        if let Some((inner, operation, start, suspended_at_start)) = self.inner.take() {
+            let suspended = SUSPENDED.get() - suspended_at_start;
+            inner
+                .entry(operation)
+                .or_default()
+                .record(start.elapsed().saturating_sub(suspended));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::LatencyHistogram;
+
+    #[test]
+    fn test_percentile() {
+        let mut histogram = LatencyHistogram::default();
+        for _ in 0..99 {
+            histogram.record(Duration::from_micros(3));
+        }
+        histogram.record(Duration::from_millis(5));
+        assert_eq!(histogram.count, 100);
+        assert_eq!(histogram.buckets[2], 99);
+        assert_eq!(histogram.percentile(0.5), Duration::from_micros(4));
+        assert_eq!(histogram.percentile(0.99), Duration::from_micros(4));
+        assert_eq!(histogram.percentile(1.0), Duration::from_millis(5));
+    }
+}
@@ -1,3 +1,5 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
 use turbo_tasks::{KeyValuePair, TaskId};
 
 use crate::{
@@ -22,12 +24,16 @@ impl ShardData {
 
 pub struct PersistedStorageLog {
     data: Sharded<ShardData>,
+    /// The number of updates in all shards. Updated while the shard is locked, so that taking
+    /// the log never sees updates that aren't counted yet.
+    len: AtomicUsize,
 }
 
 impl PersistedStorageLog {
     pub fn new(shard_amount: usize) -> Self {
         Self {
             data: Sharded::new(shard_amount),
+            len: AtomicUsize::new(0),
         }
     }
 
@@ -39,6 +45,7 @@ impl PersistedStorageLog {
         new_value: Option<CachedDataItemValue>,
     ) {
         let mut guard = self.data.lock(task);
+        let len = guard.data.len();
         guard.set_task(task);
         match (old_value, new_value) {
             (None, None) => {}
@@ -57,6 +64,8 @@ impl PersistedStorageLog {
                     .push(CachedDataUpdate::Replace2 { value: new_value });
             }
         }
+        self.len
+            .fetch_add(guard.data.len() - len, Ordering::Relaxed);
     }
 
     pub fn push_batch_insert(
@@ -68,11 +77,53 @@ impl PersistedStorageLog {
             .into_iter()
             .map(|item| CachedDataUpdate::New { item });
         let mut guard = self.data.lock(task);
+        let len = guard.data.len();
         guard.set_task(task);
         guard.data.extend(updates);
+        self.len
+            .fetch_add(guard.data.len() - len, Ordering::Relaxed);
+    }
+
+    pub fn push_batch_remove(
+        &self,
+        task: TaskId,
+        updates: impl IntoIterator<Item = CachedDataItem>,
+    ) {
+        let updates = updates
+            .into_iter()
+            .map(|old_item| CachedDataUpdate::Removed { old_item });
+        let mut guard = self.data.lock(task);
+        let len = guard.data.len();
+        guard.set_task(task);
+        guard.data.extend(updates);
+        self.len
+            .fetch_add(guard.data.len() - len, Ordering::Relaxed);
+    }
+
+    /// The number of pending updates, including the task markers.
+    pub fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+
+    /// Visits all pending updates with the task they belong to.
+    pub fn for_each(&self, mut f: impl FnMut(TaskId, &CachedDataUpdate)) {
+        self.data.for_each(|shard| {
+            let mut task = None;
+            for update in shard.data.iter() {
+                if let CachedDataUpdate::Task { task: new_task } = update {
+                    task = Some(*new_task);
+                }
+                if let Some(task) = task {
+                    f(task, update);
+                }
+            }
+        });
     }
 
     pub fn take(&self) -> Vec<ChunkedVec<CachedDataUpdate>> {
-        self.data.take(|shard| shard.data)
+        let data = self.data.take(|shard| shard.data);
+        let len = data.iter().map(|shard| shard.len()).sum();
+        self.len.fetch_sub(len, Ordering::Relaxed);
+        data
     }
 }
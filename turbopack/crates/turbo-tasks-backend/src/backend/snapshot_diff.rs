@@ -0,0 +1,104 @@
+use rustc_hash::FxHashMap;
+use turbo_tasks::{KeyValuePair, TaskId};
+
+use crate::{
+    backend::TurboTasksBackendInner, backing_storage::BackingStorage, data::CachedDataUpdate,
+};
+
+/// The data that the next snapshot would persist, by task.
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotDiff {
+    /// Tasks that were created since the last snapshot.
+    pub added_tasks: Vec<TaskId>,
+    /// Tasks with modified data since the last snapshot, the most modified first.
+    pub changed_tasks: Vec<ChangedTask>,
+    /// Invalidation keys with new registrations or states.
+    pub invalidation_keys: Vec<String>,
+}
+
+impl SnapshotDiff {
+    /// Returns false when the next snapshot would have nothing to persist.
+    pub fn has_changes(&self) -> bool {
+        !self.added_tasks.is_empty()
+            || !self.changed_tasks.is_empty()
+            || !self.invalidation_keys.is_empty()
+    }
+}
+
+/// The modified data of a task, see [`SnapshotDiff`].
+#[derive(Debug, Clone)]
+pub struct ChangedTask {
+    pub task: TaskId,
+    /// The number of added, removed or replaced data items.
+    pub updates: usize,
+    /// The number of updates by the type of the data item, e.g. `OutputDependency`, the most
+    /// frequent first.
+    pub updates_by_type: Vec<(String, usize)>,
+}
+
+impl<B: BackingStorage> TurboTasksBackendInner<B> {
+    /// Collects the updates that were logged since the last snapshot. Tasks are never removed
+    /// from the persistent cache, so there are only added and changed tasks.
+    ///
+    /// The logs are read shard by shard while operations continue, so the result is not a
+    /// consistent view while tasks are executing.
+    pub(super) fn pending_snapshot_diff(&self) -> SnapshotDiff {
+        let _span = tracing::trace_span!("pending snapshot diff").entered();
+        let mut added_tasks = Vec::new();
+        if let Some(log) = &self.persisted_task_cache_log {
+            log.for_each(|shard| added_tasks.extend(shard.iter().map(|(_, task_id)| *task_id)));
+        }
+        added_tasks.sort_unstable();
+
+        let mut changes: FxHashMap<TaskId, FxHashMap<_, usize>> = FxHashMap::default();
+        for log in [
+            &self.persisted_storage_meta_log,
+            &self.persisted_storage_data_log,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            log.for_each(|task_id, update| {
+                let ty = match update {
+                    CachedDataUpdate::New { item } => item.ty(),
+                    CachedDataUpdate::Removed { old_item } => old_item.ty(),
+                    CachedDataUpdate::Replace1 { old_item } => old_item.ty(),
+                    // The second step of a replacement belongs to the first one
+                    CachedDataUpdate::Task { .. } | CachedDataUpdate::Replace2 { .. } => return,
+                };
+                *changes.entry(task_id).or_default().entry(ty).or_default() += 1;
+            });
+        }
+        let mut changed_tasks = changes
+            .into_iter()
+            .map(|(task, by_type)| {
+                let mut updates_by_type = by_type
+                    .into_iter()
+                    .map(|(ty, count)| (format!("{ty:?}"), count))
+                    .collect::<Vec<_>>();
+                updates_by_type.sort_by(|(_, a), (_, b)| b.cmp(a));
+                ChangedTask {
+                    task,
+                    updates: updates_by_type.iter().map(|(_, count)| count).sum(),
+                    updates_by_type,
+                }
+            })
+            .collect::<Vec<_>>();
+        changed_tasks.sort_by(|a, b| b.updates.cmp(&a.updates));
+
+        let mut invalidation_keys = Vec::new();
+        if let Some(log) = &self.persisted_invalidation_key_log {
+            let log = log.lock();
+            invalidation_keys.extend(log.tasks.keys().map(|(key, _)| key.clone()));
+            invalidation_keys.extend(log.states.keys().cloned());
+        }
+        invalidation_keys.sort_unstable();
+        invalidation_keys.dedup();
+
+        SnapshotDiff {
+            added_tasks,
+            changed_tasks,
+            invalidation_keys,
+        }
+    }
+}
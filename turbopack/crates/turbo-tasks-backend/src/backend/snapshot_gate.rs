@@ -0,0 +1,313 @@
+//! Synchronization between operations and snapshots. A snapshot must only capture the data when
+//! no operation is in the middle of modifying it, so operations either complete or suspend at a
+//! suspend point while a snapshot is taken.
+//!
+//! The protocol can be model checked with loom:
+//! `RUSTFLAGS="--cfg loom" cargo test -p turbo-tasks-backend --release --lib snapshot_gate`
+
+use std::{
+    hash::Hash,
+    time::{Duration, Instant},
+};
+
+use rustc_hash::FxHashSet;
+
+use self::sync::{lock, AtomicUsize, Condvar, Mutex, MutexGuard, Ordering};
+
+const SNAPSHOT_REQUESTED_BIT: usize = 1 << (usize::BITS - 1);
+
+pub struct SnapshotGate<T> {
+    /// Number of executing operations + Highest bit is set when snapshot is requested. When that
+    /// bit is set, operations should pause until the snapshot is completed. When the bit is set
+    /// and in progress counter reaches zero, `operations_suspended` is triggered.
+    in_progress_operations: AtomicUsize,
+    state: Mutex<GateState<T>>,
+    /// Condition Variable that is triggered when `in_progress_operations` reaches zero while
+    /// snapshot is requested. All operations are either completed or suspended.
+    operations_suspended: Condvar,
+    /// Condition Variable that is triggered when a snapshot is completed and operations can
+    /// continue.
+    snapshot_completed: Condvar,
+}
+
+struct GateState<T> {
+    snapshot_requested: bool,
+    suspended_operations: FxHashSet<T>,
+}
+
+impl<T: Clone + Hash + Eq> SnapshotGate<T> {
+    pub fn new() -> Self {
+        Self {
+            in_progress_operations: AtomicUsize::new(0),
+            state: Mutex::new(GateState {
+                snapshot_requested: false,
+                suspended_operations: FxHashSet::default(),
+            }),
+            operations_suspended: Condvar::new(),
+            snapshot_completed: Condvar::new(),
+        }
+    }
+
+    /// Returns true when a snapshot is requested and operations should suspend at the next
+    /// suspend point.
+    pub fn snapshot_requested(&self) -> bool {
+        (self.in_progress_operations.load(Ordering::Relaxed) & SNAPSHOT_REQUESTED_BIT) != 0
+    }
+
+    /// The number of operations that are executing and not suspended.
+    pub fn operations_in_progress(&self) -> usize {
+        self.in_progress_operations.load(Ordering::Acquire) & !SNAPSHOT_REQUESTED_BIT
+    }
+
+    /// Registers a new operation. Blocks while a snapshot is taken.
+    pub fn start_operation(&self) {
+        let fetch_add = self.in_progress_operations.fetch_add(1, Ordering::AcqRel);
+        if (fetch_add & SNAPSHOT_REQUESTED_BIT) != 0 {
+            let state = lock(&self.state);
+            if state.snapshot_requested {
+                let value = self.in_progress_operations.fetch_sub(1, Ordering::AcqRel) - 1;
+                if value == SNAPSHOT_REQUESTED_BIT {
+                    self.operations_suspended.notify_all();
+                }
+                let _state = self.wait_for_snapshot(state);
+                self.in_progress_operations.fetch_add(1, Ordering::AcqRel);
+            }
+        }
+    }
+
+    /// Unregisters a completed operation.
+    pub fn finish_operation(&self) {
+        let fetch_sub = self.in_progress_operations.fetch_sub(1, Ordering::AcqRel);
+        if fetch_sub - 1 == SNAPSHOT_REQUESTED_BIT {
+            // The snapshot checks the counter while holding the lock before it waits. Notifying
+            // without the lock could happen between the check and the wait and get lost.
+            let _state = lock(&self.state);
+            self.operations_suspended.notify_all();
+        }
+    }
+
+    /// Suspends the current operation while a snapshot is taken. `operation` is the state of
+    /// the operation, which is part of the snapshot.
+    pub fn suspend_operation(&self, operation: T) {
+        let mut state = lock(&self.state);
+        if !state.snapshot_requested {
+            return;
+        }
+        state.suspended_operations.insert(operation.clone());
+        let value = self.in_progress_operations.fetch_sub(1, Ordering::AcqRel) - 1;
+        assert!((value & SNAPSHOT_REQUESTED_BIT) != 0);
+        if value == SNAPSHOT_REQUESTED_BIT {
+            self.operations_suspended.notify_all();
+        }
+        let mut state = self.wait_for_snapshot(state);
+        self.in_progress_operations.fetch_add(1, Ordering::AcqRel);
+        state.suspended_operations.remove(&operation);
+    }
+
+    /// Requests a snapshot and blocks until all operations are either completed or suspended.
+    /// `on_request` is called while the request is registered. Returns the suspended
+    /// operations.
+    ///
+    /// Every time waiting for the operations takes another `stall_threshold`, `on_stall` is
+    /// called with the number of operations in progress and the time waited so far.
+    ///
+    /// Operations stay blocked until [`SnapshotGate::finish_snapshot`] is called.
+    pub fn request_snapshot(
+        &self,
+        on_request: impl FnOnce(),
+        stall_threshold: Option<Duration>,
+        mut on_stall: impl FnMut(usize, Duration),
+    ) -> Vec<T> {
+        let mut state = lock(&self.state);
+        state.snapshot_requested = true;
+        on_request();
+        let active_operations = self
+            .in_progress_operations
+            .fetch_or(SNAPSHOT_REQUESTED_BIT, Ordering::AcqRel);
+        if active_operations != 0 {
+            let start = Instant::now();
+            let mut next_stall_report = stall_threshold;
+            while self.in_progress_operations.load(Ordering::Acquire) != SNAPSHOT_REQUESTED_BIT {
+                let Some(report_after) = next_stall_report else {
+                    state = self.operations_suspended.wait(state);
+                    continue;
+                };
+                let elapsed = start.elapsed();
+                if elapsed >= report_after {
+                    on_stall(self.operations_in_progress(), elapsed);
+                    next_stall_report = stall_threshold.map(|threshold| report_after + threshold);
+                    continue;
+                }
+                state = self
+                    .operations_suspended
+                    .wait_for(state, report_after - elapsed);
+            }
+        }
+        state.suspended_operations.iter().cloned().collect()
+    }
+
+    /// Lets suspended and blocked operations continue after a snapshot was captured.
+    pub fn finish_snapshot(&self) {
+        let mut state = lock(&self.state);
+        state.snapshot_requested = false;
+        self.in_progress_operations
+            .fetch_sub(SNAPSHOT_REQUESTED_BIT, Ordering::AcqRel);
+        self.snapshot_completed.notify_all();
+    }
+
+    fn wait_for_snapshot<'a>(
+        &self,
+        mut state: MutexGuard<'a, GateState<T>>,
+    ) -> MutexGuard<'a, GateState<T>> {
+        while state.snapshot_requested {
+            state = self.snapshot_completed.wait(state);
+        }
+        state
+    }
+}
+
+#[cfg(not(loom))]
+mod sync {
+    pub use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
+
+    pub use parking_lot::{Mutex, MutexGuard};
+
+    pub struct Condvar(parking_lot::Condvar);
+
+    impl Condvar {
+        pub fn new() -> Self {
+            Self(parking_lot::Condvar::new())
+        }
+
+        pub fn notify_all(&self) {
+            self.0.notify_all();
+        }
+
+        pub fn wait<'a, T>(&self, mut guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+            self.0.wait(&mut guard);
+            guard
+        }
+
+        pub fn wait_for<'a, T>(
+            &self,
+            mut guard: MutexGuard<'a, T>,
+            timeout: Duration,
+        ) -> MutexGuard<'a, T> {
+            self.0.wait_for(&mut guard, timeout);
+            guard
+        }
+    }
+
+    pub fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+        mutex.lock()
+    }
+}
+
+#[cfg(loom)]
+mod sync {
+    use std::time::Duration;
+
+    pub use loom::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex, MutexGuard,
+    };
+
+    pub struct Condvar(loom::sync::Condvar);
+
+    impl Condvar {
+        pub fn new() -> Self {
+            Self(loom::sync::Condvar::new())
+        }
+
+        pub fn notify_all(&self) {
+            self.0.notify_all();
+        }
+
+        pub fn wait<'a, T>(&self, guard: MutexGuard<'a, T>) -> MutexGuard<'a, T> {
+            self.0.wait(guard).unwrap()
+        }
+
+        /// Time doesn't pass in the model, so this waits like [`Condvar::wait`].
+        pub fn wait_for<'a, T>(
+            &self,
+            guard: MutexGuard<'a, T>,
+            _timeout: Duration,
+        ) -> MutexGuard<'a, T> {
+            self.wait(guard)
+        }
+    }
+
+    pub fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+        mutex.lock().unwrap()
+    }
+}
+
+#[cfg(all(test, loom))]
+mod tests {
+    use loom::{
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc,
+        },
+        thread,
+    };
+
+    use super::SnapshotGate;
+
+    #[test]
+    fn snapshot_waits_for_operations() {
+        loom::model(|| {
+            let gate = Arc::new(SnapshotGate::<u32>::new());
+            let in_operation = Arc::new(AtomicBool::new(false));
+            let operation = {
+                let gate = gate.clone();
+                let in_operation = in_operation.clone();
+                thread::spawn(move || {
+                    gate.start_operation();
+                    in_operation.store(true, Ordering::SeqCst);
+                    in_operation.store(false, Ordering::SeqCst);
+                    gate.finish_operation();
+                })
+            };
+
+            let suspended = gate.request_snapshot(|| {}, None, |_, _| {});
+            assert!(!in_operation.load(Ordering::SeqCst));
+            assert!(suspended.is_empty());
+            gate.finish_snapshot();
+
+            operation.join().unwrap();
+            assert_eq!(gate.operations_in_progress(), 0);
+        });
+    }
+
+    #[test]
+    fn operations_suspend_during_snapshot() {
+        loom::model(|| {
+            let gate = Arc::new(SnapshotGate::<u32>::new());
+            let in_operation = Arc::new(AtomicBool::new(false));
+            let operation = {
+                let gate = gate.clone();
+                let in_operation = in_operation.clone();
+                thread::spawn(move || {
+                    gate.start_operation();
+                    in_operation.store(true, Ordering::SeqCst);
+                    in_operation.store(false, Ordering::SeqCst);
+                    gate.suspend_operation(1);
+                    in_operation.store(true, Ordering::SeqCst);
+                    in_operation.store(false, Ordering::SeqCst);
+                    gate.finish_operation();
+                })
+            };
+
+            let suspended = gate.request_snapshot(|| {}, None, |_, _| {});
+            assert!(!in_operation.load(Ordering::SeqCst));
+            assert!(suspended.is_empty() || suspended == [1]);
+            gate.finish_snapshot();
+
+            operation.join().unwrap();
+            assert!(!gate.snapshot_requested());
+            assert_eq!(gate.operations_in_progress(), 0);
+        });
+    }
+}
@@ -1,7 +1,6 @@
 use std::{
     hash::Hash,
     ops::{Deref, DerefMut},
-    thread::available_parallelism,
 };
 
 use turbo_tasks::{FxDashMap, TaskId};
@@ -10,7 +9,8 @@ use crate::{
     backend::dynamic_storage::DynamicStorage,
     data::{
         AggregationNumber, CachedDataItem, CachedDataItemKey, CachedDataItemType,
-        CachedDataItemValue, CachedDataItemValueRef, CachedDataItemValueRefMut, OutputValue,
+        CachedDataItemValue, CachedDataItemValueRef, CachedDataItemValueRefMut,
+        DirtyContainerCount, DirtyState, OutputValue,
     },
     data_storage::{AutoMapStorage, OptionStorage},
     utils::dash_map_multi::{get_multiple_mut, RefMut},
@@ -18,6 +18,7 @@ use crate::{
 
 const META_UNRESTORED: u32 = 1 << 31;
 const DATA_UNRESTORED: u32 = 1 << 30;
+const PREFETCHED: u32 = 1 << 29;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum TaskDataCategory {
@@ -116,6 +117,19 @@ impl PersistanceState {
     pub fn is_restored(&self, category: TaskDataCategory) -> bool {
         (self.value & category.flag()) == 0
     }
+
+    /// Marks data that was restored by prefetching, before it was accessed.
+    pub fn set_prefetched(&mut self) {
+        self.value |= PREFETCHED;
+    }
+
+    pub fn is_prefetched(&self) -> bool {
+        (self.value & PREFETCHED) != 0
+    }
+
+    pub fn clear_prefetched(&mut self) {
+        self.value &= !PREFETCHED;
+    }
 }
 
 pub struct InnerStorage {
@@ -123,6 +137,11 @@ pub struct InnerStorage {
     output_dependent: AutoMapStorage<TaskId, ()>,
     output: OptionStorage<OutputValue>,
     upper: AutoMapStorage<TaskId, i32>,
+    // Small values that are read on nearly every task access. Storing them inline avoids a lookup
+    // in the dynamic storage.
+    dirty: OptionStorage<DirtyState>,
+    aggregated_dirty_container_count: OptionStorage<DirtyContainerCount>,
+    stateful: OptionStorage<()>,
     dynamic: DynamicStorage,
     persistance_state: PersistanceState,
 }
@@ -134,6 +153,9 @@ impl InnerStorage {
             output_dependent: Default::default(),
             output: Default::default(),
             upper: Default::default(),
+            dirty: Default::default(),
+            aggregated_dirty_container_count: Default::default(),
+            stateful: Default::default(),
             dynamic: DynamicStorage::new(),
             persistance_state: PersistanceState::default(),
         }
@@ -434,23 +456,32 @@ generate_inner_storage!(
     OutputDependent task => output_dependent,
     Output => output,
     Upper task => upper,
+    Dirty => dirty,
+    AggregatedDirtyContainerCount => aggregated_dirty_container_count,
+    Stateful => stateful,
 );
 
-enum InnerStorageIter<A, B, C, D, E> {
+enum InnerStorageIter<A, B, C, D, E, F, G, H> {
     AggregationNumber(A),
     OutputDependent(B),
     Output(C),
     Upper(D),
-    Dynamic(E),
+    Dirty(E),
+    AggregatedDirtyContainerCount(F),
+    Stateful(G),
+    Dynamic(H),
 }
 
-impl<T, A, B, C, D, E> Iterator for InnerStorageIter<A, B, C, D, E>
+impl<T, A, B, C, D, E, F, G, H> Iterator for InnerStorageIter<A, B, C, D, E, F, G, H>
 where
     A: Iterator<Item = T>,
     B: Iterator<Item = T>,
     C: Iterator<Item = T>,
     D: Iterator<Item = T>,
     E: Iterator<Item = T>,
+    F: Iterator<Item = T>,
+    G: Iterator<Item = T>,
+    H: Iterator<Item = T>,
 {
     type Item = T;
 
@@ -460,6 +491,9 @@ where
             InnerStorageIter::OutputDependent(iter) => iter.next(),
             InnerStorageIter::Output(iter) => iter.next(),
             InnerStorageIter::Upper(iter) => iter.next(),
+            InnerStorageIter::Dirty(iter) => iter.next(),
+            InnerStorageIter::AggregatedDirtyContainerCount(iter) => iter.next(),
+            InnerStorageIter::Stateful(iter) => iter.next(),
             InnerStorageIter::Dynamic(iter) => iter.next(),
         }
     }
@@ -496,6 +530,28 @@ impl InnerStorage {
                     CachedDataItemValueRef::OutputDependent { value },
                 )
             }))
+            .chain(self.dirty.iter().map(|(_, value)| {
+                (
+                    CachedDataItemKey::Dirty {},
+                    CachedDataItemValueRef::Dirty { value },
+                )
+            }))
+            .chain(
+                self.aggregated_dirty_container_count
+                    .iter()
+                    .map(|(_, value)| {
+                        (
+                            CachedDataItemKey::AggregatedDirtyContainerCount {},
+                            CachedDataItemValueRef::AggregatedDirtyContainerCount { value },
+                        )
+                    }),
+            )
+            .chain(self.stateful.iter().map(|(_, value)| {
+                (
+                    CachedDataItemKey::Stateful {},
+                    CachedDataItemValueRef::Stateful { value },
+                )
+            }))
     }
 }
 
@@ -504,9 +560,7 @@ pub struct Storage {
 }
 
 impl Storage {
-    pub fn new() -> Self {
-        let shard_amount =
-            (available_parallelism().map_or(4, |v| v.get()) * 64).next_power_of_two();
+    pub fn new(shard_amount: usize) -> Self {
         Self {
             map: FxDashMap::with_capacity_and_hasher_and_shard_amount(
                 1024 * 1024,
@@ -516,6 +570,14 @@ impl Storage {
         }
     }
 
+    /// Calls `f` for every task in memory. A shard stays locked while its tasks are visited, so
+    /// `f` must not access the storage.
+    pub fn for_each(&self, mut f: impl FnMut(TaskId, &InnerStorage)) {
+        for entry in self.map.iter() {
+            f(*entry.key(), entry.value());
+        }
+    }
+
     pub fn access_mut(&self, key: TaskId) -> StorageWriteGuard<'_> {
         let inner = match self.map.entry(key) {
             dashmap::mapref::entry::Entry::Occupied(e) => e.into_ref(),
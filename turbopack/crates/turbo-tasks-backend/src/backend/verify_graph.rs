@@ -0,0 +1,228 @@
+use std::fmt::Write;
+
+use rustc_hash::{FxHashMap, FxHashSet};
+use turbo_tasks::{CellId, TaskId};
+
+use crate::{
+    backend::{
+        operation::{get_aggregation_number, is_aggregating_node},
+        storage::{get, iter_many},
+        TaskDataCategory, TurboTasksBackendInner,
+    },
+    backing_storage::BackingStorage,
+    data::CellRef,
+};
+
+/// Upper bound for the number of violations in a report.
+const MAX_REPORTED_VIOLATIONS: usize = 100;
+
+/// The edges of a task that are relevant for the invariants, copied out of the storage so no
+/// locks are held while verifying.
+struct TaskEdges {
+    meta_restored: bool,
+    data_restored: bool,
+    in_progress: bool,
+    has_in_progress_cells: bool,
+    /// The task is dirty or contains dirty tasks in the current session.
+    dirty: bool,
+    aggregation_number: u32,
+    children: Vec<TaskId>,
+    uppers: Vec<TaskId>,
+    followers: Vec<TaskId>,
+    output_dependencies: Vec<TaskId>,
+    output_dependents: FxHashSet<TaskId>,
+    cell_dependencies: Vec<CellRef>,
+    cell_dependents: FxHashSet<(CellId, TaskId)>,
+    dirty_containers: FxHashSet<TaskId>,
+}
+
+impl<B: BackingStorage> TurboTasksBackendInner<B> {
+    /// Walks all tasks in memory and checks the invariants of the task graph. Panics with a
+    /// report of all violations.
+    ///
+    /// Only meaningful when no operations are in progress, so it's skipped otherwise. Operations
+    /// are blocked by the snapshot gate while walking the graph.
+    pub(super) fn verify_graph(&self) {
+        if self.snapshot_gate.operations_in_progress() != 0 {
+            return;
+        }
+        let _span = tracing::trace_span!("verify graph").entered();
+        // Snapshots use the gate too, so don't run concurrently to one
+        let _last_snapshot_result = self.last_snapshot_result.lock();
+        let suspended_operations = self.snapshot_gate.request_snapshot(|| {}, None, |_, _| {});
+        let report = if suspended_operations.is_empty() {
+            self.verify_graph_report()
+        } else {
+            // Suspended operations leave the graph in an intermediate state
+            None
+        };
+        self.snapshot_gate.finish_snapshot();
+        if let Some(report) = report {
+            panic!("{report}");
+        }
+    }
+
+    /// Checks the invariants of the task graph and returns a report of all violations. Must only
+    /// be called while no operations are in progress.
+    fn verify_graph_report(&self) -> Option<String> {
+        let session_id = self.session_id;
+        let mut tasks = FxHashMap::default();
+        self.storage.for_each(|task_id, task| {
+            let persistance_state = task.persistance_state();
+            let dirty = get!(task, Dirty).is_some_and(|dirty| dirty.get(session_id))
+                || get!(task, AggregatedDirtyContainerCount)
+                    .is_some_and(|count| count.get(session_id) > 0);
+            tasks.insert(
+                task_id,
+                TaskEdges {
+                    meta_restored: persistance_state.is_restored(TaskDataCategory::Meta),
+                    data_restored: persistance_state.is_restored(TaskDataCategory::Data),
+                    in_progress: get!(task, InProgress).is_some(),
+                    has_in_progress_cells: iter_many!(task, InProgressCell { cell } => cell)
+                        .next()
+                        .is_some(),
+                    dirty,
+                    aggregation_number: get_aggregation_number(task),
+                    children: iter_many!(task, Child { task } => task).collect(),
+                    uppers: iter_many!(task, Upper { task } count if *count > 0 => task).collect(),
+                    followers: iter_many!(task, Follower { task } count if *count > 0 => task)
+                        .collect(),
+                    output_dependencies: iter_many!(task, OutputDependency { target } => target)
+                        .collect(),
+                    output_dependents: iter_many!(task, OutputDependent { task } => task).collect(),
+                    cell_dependencies: iter_many!(task, CellDependency { target } => target)
+                        .collect(),
+                    cell_dependents: iter_many!(task, CellDependent { cell, task } => (cell, task))
+                        .collect(),
+                    dirty_containers: iter_many!(
+                        task,
+                        AggregatedDirtyContainer { task } count if count.get(session_id) > 0 => task
+                    )
+                    .collect(),
+                },
+            );
+        });
+
+        let mut violations = Vec::new();
+        let mut violation = |task_id: TaskId, message: String| {
+            if violations.len() < MAX_REPORTED_VIOLATIONS {
+                violations.push((task_id, message));
+            }
+        };
+        let is_known_task = |task_id: TaskId| {
+            tasks.contains_key(&task_id)
+                || self.transient_tasks.contains_key(&task_id)
+                || self.lookup_task_type(task_id).is_some()
+        };
+
+        for (&task_id, edges) in tasks.iter() {
+            if edges.has_in_progress_cells && !edges.in_progress {
+                violation(
+                    task_id,
+                    "has in progress cells, but is not in progress".to_string(),
+                );
+            }
+
+            for &child in edges.children.iter() {
+                if !is_known_task(child) {
+                    violation(task_id, format!("has unknown child {child}"));
+                }
+            }
+            // Aggregating tasks either aggregate their children or have them as followers
+            if self.should_track_children()
+                && edges.meta_restored
+                && is_aggregating_node(edges.aggregation_number)
+            {
+                for &child in edges.children.iter() {
+                    let Some(child_edges) = tasks.get(&child) else {
+                        continue;
+                    };
+                    if child_edges.meta_restored
+                        && !child_edges.uppers.contains(&task_id)
+                        && !edges.followers.contains(&child)
+                    {
+                        violation(
+                            task_id,
+                            format!("has child {child}, which is neither an inner nor a follower"),
+                        );
+                    }
+                }
+            }
+            for &follower in edges.followers.iter() {
+                if !is_known_task(follower) {
+                    violation(task_id, format!("has unknown follower {follower}"));
+                }
+            }
+            for &upper in edges.uppers.iter() {
+                if !is_known_task(upper) {
+                    violation(task_id, format!("has unknown upper {upper}"));
+                }
+            }
+
+            for &target in edges.output_dependencies.iter() {
+                let Some(target_edges) = tasks.get(&target) else {
+                    if !is_known_task(target) {
+                        violation(
+                            task_id,
+                            format!("depends on output of unknown task {target}"),
+                        );
+                    }
+                    continue;
+                };
+                if target_edges.data_restored && !target_edges.output_dependents.contains(&task_id)
+                {
+                    violation(
+                        task_id,
+                        format!("depends on output of {target}, which has no dependent edge back"),
+                    );
+                }
+            }
+            for &CellRef { task: target, cell } in edges.cell_dependencies.iter() {
+                let Some(target_edges) = tasks.get(&target) else {
+                    if !is_known_task(target) {
+                        violation(task_id, format!("depends on cell of unknown task {target}"));
+                    }
+                    continue;
+                };
+                if target_edges.data_restored
+                    && !target_edges.cell_dependents.contains(&(cell, task_id))
+                {
+                    violation(
+                        task_id,
+                        format!(
+                            "depends on cell {cell} of {target}, which has no dependent edge back"
+                        ),
+                    );
+                }
+            }
+
+            // Dirty tasks and tasks containing dirty tasks need to be tracked by all uppers
+            if self.should_track_children() && edges.meta_restored && edges.dirty {
+                for &upper in edges.uppers.iter() {
+                    let Some(upper_edges) = tasks.get(&upper) else {
+                        continue;
+                    };
+                    if upper_edges.meta_restored && !upper_edges.dirty_containers.contains(&task_id)
+                    {
+                        violation(
+                            task_id,
+                            format!("is dirty, but upper {upper} doesn't track it as dirty"),
+                        );
+                    }
+                }
+            }
+        }
+
+        if violations.is_empty() {
+            return None;
+        }
+        let mut report = format!(
+            "Task graph invariants violated ({} tasks checked):\n",
+            tasks.len()
+        );
+        for (task_id, message) in violations {
+            let _ = writeln!(report, "- {} {message}", self.get_task_description(task_id));
+        }
+        Some(report)
+    }
+}
@@ -0,0 +1,183 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rustc_hash::FxHashSet;
+use smallvec::SmallVec;
+use turbo_tasks::{FxDashMap, TaskId};
+
+/// Tracks which executing tasks are waiting for the output of which other tasks, to detect
+/// dependency cycles. A task that transitively awaits its own output would wait forever.
+///
+/// A wait is recorded until the output of the target is available, the reader received it, or
+/// the execution of the reader ended.
+#[derive(Default)]
+pub struct WaitGraph {
+    /// The tasks each reader waits for.
+    edges: FxDashMap<TaskId, SmallVec<[TaskId; 2]>>,
+    /// The readers waiting for each task, to remove the waits when the output is available.
+    waiters: FxDashMap<TaskId, SmallVec<[TaskId; 2]>>,
+    /// The number of edges, so that reads can skip the maps while no task is waiting.
+    edge_count: AtomicUsize,
+}
+
+impl WaitGraph {
+    /// Records that `reader` waits for the output of `target`. When that closes a cycle, the
+    /// edge is not recorded and the tasks of the cycle are returned, starting and ending with
+    /// `reader`.
+    ///
+    /// Only a new edge can close a cycle, so waiting again for the same target is cheap.
+    pub fn add_wait(&self, reader: TaskId, target: TaskId) -> Option<Vec<TaskId>> {
+        {
+            let mut targets = self.edges.entry(reader).or_default();
+            if targets.contains(&target) {
+                return None;
+            }
+            targets.push(target);
+            self.edge_count.fetch_add(1, Ordering::AcqRel);
+        }
+        self.waiters.entry(target).or_default().push(reader);
+        let cycle = self.find_path(target, reader)?;
+        self.remove_wait(reader, target);
+        Some([reader].into_iter().chain(cycle).collect())
+    }
+
+    /// Removes the edge when `reader` has received the output of `target`.
+    pub fn remove_wait(&self, reader: TaskId, target: TaskId) {
+        if self.edge_count.load(Ordering::Acquire) == 0 {
+            return;
+        }
+        if self.remove_edge(reader, target) {
+            remove_from(&self.waiters, target, reader);
+        }
+    }
+
+    /// Removes all edges to a task whose output is available, since its readers don't wait
+    /// anymore.
+    pub fn remove_target(&self, target: TaskId) {
+        if self.edge_count.load(Ordering::Acquire) == 0 {
+            return;
+        }
+        let Some((_, readers)) = self.waiters.remove(&target) else {
+            return;
+        };
+        for reader in readers {
+            self.remove_edge(reader, target);
+        }
+    }
+
+    /// Removes all edges of a task whose execution has ended.
+    pub fn remove_reader(&self, reader: TaskId) {
+        if self.edge_count.load(Ordering::Acquire) == 0 {
+            return;
+        }
+        if let Some((_, targets)) = self.edges.remove(&reader) {
+            self.edge_count.fetch_sub(targets.len(), Ordering::AcqRel);
+            for target in targets {
+                remove_from(&self.waiters, target, reader);
+            }
+        }
+    }
+
+    /// Removes the edge from `reader` to `target` and returns whether it existed.
+    fn remove_edge(&self, reader: TaskId, target: TaskId) -> bool {
+        let removed = remove_from(&self.edges, reader, target);
+        if removed {
+            self.edge_count.fetch_sub(1, Ordering::AcqRel);
+        }
+        removed
+    }
+
+    /// Finds a path of wait edges from `from` to `to` with a depth-first search. The returned
+    /// path starts with `from` and ends with `to`.
+    fn find_path(&self, from: TaskId, to: TaskId) -> Option<Vec<TaskId>> {
+        if from == to {
+            return Some(vec![from]);
+        }
+        let mut visited = FxHashSet::default();
+        let mut path = vec![from];
+        let mut stack = vec![self.targets(from).into_iter()];
+        visited.insert(from);
+        while let Some(targets) = stack.last_mut() {
+            let Some(next) = targets.next() else {
+                stack.pop();
+                path.pop();
+                continue;
+            };
+            if next == to {
+                path.push(to);
+                return Some(path);
+            }
+            if visited.insert(next) {
+                path.push(next);
+                stack.push(self.targets(next).into_iter());
+            }
+        }
+        None
+    }
+
+    fn targets(&self, reader: TaskId) -> SmallVec<[TaskId; 2]> {
+        // Clone the targets to avoid holding a lock while walking the graph
+        self.edges
+            .get(&reader)
+            .map(|targets| targets.clone())
+            .unwrap_or_default()
+    }
+}
+
+/// Removes `value` from the list of `key` and returns whether it was in the list. Empty lists
+/// are removed from the map.
+fn remove_from(map: &FxDashMap<TaskId, SmallVec<[TaskId; 2]>>, key: TaskId, value: TaskId) -> bool {
+    let mut removed = false;
+    drop(map.remove_if_mut(&key, |_, values| {
+        if let Some(index) = values.iter().position(|&v| v == value) {
+            values.swap_remove(index);
+            removed = true;
+        }
+        values.is_empty()
+    }));
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use turbo_tasks::TaskId;
+
+    use super::WaitGraph;
+
+    fn task(id: u32) -> TaskId {
+        TaskId::from(id)
+    }
+
+    #[test]
+    fn test_cycle() {
+        let graph = WaitGraph::default();
+        assert_eq!(graph.add_wait(task(1), task(2)), None);
+        assert_eq!(graph.add_wait(task(2), task(3)), None);
+        assert_eq!(
+            graph.add_wait(task(3), task(1)),
+            Some(vec![task(3), task(1), task(2), task(3)])
+        );
+        // The edge that would close the cycle is not recorded
+        assert_eq!(graph.add_wait(task(4), task(3)), None);
+    }
+
+    #[test]
+    fn test_resolved_wait() {
+        let graph = WaitGraph::default();
+        assert_eq!(graph.add_wait(task(1), task(2)), None);
+        assert_eq!(graph.add_wait(task(3), task(2)), None);
+        // The output of 2 is available, so neither 1 nor 3 waits for it anymore
+        graph.remove_target(task(2));
+        assert_eq!(graph.add_wait(task(2), task(1)), None);
+        assert_eq!(graph.add_wait(task(2), task(3)), None);
+
+        // 1 received the output of 2, 3 is still waiting
+        assert_eq!(graph.add_wait(task(1), task(4)), None);
+        assert_eq!(graph.add_wait(task(3), task(4)), None);
+        graph.remove_wait(task(1), task(4));
+        assert_eq!(graph.add_wait(task(4), task(1)), None);
+        assert_eq!(
+            graph.add_wait(task(4), task(3)),
+            Some(vec![task(4), task(3), task(4)])
+        );
+    }
+}
@@ -1,6 +1,7 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Duration};
 
 use anyhow::Result;
+use rustc_hash::FxHashMap;
 use turbo_tasks::{backend::CachedTaskType, SessionId, TaskId};
 
 use crate::{
@@ -9,6 +10,51 @@ use crate::{
     utils::chunked_vec::ChunkedVec,
 };
 
+/// Changes of the invalidation keys since the last snapshot.
+#[derive(Default)]
+pub struct InvalidationKeyUpdates {
+    /// Changed registrations of tasks for keys. `true` registers the task for the key, `false`
+    /// removes the registration.
+    pub tasks: FxHashMap<(String, TaskId), bool>,
+    /// New states of the external resources behind the keys. `None` removes the state.
+    pub states: FxHashMap<String, Option<Vec<u8>>>,
+}
+
+impl InvalidationKeyUpdates {
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty() && self.states.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.tasks.len() + self.states.len()
+    }
+}
+
+/// What was read from the persistent cache since startup, see
+/// [`BackingStorage::restore_statistics`].
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RestoreStatistics {
+    /// The number of task meta and data entries that were restored.
+    pub restored_task_data: u64,
+    /// The number of task cache lookups that found a persisted task.
+    pub restored_task_cache_entries: u64,
+    /// The number of bytes read for the restored entries.
+    pub bytes_read: u64,
+    /// The time spent reading and deserializing persisted entries.
+    pub duration: Duration,
+    /// The number of entries that were discarded because they are corrupted or were written by
+    /// an incompatible version.
+    pub discarded: u64,
+    /// The number of cells that were skipped within otherwise restored entries, e.g. because
+    /// their value type changed its serialization version.
+    pub discarded_cells: u64,
+}
+
+/// Persists the task graph between sessions.
+///
+/// A task cache entry can be persisted without any data of its task, e.g. when it was persisted
+/// ahead of a snapshot or the task didn't complete before it. Such tasks are recomputed when
+/// they are read. Task data is never persisted without its task cache entry.
 pub trait BackingStorage: 'static + Send + Sync {
     type ReadTransaction<'l>;
     fn lower_read_transaction<'l: 'i + 'r, 'i: 'r, 'r>(
@@ -17,6 +63,16 @@ pub trait BackingStorage: 'static + Send + Sync {
     fn next_free_task_id(&self) -> TaskId;
     fn next_session_id(&self) -> SessionId;
     fn uncompleted_operations(&self) -> Vec<AnyOperation>;
+    /// Persists all updates of a snapshot. The task cache updates, the task data updates, the
+    /// invalidation keys and the session id (which acts as epoch of the snapshot) must be
+    /// committed atomically, so that readers never observe task data without its task cache
+    /// entry. When an error is returned, none of the updates must be visible.
+    ///
+    /// Task cache entries may already be persisted by [`BackingStorage::save_task_cache`], since
+    /// persisted tasks without data are recomputed when they are read.
+    ///
+    /// `progress` is called with the number of processed updates and the number of bytes they
+    /// occupy, possibly concurrently from multiple threads.
     fn save_snapshot(
         &self,
         session_id: SessionId,
@@ -24,7 +80,24 @@ pub trait BackingStorage: 'static + Send + Sync {
         task_cache_updates: Vec<ChunkedVec<(Arc<CachedTaskType>, TaskId)>>,
         meta_updates: Vec<ChunkedVec<CachedDataUpdate>>,
         data_updates: Vec<ChunkedVec<CachedDataUpdate>>,
+        invalidation_key_updates: InvalidationKeyUpdates,
+        progress: &(dyn Fn(usize, u64) + Sync),
     ) -> Result<()>;
+    /// Returns true when [`BackingStorage::save_task_cache`] is implemented. Otherwise task cache
+    /// entries are only persisted with snapshots.
+    fn can_save_task_cache(&self) -> bool {
+        false
+    }
+    /// Persists task cache entries ahead of the next snapshot, so they don't need to be kept in
+    /// memory until then. The next free task id must be updated with the entries.
+    ///
+    /// Only called when [`BackingStorage::can_save_task_cache`] returns true.
+    fn save_task_cache(
+        &self,
+        _task_cache_updates: Vec<ChunkedVec<(Arc<CachedTaskType>, TaskId)>>,
+    ) -> Result<()> {
+        Ok(())
+    }
     fn start_read_transaction(&self) -> Option<Self::ReadTransaction<'_>>;
     /// # Safety
     ///
@@ -52,6 +125,75 @@ pub trait BackingStorage: 'static + Send + Sync {
         category: TaskDataCategory,
     ) -> Vec<CachedDataItem>;
 
+    /// Compacts the storage to reclaim disk space. Returns the number of bytes reclaimed.
+    fn compact(&self) -> Result<u64> {
+        Ok(0)
+    }
+
+    /// Returns all persisted tasks whose task type matches the predicate. This is slow, as it
+    /// needs to look at every persisted task.
+    fn persisted_tasks_matching(
+        &self,
+        _predicate: &dyn Fn(&CachedTaskType) -> bool,
+    ) -> Vec<TaskId> {
+        Vec::new()
+    }
+
+    /// Returns all persisted tasks that were registered for the invalidation key.
+    fn lookup_invalidation_key(&self, _key: &str) -> Vec<TaskId> {
+        Vec::new()
+    }
+
+    /// Returns the persisted states of all invalidation keys.
+    fn invalidation_key_states(&self) -> FxHashMap<String, Vec<u8>> {
+        FxHashMap::default()
+    }
+
+    /// Returns up to `limit` persisted tasks that were accessed in most of the recent sessions,
+    /// the most frequently accessed first.
+    fn hot_tasks(&self, _limit: usize) -> Vec<TaskId> {
+        Vec::new()
+    }
+
+    /// Updates the access frequencies with the persisted tasks that were accessed in this session.
+    /// Only the `limit` most frequently accessed tasks are kept.
+    fn save_task_accesses(&self, _accessed_tasks: Vec<TaskId>, _limit: usize) -> Result<()> {
+        Ok(())
+    }
+
+    /// Finds tasks with persisted data but without a task cache entry, and tasks whose task cache
+    /// entry can't be looked up anymore, which a crash can leave behind. Reads from a single
+    /// transaction and can run concurrently with snapshots.
+    fn find_orphaned_data(&self) -> Result<Vec<TaskId>> {
+        Ok(Vec::new())
+    }
+
+    /// Removes the persisted data of the `candidates` returned by
+    /// [`BackingStorage::find_orphaned_data`] that are still orphaned, and restores their missing
+    /// task cache entries. Must not run concurrently with snapshots. Returns the number of
+    /// affected tasks.
+    fn remove_orphaned_data(&self, _candidates: Vec<TaskId>) -> Result<usize> {
+        Ok(0)
+    }
+
+    /// Checks the persisted entries of `count` tasks for corrupted or inconsistent data and returns
+    /// the problems that were found. Every call continues after the tasks of the previous call,
+    /// so repeated calls cover all persisted tasks.
+    fn verify_tasks(&self, _count: usize) -> Result<Vec<(TaskId, String)>> {
+        Ok(Vec::new())
+    }
+
+    /// Removes all persisted data in one atomic step. The removal may only take effect on the next
+    /// start, so nothing must be persisted afterwards.
+    fn clear(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Returns what was read from the persistent cache since startup.
+    fn restore_statistics(&self) -> RestoreStatistics {
+        RestoreStatistics::default()
+    }
+
     fn shutdown(&self) -> Result<()> {
         Ok(())
     }
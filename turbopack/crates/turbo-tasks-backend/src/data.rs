@@ -1,8 +1,14 @@
-use std::cmp::Ordering;
+use std::{
+    backtrace::BacktraceStatus,
+    cmp::Ordering,
+    fmt::{self, Display},
+    iter::once,
+};
 
 use rustc_hash::FxHashSet;
 use serde::{Deserialize, Serialize};
 use turbo_tasks::{
+    backend::TurboTasksPanic,
     event::{Event, EventListener},
     registry,
     util::SharedError,
@@ -140,6 +146,61 @@ impl ActivenessState {
     }
 }
 
+/// The error of a failed or panicked task. It's persisted, so reading a failed task that was
+/// restored from the persistent cache reports the original failure without executing it again.
+///
+/// Errors read from other tasks are wrapped in this type, so a failure that was caused by an
+/// upstream task records the chain of failed tasks.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TaskError {
+    /// The failed task.
+    pub task: TaskId,
+    /// The error, including the messages of all of its causes.
+    pub error: SharedError,
+    /// The backtrace of the error, when one was captured.
+    pub backtrace: Option<String>,
+    /// The failed upstream tasks that caused this error, closest first.
+    pub upstream_tasks: Vec<TaskId>,
+    /// The caught panic, when the task panicked.
+    pub panic: Option<TurboTasksPanic>,
+}
+
+impl TaskError {
+    pub fn from_error(task: TaskId, error: anyhow::Error) -> Self {
+        let backtrace = error.backtrace();
+        let backtrace =
+            (backtrace.status() == BacktraceStatus::Captured).then(|| backtrace.to_string());
+        let upstream_tasks = error
+            .chain()
+            .find_map(|cause| cause.downcast_ref::<TaskError>())
+            .map(|upstream| {
+                once(upstream.task)
+                    .chain(upstream.upstream_tasks.iter().copied())
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            task,
+            error: SharedError::new(error),
+            backtrace,
+            upstream_tasks,
+            panic: None,
+        }
+    }
+}
+
+impl Display for TaskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.error, f)
+    }
+}
+
+impl std::error::Error for TaskError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
+
 transient_traits!(ActivenessState);
 
 impl Eq for ActivenessState {}
@@ -482,10 +543,8 @@ pub enum CachedDataItem {
         value: (),
     },
 
-    // Transient Error State
-    #[serde(skip)]
     Error {
-        value: SharedError,
+        value: Box<TaskError>,
     },
 }
 
@@ -522,7 +581,7 @@ impl CachedDataItem {
             CachedDataItem::OutdatedOutputDependency { .. } => false,
             CachedDataItem::OutdatedCellDependency { .. } => false,
             CachedDataItem::OutdatedCollectiblesDependency { .. } => false,
-            CachedDataItem::Error { .. } => false,
+            CachedDataItem::Error { .. } => true,
         }
     }
 
@@ -569,7 +628,8 @@ impl CachedDataItem {
             | Self::AggregatedDirtyContainer { .. }
             | Self::AggregatedCollectible { .. }
             | Self::AggregatedDirtyContainerCount { .. }
-            | Self::Stateful { .. } => TaskDataCategory::Meta,
+            | Self::Stateful { .. }
+            | Self::Error { .. } => TaskDataCategory::Meta,
 
             Self::OutdatedCollectible { .. }
             | Self::OutdatedOutputDependency { .. }
@@ -577,7 +637,6 @@ impl CachedDataItem {
             | Self::OutdatedCollectiblesDependency { .. }
             | Self::InProgressCell { .. }
             | Self::InProgress { .. }
-            | Self::Error { .. }
             | Self::Activeness { .. } => TaskDataCategory::All,
         }
     }
@@ -616,7 +675,7 @@ impl CachedDataItemKey {
             CachedDataItemKey::OutdatedOutputDependency { .. } => false,
             CachedDataItemKey::OutdatedCellDependency { .. } => false,
             CachedDataItemKey::OutdatedCollectiblesDependency { .. } => false,
-            CachedDataItemKey::Error { .. } => false,
+            CachedDataItemKey::Error { .. } => true,
         }
     }
 
@@ -651,7 +710,8 @@ impl CachedDataItemType {
             | Self::AggregatedDirtyContainer { .. }
             | Self::AggregatedCollectible { .. }
             | Self::AggregatedDirtyContainerCount { .. }
-            | Self::Stateful { .. } => TaskDataCategory::Meta,
+            | Self::Stateful { .. }
+            | Self::Error { .. } => TaskDataCategory::Meta,
 
             Self::OutdatedCollectible { .. }
             | Self::OutdatedOutputDependency { .. }
@@ -659,7 +719,6 @@ impl CachedDataItemType {
             | Self::OutdatedCollectiblesDependency { .. }
             | Self::InProgressCell { .. }
             | Self::InProgress { .. }
-            | Self::Error { .. }
             | Self::Activeness { .. } => TaskDataCategory::All,
         }
     }
@@ -0,0 +1,650 @@
+//! Cache profiles let different commands share a cache directory, e.g. `next dev` and
+//! `next build` with a different environment.
+//!
+//! The default profile writes the unprefixed keys. Another profile writes its mutable data
+//! (task data, sessions, invalidation keys) under a profile prefix and reads the data of the
+//! default profile where it has none of its own. Changed invalidation key states invalidate the
+//! tasks that are not valid for the profile, so only work that is still valid is reused. The task
+//! cache and the task id counter are immutable or append-only and shared by all profiles.
+//!
+//! The profile data is an overlay of the default profile data at a specific session. When the
+//! default profile persisted another session since, the overlay would mix two states of the task
+//! graph, so a new epoch with an empty overlay is started. The keys written in an epoch are
+//! recorded in index chunks, so the data of the outdated epoch is removed when the next one
+//! starts. Each key is only recorded once per epoch.
+
+use std::borrow::{Borrow, Cow};
+
+use anyhow::{bail, Result};
+use parking_lot::Mutex;
+use rustc_hash::FxHashSet;
+
+use crate::database::{
+    key_value_database::{KeySpace, KeyValueDatabase},
+    write_batch::{BaseWriteBatch, ConcurrentWriteBatch, SerialWriteBatch, WriteBatch},
+};
+
+const PROFILE_STATE_KEY_PREFIX: &[u8] = b"cache-profile:";
+const PROFILE_INDEX_KEY_PREFIX: &[u8] = b"cache-profile-index:";
+const PROFILE_KEY_PREFIX: &[u8] = b"profile:";
+const PROFILE_TOMBSTONE_KEY_PREFIX: &[u8] = b"profile-deleted:";
+
+pub struct CacheProfileLayer<T: KeyValueDatabase> {
+    database: T,
+    /// Prefix of the profile-scoped keys, which includes the name and the epoch.
+    prefix: Vec<u8>,
+    /// Prefix of the keys that mark profile-scoped keys as deleted, so that reads don't fall
+    /// back to the data of the default profile.
+    tombstone_prefix: Vec<u8>,
+    /// Prefix of the index chunks, which list the keys written in the epoch.
+    index_prefix: Vec<u8>,
+    /// Infra keys that are shared by all profiles.
+    shared_infra_keys: Vec<Vec<u8>>,
+    state_key: Vec<u8>,
+    epoch: u32,
+    /// The last persisted session of the default profile that the epoch is based on.
+    base_session: Vec<u8>,
+    /// Locked while a batch that writes an index chunk is committed, so the persisted count
+    /// never skips a chunk.
+    index: Mutex<EpochIndex>,
+}
+
+/// The index chunks of the epoch and the keys they list.
+struct EpochIndex {
+    chunks: u32,
+    /// The key space tags and keys that are recorded in the chunks.
+    keys: FxHashSet<(u8, Vec<u8>)>,
+}
+
+impl<T: KeyValueDatabase> CacheProfileLayer<T> {
+    /// `session_key` is the infra key of the last persisted session, which tells if the default
+    /// profile changed.
+    pub fn new(
+        database: T,
+        profile: &str,
+        session_key: &[u8],
+        shared_infra_keys: Vec<Vec<u8>>,
+    ) -> Result<Self> {
+        let state_key = [PROFILE_STATE_KEY_PREFIX, profile.as_bytes()].concat();
+        let (base_session, previous_state) = {
+            let tx = database.begin_read_transaction()?;
+            let base_session = database
+                .get(&tx, KeySpace::Infra, session_key)?
+                .map(|bytes| Borrow::<[u8]>::borrow(&bytes).to_vec())
+                .unwrap_or_default();
+            let previous_state = database
+                .get(&tx, KeySpace::Infra, &state_key)?
+                .map(|bytes| Borrow::<[u8]>::borrow(&bytes).to_vec());
+            (base_session, previous_state)
+        };
+        let (epoch, index_chunks, outdated) = match previous_state {
+            Some(state) if state.len() >= 8 => {
+                let epoch = u32::from_le_bytes(state[..4].try_into()?);
+                let index_chunks = u32::from_le_bytes(state[4..8].try_into()?);
+                if state[8..] == base_session[..] {
+                    (epoch, index_chunks, None)
+                } else {
+                    (epoch + 1, 0, Some((epoch, index_chunks)))
+                }
+            }
+            _ => (0, 0, None),
+        };
+        let profile_prefix = |prefix: &[u8], epoch: u32| {
+            [
+                prefix,
+                &(profile.len() as u32).to_le_bytes(),
+                profile.as_bytes(),
+                &epoch.to_le_bytes(),
+            ]
+            .concat()
+        };
+        let mut layer = Self {
+            prefix: profile_prefix(PROFILE_KEY_PREFIX, epoch),
+            tombstone_prefix: profile_prefix(PROFILE_TOMBSTONE_KEY_PREFIX, epoch),
+            index_prefix: profile_prefix(PROFILE_INDEX_KEY_PREFIX, epoch),
+            database,
+            shared_infra_keys,
+            state_key,
+            epoch,
+            base_session,
+            index: Mutex::new(EpochIndex {
+                chunks: index_chunks,
+                keys: FxHashSet::default(),
+            }),
+        };
+        if let Some((outdated_epoch, outdated_index_chunks)) = outdated {
+            layer.prune_epoch(
+                &profile_prefix(PROFILE_INDEX_KEY_PREFIX, outdated_epoch),
+                outdated_index_chunks,
+            )?;
+        } else {
+            let keys = layer.read_index(&layer.index_prefix, index_chunks)?;
+            layer.index.get_mut().keys = keys
+                .into_iter()
+                .map(|(key_space, key)| (key_space_tag(key_space), key))
+                .collect();
+        }
+        Ok(layer)
+    }
+
+    fn state(&self, index_chunks: u32) -> Vec<u8> {
+        [
+            &self.epoch.to_le_bytes()[..],
+            &index_chunks.to_le_bytes(),
+            &self.base_session,
+        ]
+        .concat()
+    }
+
+    /// Returns the keys that are listed in the index chunks of an epoch.
+    fn read_index(
+        &self,
+        index_prefix: &[u8],
+        index_chunks: u32,
+    ) -> Result<Vec<(KeySpace, Vec<u8>)>> {
+        let mut keys = Vec::new();
+        let tx = self.database.begin_read_transaction()?;
+        for chunk in 0..index_chunks {
+            let key = index_key(index_prefix, chunk);
+            if let Some(entries) = self.database.get(&tx, KeySpace::Infra, &key)? {
+                decode_index_chunk(Borrow::<[u8]>::borrow(&entries), &mut keys)?;
+            }
+        }
+        Ok(keys)
+    }
+
+    /// Removes the keys that were written in an outdated epoch and starts the current one, in a
+    /// single batch.
+    fn prune_epoch(&self, index_prefix: &[u8], index_chunks: u32) -> Result<()> {
+        let keys = self.read_index(index_prefix, index_chunks)?;
+        let mut batch = self.database.write_batch()?;
+        for (key_space, key) in keys {
+            batch.delete(key_space, Cow::Owned(key))?;
+        }
+        for chunk in 0..index_chunks {
+            batch.delete(KeySpace::Infra, Cow::Owned(index_key(index_prefix, chunk)))?;
+        }
+        batch.put(
+            KeySpace::Infra,
+            Cow::Borrowed(&self.state_key),
+            Cow::Owned(self.state(0)),
+        )?;
+        batch.commit()
+    }
+
+    /// Returns the profile-scoped key, or `None` when the key is shared by all profiles.
+    fn scoped_key(&self, key_space: KeySpace, key: &[u8]) -> Option<Vec<u8>> {
+        match key_space {
+            KeySpace::ForwardTaskCache | KeySpace::ReverseTaskCache => None,
+            KeySpace::Infra if self.shared_infra_keys.iter().any(|k| k == key) => None,
+            KeySpace::Infra | KeySpace::TaskMeta | KeySpace::TaskData => {
+                Some([&self.prefix, key].concat())
+            }
+        }
+    }
+
+    fn tombstone_key(&self, key: &[u8]) -> Vec<u8> {
+        [&self.tombstone_prefix, key].concat()
+    }
+}
+
+fn index_key(index_prefix: &[u8], chunk: u32) -> Vec<u8> {
+    [index_prefix, &chunk.to_le_bytes()].concat()
+}
+
+fn key_space_tag(key_space: KeySpace) -> u8 {
+    match key_space {
+        KeySpace::Infra => 0,
+        KeySpace::TaskMeta => 1,
+        KeySpace::TaskData => 2,
+        KeySpace::ForwardTaskCache => 3,
+        KeySpace::ReverseTaskCache => 4,
+    }
+}
+
+fn encode_index_chunk(keys: &[(KeySpace, Vec<u8>)]) -> Vec<u8> {
+    let mut chunk = Vec::new();
+    for (key_space, key) in keys {
+        chunk.push(key_space_tag(*key_space));
+        chunk.extend_from_slice(&(key.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(key);
+    }
+    chunk
+}
+
+fn decode_index_chunk(mut chunk: &[u8], keys: &mut Vec<(KeySpace, Vec<u8>)>) -> Result<()> {
+    while let [tag, rest @ ..] = chunk {
+        let key_space = match tag {
+            0 => KeySpace::Infra,
+            1 => KeySpace::TaskMeta,
+            2 => KeySpace::TaskData,
+            3 => KeySpace::ForwardTaskCache,
+            4 => KeySpace::ReverseTaskCache,
+            _ => bail!("Invalid key space {tag} in cache profile index"),
+        };
+        let Some((len, rest)) = rest.split_first_chunk::<4>() else {
+            bail!("Truncated cache profile index");
+        };
+        let len = u32::from_le_bytes(*len) as usize;
+        if rest.len() < len {
+            bail!("Truncated cache profile index");
+        }
+        let (key, rest) = rest.split_at(len);
+        keys.push((key_space, key.to_vec()));
+        chunk = rest;
+    }
+    Ok(())
+}
+
+impl<T: KeyValueDatabase + Sync> KeyValueDatabase for CacheProfileLayer<T> {
+    type ReadTransaction<'l>
+        = T::ReadTransaction<'l>
+    where
+        Self: 'l;
+
+    fn lower_read_transaction<'l: 'i + 'r, 'i: 'r, 'r>(
+        tx: &'r Self::ReadTransaction<'l>,
+    ) -> &'r Self::ReadTransaction<'i> {
+        T::lower_read_transaction(tx)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.database.is_empty()
+    }
+
+    fn begin_read_transaction(&self) -> Result<Self::ReadTransaction<'_>> {
+        self.database.begin_read_transaction()
+    }
+
+    type ValueBuffer<'l>
+        = T::ValueBuffer<'l>
+    where
+        Self: 'l;
+
+    fn get<'l, 'db: 'l>(
+        &'l self,
+        transaction: &'l Self::ReadTransaction<'db>,
+        key_space: KeySpace,
+        key: &[u8],
+    ) -> Result<Option<Self::ValueBuffer<'l>>> {
+        if let Some(scoped_key) = self.scoped_key(key_space, key) {
+            if let Some(value) = self.database.get(transaction, key_space, &scoped_key)? {
+                return Ok(Some(value));
+            }
+            if self
+                .database
+                .get(transaction, key_space, &self.tombstone_key(&scoped_key))?
+                .is_some()
+            {
+                return Ok(None);
+            }
+        }
+        // Fall back to the data of the default profile
+        self.database.get(transaction, key_space, key)
+    }
+
+    type SerialWriteBatch<'l>
+        = CacheProfileWriteBatch<'l, T::SerialWriteBatch<'l>, T>
+    where
+        Self: 'l;
+
+    type ConcurrentWriteBatch<'l>
+        = CacheProfileWriteBatch<'l, T::ConcurrentWriteBatch<'l>, T>
+    where
+        Self: 'l;
+
+    fn write_batch(
+        &self,
+    ) -> Result<WriteBatch<'_, Self::SerialWriteBatch<'_>, Self::ConcurrentWriteBatch<'_>>> {
+        Ok(match self.database.write_batch()? {
+            WriteBatch::Serial(batch) => WriteBatch::serial(CacheProfileWriteBatch::new(
+                batch,
+                self,
+                |batch, key, value| {
+                    SerialWriteBatch::put(
+                        batch,
+                        KeySpace::Infra,
+                        Cow::Owned(key),
+                        Cow::Owned(value),
+                    )
+                },
+            )),
+            WriteBatch::Concurrent(batch, _) => WriteBatch::concurrent(
+                CacheProfileWriteBatch::new(batch, self, |batch, key, value| {
+                    ConcurrentWriteBatch::put(
+                        &*batch,
+                        KeySpace::Infra,
+                        Cow::Owned(key),
+                        Cow::Owned(value),
+                    )
+                }),
+            ),
+        })
+    }
+
+    fn compact(&self) -> Result<u64> {
+        self.database.compact()
+    }
+
+    /// Clears the data of all profiles, since they share the database.
+    fn clear(&self) -> Result<()> {
+        self.database.clear()
+    }
+
+    fn shutdown(&self) -> Result<()> {
+        self.database.shutdown()
+    }
+}
+
+pub struct CacheProfileWriteBatch<'a, B, T: KeyValueDatabase> {
+    batch: B,
+    layer: &'a CacheProfileLayer<T>,
+    /// The profile-scoped keys written by this batch, which are added to the index of the epoch.
+    scoped_keys: Mutex<Vec<(KeySpace, Vec<u8>)>>,
+    /// Writes an infra entry to the underlying batch on commit, which is either serial or
+    /// concurrent.
+    put_infra: fn(&mut B, Vec<u8>, Vec<u8>) -> Result<()>,
+}
+
+impl<'a, B, T: KeyValueDatabase> CacheProfileWriteBatch<'a, B, T> {
+    fn new(
+        batch: B,
+        layer: &'a CacheProfileLayer<T>,
+        put_infra: fn(&mut B, Vec<u8>, Vec<u8>) -> Result<()>,
+    ) -> Self {
+        Self {
+            batch,
+            layer,
+            scoped_keys: Mutex::new(Vec::new()),
+            put_infra,
+        }
+    }
+
+    fn write_key<'k>(&self, key_space: KeySpace, key: Cow<'k, [u8]>) -> Cow<'k, [u8]> {
+        match self.layer.scoped_key(key_space, &key) {
+            Some(scoped_key) => {
+                self.scoped_keys
+                    .lock()
+                    .push((key_space, scoped_key.clone()));
+                Cow::Owned(scoped_key)
+            }
+            None => key,
+        }
+    }
+
+    /// Returns the tombstone to write for a deleted profile-scoped key, so that reads don't fall
+    /// back to the entry of the default profile.
+    fn tombstone_key(&self, key_space: KeySpace, key: &[u8]) -> Option<Vec<u8>> {
+        let tombstone_key = self
+            .layer
+            .tombstone_key(&self.layer.scoped_key(key_space, key)?);
+        self.scoped_keys
+            .lock()
+            .push((key_space, tombstone_key.clone()));
+        Some(tombstone_key)
+    }
+}
+
+impl<'a, B: BaseWriteBatch<'a>, T: KeyValueDatabase> BaseWriteBatch<'a>
+    for CacheProfileWriteBatch<'a, B, T>
+{
+    type ValueBuffer<'l>
+        = B::ValueBuffer<'l>
+    where
+        Self: 'l,
+        'a: 'l;
+
+    fn get<'l>(&'l self, key_space: KeySpace, key: &[u8]) -> Result<Option<Self::ValueBuffer<'l>>>
+    where
+        'a: 'l,
+    {
+        if let Some(scoped_key) = self.layer.scoped_key(key_space, key) {
+            if let Some(value) = self.batch.get(key_space, &scoped_key)? {
+                return Ok(Some(value));
+            }
+            if self
+                .batch
+                .get(key_space, &self.layer.tombstone_key(&scoped_key))?
+                .is_some()
+            {
+                return Ok(None);
+            }
+        }
+        self.batch.get(key_space, key)
+    }
+
+    fn commit(self) -> Result<()> {
+        let Self {
+            mut batch,
+            layer,
+            scoped_keys,
+            put_infra,
+        } = self;
+        let scoped_keys = scoped_keys.into_inner();
+        if scoped_keys.is_empty() {
+            return batch.commit();
+        }
+        let mut index = layer.index.lock();
+        // Only keys that aren't recorded in the epoch yet are added, so the index doesn't grow
+        // when the same tasks are persisted again
+        let mut new_keys = FxHashSet::default();
+        let scoped_keys = scoped_keys
+            .into_iter()
+            .filter(|(key_space, key)| {
+                let entry = (key_space_tag(*key_space), key.clone());
+                !index.keys.contains(&entry) && new_keys.insert(entry)
+            })
+            .collect::<Vec<_>>();
+        if scoped_keys.is_empty() {
+            return batch.commit();
+        }
+        // The index chunk and the state are committed with the overlay, so every key of the
+        // epoch is found when it's pruned
+        put_infra(
+            &mut batch,
+            index_key(&layer.index_prefix, index.chunks),
+            encode_index_chunk(&scoped_keys),
+        )?;
+        put_infra(
+            &mut batch,
+            layer.state_key.clone(),
+            layer.state(index.chunks + 1),
+        )?;
+        batch.commit()?;
+        index.chunks += 1;
+        index.keys.extend(new_keys);
+        Ok(())
+    }
+}
+
+impl<'a, B: SerialWriteBatch<'a>, T: KeyValueDatabase> SerialWriteBatch<'a>
+    for CacheProfileWriteBatch<'a, B, T>
+{
+    fn put(&mut self, key_space: KeySpace, key: Cow<[u8]>, value: Cow<[u8]>) -> Result<()> {
+        let key = self.write_key(key_space, key);
+        self.batch.put(key_space, key, value)
+    }
+
+    fn delete(&mut self, key_space: KeySpace, key: Cow<[u8]>) -> Result<()> {
+        if let Some(tombstone_key) = self.tombstone_key(key_space, &key) {
+            self.batch
+                .put(key_space, Cow::Owned(tombstone_key), Cow::Borrowed(&[]))?;
+        }
+        let key = self.write_key(key_space, key);
+        self.batch.delete(key_space, key)
+    }
+}
+
+impl<'a, B: ConcurrentWriteBatch<'a>, T: KeyValueDatabase + Sync> ConcurrentWriteBatch<'a>
+    for CacheProfileWriteBatch<'a, B, T>
+{
+    fn put(&self, key_space: KeySpace, key: Cow<[u8]>, value: Cow<[u8]>) -> Result<()> {
+        let key = self.write_key(key_space, key);
+        self.batch.put(key_space, key, value)
+    }
+
+    fn delete(&self, key_space: KeySpace, key: Cow<[u8]>) -> Result<()> {
+        if let Some(tombstone_key) = self.tombstone_key(key_space, &key) {
+            self.batch
+                .put(key_space, Cow::Owned(tombstone_key), Cow::Borrowed(&[]))?;
+        }
+        let key = self.write_key(key_space, key);
+        self.batch.delete(key_space, key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::borrow::{Borrow, Cow};
+
+    use super::CacheProfileLayer;
+    use crate::database::{
+        in_memory_kv::InMemoryKvDb,
+        key_value_database::{KeySpace, KeyValueDatabase},
+        write_batch::{BaseWriteBatch, SerialWriteBatch},
+    };
+
+    const SESSION_KEY: &[u8] = b"session";
+    const SHARED_KEY: &[u8] = b"shared";
+
+    fn open(db: &InMemoryKvDb) -> CacheProfileLayer<InMemoryKvDb> {
+        CacheProfileLayer::new(db.share(), "build", SESSION_KEY, vec![SHARED_KEY.to_vec()]).unwrap()
+    }
+
+    fn get(db: &impl KeyValueDatabase, key_space: KeySpace, key: &[u8]) -> Option<Vec<u8>> {
+        let tx = db.begin_read_transaction().unwrap();
+        db.get(&tx, key_space, key)
+            .unwrap()
+            .map(|value| Borrow::<[u8]>::borrow(&value).to_vec())
+    }
+
+    fn put(db: &impl KeyValueDatabase, key_space: KeySpace, key: &[u8], value: &[u8]) {
+        let mut batch = db.write_batch().unwrap();
+        batch
+            .put(key_space, Cow::Borrowed(key), Cow::Borrowed(value))
+            .unwrap();
+        batch.commit().unwrap();
+    }
+
+    fn delete(db: &impl KeyValueDatabase, key_space: KeySpace, key: &[u8]) {
+        let mut batch = db.write_batch().unwrap();
+        batch.delete(key_space, Cow::Borrowed(key)).unwrap();
+        batch.commit().unwrap();
+    }
+
+    #[test]
+    fn test_overlay() {
+        let db = InMemoryKvDb::new();
+        put(&db, KeySpace::TaskData, b"a", b"default");
+        put(&db, KeySpace::TaskData, b"b", b"default");
+        let profile = open(&db);
+
+        assert_eq!(
+            get(&profile, KeySpace::TaskData, b"a"),
+            Some(b"default".to_vec())
+        );
+        put(&profile, KeySpace::TaskData, b"a", b"profile");
+        put(&profile, KeySpace::Infra, SHARED_KEY, b"profile");
+        assert_eq!(
+            get(&profile, KeySpace::TaskData, b"a"),
+            Some(b"profile".to_vec())
+        );
+        assert_eq!(
+            get(&db, KeySpace::TaskData, b"a"),
+            Some(b"default".to_vec())
+        );
+        assert_eq!(
+            get(&db, KeySpace::Infra, SHARED_KEY),
+            Some(b"profile".to_vec())
+        );
+
+        // A deleted entry doesn't fall back to the data of the default profile
+        delete(&profile, KeySpace::TaskData, b"b");
+        assert_eq!(get(&profile, KeySpace::TaskData, b"b"), None);
+        assert_eq!(
+            get(&db, KeySpace::TaskData, b"b"),
+            Some(b"default".to_vec())
+        );
+        let mut batch = profile.write_batch().unwrap();
+        assert!(batch.get(KeySpace::TaskData, b"b").unwrap().is_none());
+        batch
+            .put(
+                KeySpace::TaskData,
+                Cow::Borrowed(b"b"),
+                Cow::Borrowed(b"profile"),
+            )
+            .unwrap();
+        batch.commit().unwrap();
+        assert_eq!(
+            get(&profile, KeySpace::TaskData, b"b"),
+            Some(b"profile".to_vec())
+        );
+    }
+
+    #[test]
+    fn test_same_epoch() {
+        let db = InMemoryKvDb::new();
+        put(&db, KeySpace::Infra, SESSION_KEY, b"1");
+        put(&db, KeySpace::TaskData, b"a", b"default");
+        put(&open(&db), KeySpace::TaskData, b"a", b"profile");
+
+        // The default profile didn't persist another session, so the overlay is reused
+        let profile = open(&db);
+        assert_eq!(profile.epoch, 0);
+        assert_eq!(
+            get(&profile, KeySpace::TaskData, b"a"),
+            Some(b"profile".to_vec())
+        );
+
+        // Keys that are already recorded in the epoch aren't indexed again
+        put(&profile, KeySpace::TaskData, b"a", b"profile 2");
+        put(&profile, KeySpace::TaskMeta, b"a", b"profile");
+        put(&profile, KeySpace::TaskMeta, b"a", b"profile 2");
+        let index_chunks = db
+            .keys(KeySpace::Infra)
+            .into_iter()
+            .filter(|key| key.starts_with(b"cache-profile-index:"))
+            .count();
+        assert_eq!(index_chunks, 2);
+    }
+
+    #[test]
+    fn test_new_epoch() {
+        let db = InMemoryKvDb::new();
+        put(&db, KeySpace::Infra, SESSION_KEY, b"1");
+        put(&db, KeySpace::TaskData, b"a", b"default");
+        let profile = open(&db);
+        put(&profile, KeySpace::TaskData, b"a", b"profile");
+        put(&profile, KeySpace::TaskData, b"b", b"profile");
+        delete(&profile, KeySpace::TaskData, b"b");
+        put(&profile, KeySpace::Infra, b"c", b"profile");
+        drop(profile);
+
+        put(&db, KeySpace::Infra, SESSION_KEY, b"2");
+        put(&db, KeySpace::TaskData, b"a", b"default 2");
+        let profile = open(&db);
+        assert_eq!(profile.epoch, 1);
+        assert_eq!(
+            get(&profile, KeySpace::TaskData, b"a"),
+            Some(b"default 2".to_vec())
+        );
+        assert_eq!(get(&profile, KeySpace::Infra, b"c"), None);
+
+        // The keys of the outdated epoch are removed, only the state of the profile remains
+        assert_eq!(db.keys(KeySpace::TaskData), vec![b"a".to_vec()]);
+        let mut infra_keys = db.keys(KeySpace::Infra);
+        infra_keys.sort();
+        assert_eq!(
+            infra_keys,
+            vec![b"cache-profile:build".to_vec(), SESSION_KEY.to_vec()]
+        );
+
+        put(&profile, KeySpace::TaskData, b"a", b"profile 2");
+        let profile = open(&db);
+        assert_eq!(profile.epoch, 1);
+        assert_eq!(
+            get(&profile, KeySpace::TaskData, b"a"),
+            Some(b"profile 2".to_vec())
+        );
+    }
+}
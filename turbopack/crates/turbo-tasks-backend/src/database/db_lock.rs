@@ -0,0 +1,80 @@
+use std::{
+    fs::{create_dir_all, read_dir, remove_dir_all, remove_file, File, OpenOptions},
+    io::ErrorKind,
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+
+/// The name of the lock file inside of the database directory. It starts with a dot, so the
+/// database ignores it.
+pub const LOCK_FILE: &str = ".lock";
+
+/// An advisory lock on a database directory. Multiple processes can read the database at the
+/// same time, but only a single process can write to it, and not while others are reading.
+///
+/// The lock is released when this is dropped or the process exits.
+pub struct DbLock {
+    _file: File,
+}
+
+impl DbLock {
+    /// Acquires the lock for writing. Fails when another process uses the database.
+    pub fn exclusive(path: &Path) -> Result<Self> {
+        create_dir_all(path)
+            .with_context(|| format!("Unable to create database directory {}", path.display()))?;
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path.join(LOCK_FILE))
+            .with_context(|| format!("Unable to open lock file in {}", path.display()))?;
+        if !file.try_lock()? {
+            bail!(
+                "The persistent cache at {} is used by another process. Running multiple \
+                 processes on the same cache directory is not supported.",
+                path.display()
+            );
+        }
+        Ok(Self { _file: file })
+    }
+
+    /// Removes all content of the locked database directory, except for the lock file itself.
+    pub fn clear_directory(&self, path: &Path) -> Result<()> {
+        for entry in read_dir(path)? {
+            let entry = entry?;
+            if entry.file_name() == LOCK_FILE {
+                continue;
+            }
+            if entry.file_type()?.is_dir() {
+                remove_dir_all(entry.path())?;
+            } else {
+                remove_file(entry.path())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Acquires the lock for reading. Fails when another process writes to the database.
+    ///
+    /// This never modifies the directory. When there is no lock file yet, no process has written
+    /// to the database and `None` is returned.
+    pub fn shared(path: &Path) -> Result<Option<Self>> {
+        let file = match File::open(path.join(LOCK_FILE)) {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(err) => {
+                return Err(err)
+                    .with_context(|| format!("Unable to open lock file in {}", path.display()))
+            }
+        };
+        if !file.try_lock_shared()? {
+            bail!(
+                "The persistent cache at {} is currently written by another process",
+                path.display()
+            );
+        }
+        Ok(Some(Self { _file: file }))
+    }
+}
@@ -1,18 +1,33 @@
 use std::{
     env,
-    fs::{metadata, read_dir, remove_dir_all},
+    fs::{metadata, read_dir, remove_dir, remove_file},
     path::{Path, PathBuf},
     time::Duration,
 };
 
 use anyhow::Result;
 
+use crate::database::db_lock::{DbLock, LOCK_FILE};
+
 /// Specifies many databases that have a different version than the current one are retained.
 /// For example if MAX_OTHER_DB_VERSIONS is 2, there can be at most 3 databases in the directory,
 /// the current one and two older/newer ones.
 const MAX_OTHER_DB_VERSIONS: usize = 2;
 
-pub fn handle_db_versioning(base_path: &Path, version_info: &str) -> Result<PathBuf> {
+/// Returns the path of the database for the given version. Databases of other versions are
+/// removed when there are too many of them, when they are unused for longer than
+/// `TURBO_ENGINE_MAX_CACHE_AGE_DAYS` or when the cache directory exceeds
+/// `TURBO_ENGINE_MAX_CACHE_SIZE_MB`, unless `read_only` is set. Databases that are used by another
+/// process are never removed.
+///
+/// The size limit is enforced per database version, not per task: databases of other versions are
+/// removed as a whole, least recently used first, and the current database is reset as a whole
+/// when it alone exceeds the limit.
+pub fn handle_db_versioning(
+    base_path: &Path,
+    version_info: &str,
+    read_only: bool,
+) -> Result<PathBuf> {
     if let Ok(version) = env::var("TURBO_ENGINE_VERSION") {
         return Ok(base_path.join(version));
     }
@@ -53,8 +68,10 @@ pub fn handle_db_versioning(base_path: &Path, version_info: &str) -> Result<Path
         path = base_path.join(version);
 
         // Remove old databases if needed
-        if let Ok(read_dir) = read_dir(base_path) {
-            let old_dbs = read_dir
+        if read_only {
+            // Never modify the directory in read-only mode
+        } else if let Ok(read_dir) = read_dir(base_path) {
+            let mut old_dbs = read_dir
                 .filter_map(|entry| {
                     let entry = entry.ok()?;
                     if !entry.file_type().ok()?.is_dir() {
@@ -65,33 +82,122 @@ pub fn handle_db_versioning(base_path: &Path, version_info: &str) -> Result<Path
                     if name == version {
                         return None;
                     }
-                    Some(entry.path())
+                    let path = entry.path();
+                    let age =
+                        get_age(&path).unwrap_or(Duration::from_secs(10 * 356 * 24 * 60 * 60));
+                    Some((path, age))
                 })
                 .collect::<Vec<_>>();
+            // Most recently used first
+            old_dbs.sort_by_key(|(_, age)| *age);
+            // Pass `TURBO_ENGINE_MAX_CACHE_AGE_DAYS` to remove databases that haven't been used
+            // for that many days.
+            if let Some(max_age_days) = env::var("TURBO_ENGINE_MAX_CACHE_AGE_DAYS")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+            {
+                let max_age = Duration::from_secs(max_age_days * 24 * 60 * 60);
+                let expired = old_dbs.partition_point(|(_, age)| *age <= max_age);
+                for (p, _) in old_dbs.drain(expired..) {
+                    remove_unused_db(&p);
+                }
+            }
             if old_dbs.len() > MAX_OTHER_DB_VERSIONS {
-                let mut old_dbs = old_dbs
-                    .iter()
-                    .map(|p| {
-                        fn get_age(p: &Path) -> Result<Duration> {
-                            let m = metadata(p)?;
-                            Ok(m.accessed().or_else(|_| m.modified())?.elapsed()?)
-                        }
-                        (
-                            p,
-                            get_age(p).unwrap_or(Duration::from_secs(10 * 356 * 24 * 60 * 60)),
-                        )
-                    })
-                    .collect::<Vec<_>>();
-                old_dbs.sort_by_key(|(_, age)| *age);
-                for (p, _) in old_dbs.into_iter().skip(MAX_OTHER_DB_VERSIONS) {
-                    let _ = remove_dir_all(p);
+                for (p, _) in old_dbs.drain(MAX_OTHER_DB_VERSIONS..) {
+                    remove_unused_db(&p);
                 }
             }
+            // Pass `TURBO_ENGINE_MAX_CACHE_SIZE_MB` to limit the size of the cache directory.
+            if let Some(max_size) = env::var("TURBO_ENGINE_MAX_CACHE_SIZE_MB")
+                .ok()
+                .and_then(|v| v.parse::<u64>().ok())
+            {
+                enforce_max_size(&path, old_dbs, max_size * 1024 * 1024);
+            }
         }
     } else {
         path = base_path.join("temp");
-        let _ = remove_dir_all(&path);
+        if !read_only {
+            remove_unused_db(&path);
+        }
     }
 
     Ok(path)
 }
+
+/// Removes a database directory, unless another process holds its lock. Returns whether the
+/// directory was removed.
+fn remove_unused_db(path: &Path) -> bool {
+    if !path.exists() {
+        return true;
+    }
+    let Ok(lock) = DbLock::exclusive(path) else {
+        return false;
+    };
+    if lock.clear_directory(path).is_err() {
+        return false;
+    }
+    drop(lock);
+    // The lock file can only be removed after unlocking it (on Windows). When another process
+    // started to use the directory in the meantime, it's no longer empty and is kept.
+    remove_file(path.join(LOCK_FILE)).is_ok() && remove_dir(path).is_ok()
+}
+
+fn get_age(p: &Path) -> Result<Duration> {
+    let m = metadata(p)?;
+    Ok(m.accessed().or_else(|_| m.modified())?.elapsed()?)
+}
+
+fn dir_size(path: &Path) -> u64 {
+    let Ok(read_dir) = read_dir(path) else {
+        return 0;
+    };
+    read_dir
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let metadata = entry.metadata().ok()?;
+            Some(if metadata.is_dir() {
+                dir_size(&entry.path())
+            } else {
+                metadata.len()
+            })
+        })
+        .sum()
+}
+
+/// Removes the least recently used databases of other versions until the total size is below
+/// `max_size`. When the current database alone exceeds the limit, it's reset.
+fn enforce_max_size(path: &Path, old_dbs: Vec<(PathBuf, Duration)>, max_size: u64) {
+    let mut old_dbs = old_dbs
+        .into_iter()
+        .map(|(p, _)| {
+            let size = dir_size(&p);
+            (p, size)
+        })
+        .collect::<Vec<_>>();
+    let current_size = dir_size(path);
+    let mut total_size = current_size + old_dbs.iter().map(|(_, size)| size).sum::<u64>();
+    while total_size > max_size {
+        // old_dbs is sorted by most recently used first
+        let Some((p, size)) = old_dbs.pop() else {
+            break;
+        };
+        if remove_unused_db(&p) {
+            total_size -= size;
+        }
+    }
+    // Databases of other versions that are still in use don't count here. Resetting the current
+    // database wouldn't bring the cache below the limit, and it would be reset on every start
+    // until the other process exits. After a reset, the current database has to exceed the
+    // limit again before it's reset again.
+    if current_size > max_size {
+        // Resetting the database is only safe when no other process is using it
+        if let Ok(lock) = DbLock::exclusive(path) {
+            println!(
+                "WARNING: The persistent cache exceeds the maximum size of {} MB and is reset.",
+                max_size / 1024 / 1024
+            );
+            let _ = lock.clear_directory(path);
+        }
+    }
+}
@@ -0,0 +1,164 @@
+use std::{borrow::Cow, sync::Arc};
+
+use anyhow::Result;
+use parking_lot::Mutex;
+use rustc_hash::FxHashMap;
+
+use crate::database::{
+    by_key_space::ByKeySpace,
+    key_value_database::{KeySpace, KeyValueDatabase},
+    write_batch::{BaseWriteBatch, ConcurrentWriteBatch, UnimplementedWriteBatch, WriteBatch},
+};
+
+type Entries = ByKeySpace<Mutex<FxHashMap<Vec<u8>, Arc<[u8]>>>>;
+
+/// A database that keeps all entries in memory, to test the database layers.
+pub struct InMemoryKvDb {
+    entries: Arc<Entries>,
+}
+
+impl InMemoryKvDb {
+    pub fn new() -> Self {
+        Self {
+            entries: Arc::new(ByKeySpace::new(|_| Mutex::new(FxHashMap::default()))),
+        }
+    }
+
+    /// Returns another handle to the same entries, e.g. to inspect them after the database was
+    /// moved into a layer.
+    pub fn share(&self) -> Self {
+        Self {
+            entries: self.entries.clone(),
+        }
+    }
+
+    pub fn get_entry(&self, key_space: KeySpace, key: &[u8]) -> Option<Arc<[u8]>> {
+        self.entries.get(key_space).lock().get(key).cloned()
+    }
+
+    pub fn keys(&self, key_space: KeySpace) -> Vec<Vec<u8>> {
+        self.entries.get(key_space).lock().keys().cloned().collect()
+    }
+}
+
+impl KeyValueDatabase for InMemoryKvDb {
+    type ReadTransaction<'l>
+        = ()
+    where
+        Self: 'l;
+
+    fn lower_read_transaction<'l: 'i + 'r, 'i: 'r, 'r>(
+        tx: &'r Self::ReadTransaction<'l>,
+    ) -> &'r Self::ReadTransaction<'i> {
+        tx
+    }
+
+    fn begin_read_transaction(&self) -> Result<Self::ReadTransaction<'_>> {
+        Ok(())
+    }
+
+    fn is_empty(&self) -> bool {
+        self.entries
+            .iter()
+            .all(|(_, entries)| entries.lock().is_empty())
+    }
+
+    type ValueBuffer<'l>
+        = Arc<[u8]>
+    where
+        Self: 'l;
+
+    fn get<'l, 'db: 'l>(
+        &'l self,
+        _transaction: &'l Self::ReadTransaction<'db>,
+        key_space: KeySpace,
+        key: &[u8],
+    ) -> Result<Option<Self::ValueBuffer<'l>>> {
+        Ok(self.get_entry(key_space, key))
+    }
+
+    type SerialWriteBatch<'l>
+        = UnimplementedWriteBatch
+    where
+        Self: 'l;
+
+    type ConcurrentWriteBatch<'l>
+        = InMemoryWriteBatch<'l>
+    where
+        Self: 'l;
+
+    fn write_batch(
+        &self,
+    ) -> Result<WriteBatch<'_, Self::SerialWriteBatch<'_>, Self::ConcurrentWriteBatch<'_>>> {
+        Ok(WriteBatch::concurrent(InMemoryWriteBatch {
+            db: self,
+            changes: Mutex::new(Vec::new()),
+        }))
+    }
+
+    fn clear(&self) -> Result<()> {
+        for (_, entries) in self.entries.iter() {
+            entries.lock().clear();
+        }
+        Ok(())
+    }
+}
+
+pub struct InMemoryWriteBatch<'a> {
+    db: &'a InMemoryKvDb,
+    /// The changes in order. `None` deletes the entry.
+    changes: Mutex<Vec<(KeySpace, Vec<u8>, Option<Arc<[u8]>>)>>,
+}
+
+impl<'a> BaseWriteBatch<'a> for InMemoryWriteBatch<'a> {
+    type ValueBuffer<'l>
+        = Arc<[u8]>
+    where
+        Self: 'l,
+        'a: 'l;
+
+    fn get<'l>(&'l self, key_space: KeySpace, key: &[u8]) -> Result<Option<Self::ValueBuffer<'l>>>
+    where
+        'a: 'l,
+    {
+        let changes = self.changes.lock();
+        if let Some((_, _, value)) =
+            changes
+                .iter()
+                .rev()
+                .find(|(change_key_space, change_key, _)| {
+                    *change_key_space as u8 == key_space as u8 && change_key == key
+                })
+        {
+            return Ok(value.clone());
+        }
+        Ok(self.db.get_entry(key_space, key))
+    }
+
+    fn commit(self) -> Result<()> {
+        for (key_space, key, value) in self.changes.into_inner() {
+            let mut entries = self.db.entries.get(key_space).lock();
+            match value {
+                Some(value) => entries.insert(key, value),
+                None => entries.remove(&key),
+            };
+        }
+        Ok(())
+    }
+}
+
+impl<'a> ConcurrentWriteBatch<'a> for InMemoryWriteBatch<'a> {
+    fn put(&self, key_space: KeySpace, key: Cow<[u8]>, value: Cow<[u8]>) -> Result<()> {
+        self.changes
+            .lock()
+            .push((key_space, key.into_owned(), Some(value.into())));
+        Ok(())
+    }
+
+    fn delete(&self, key_space: KeySpace, key: Cow<[u8]>) -> Result<()> {
+        self.changes
+            .lock()
+            .push((key_space, key.into_owned(), None));
+        Ok(())
+    }
+}
@@ -1,4 +1,4 @@
-use anyhow::Result;
+use anyhow::{bail, Result};
 
 use crate::database::write_batch::{
     ConcurrentWriteBatch, SerialWriteBatch, UnimplementedWriteBatch, WriteBatch,
@@ -52,6 +52,17 @@ pub trait KeyValueDatabase {
         &self,
     ) -> Result<WriteBatch<'_, Self::SerialWriteBatch<'_>, Self::ConcurrentWriteBatch<'_>>>;
 
+    /// Compacts the database to reclaim disk space. Returns the number of bytes reclaimed.
+    fn compact(&self) -> Result<u64> {
+        Ok(0)
+    }
+
+    /// Removes all data in one atomic step. The removal may only take effect when the database
+    /// is opened the next time, so this process must not write to it anymore.
+    fn clear(&self) -> Result<()> {
+        bail!("Clearing the database is not supported")
+    }
+
     fn shutdown(&self) -> Result<()> {
         Ok(())
     }
@@ -1,14 +1,20 @@
-#[cfg(feature = "lmdb")]
+#[cfg(any(feature = "lmdb", test))]
 mod by_key_space;
+pub mod cache_profile;
+pub mod db_lock;
 pub mod db_versioning;
 #[cfg(feature = "lmdb")]
 pub mod fresh_db_optimization;
+#[cfg(test)]
+mod in_memory_kv;
 pub mod key_value_database;
 #[cfg(feature = "lmdb")]
 pub mod lmdb;
+pub mod namespace;
 pub mod noop_kv;
 #[cfg(feature = "lmdb")]
 pub mod read_transaction_cache;
+pub mod remote_cache;
 #[cfg(feature = "lmdb")]
 pub mod startup_cache;
 pub mod turbo;
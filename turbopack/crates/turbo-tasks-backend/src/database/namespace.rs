@@ -0,0 +1,94 @@
+use std::{
+    fs::{read_dir, remove_dir_all},
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Context, Result};
+
+use crate::database::db_lock::DbLock;
+
+/// Statistics about the cache of a single namespace.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NamespaceStatistics {
+    /// The number of database versions stored for the namespace.
+    pub versions: usize,
+    /// The number of files, summed over all versions.
+    pub files: usize,
+    /// The size on disk in bytes, summed over all versions.
+    pub size: u64,
+}
+
+/// Returns the cache directory of a namespace within a cache directory that is shared by
+/// multiple projects. Each namespace is versioned and locked independently, so multiple
+/// instances can use the same cache directory at the same time. The storage of a namespace is
+/// opened with [`turbo_backing_storage_in_namespace`][crate::turbo_backing_storage_in_namespace].
+pub fn namespace_path(base_path: &Path, namespace: &str) -> Result<PathBuf> {
+    if namespace.is_empty()
+        || namespace.starts_with('.')
+        || !namespace
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        bail!(
+            "Invalid cache namespace {namespace:?}, only ASCII alphanumeric characters, '-', '_' \
+             and '.' are allowed"
+        );
+    }
+    Ok(base_path.join(namespace))
+}
+
+pub fn namespace_statistics(base_path: &Path, namespace: &str) -> Result<NamespaceStatistics> {
+    fn visit(path: &Path, statistics: &mut NamespaceStatistics) -> Result<()> {
+        for entry in read_dir(path)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if metadata.is_dir() {
+                visit(&entry.path(), statistics)?;
+            } else {
+                statistics.files += 1;
+                statistics.size += metadata.len();
+            }
+        }
+        Ok(())
+    }
+
+    let path = namespace_path(base_path, namespace)?;
+    let mut statistics = NamespaceStatistics::default();
+    let entries = match read_dir(&path) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(statistics),
+        Err(err) => return Err(err.into()),
+    };
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            statistics.versions += 1;
+            visit(&entry.path(), &mut statistics)?;
+        }
+    }
+    Ok(statistics)
+}
+
+/// Removes all cached data of a namespace. Fails when another process uses the cache of this
+/// namespace.
+pub fn clear_namespace(base_path: &Path, namespace: &str) -> Result<()> {
+    let path = namespace_path(base_path, namespace)?;
+    let entries = match read_dir(&path) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(err) => return Err(err.into()),
+    };
+    // Acquire the locks of all versions first, so we don't remove anything while in use
+    let mut locks = Vec::new();
+    for entry in entries {
+        let entry = entry?;
+        if entry.file_type()?.is_dir() {
+            locks.push(DbLock::exclusive(&entry.path())?);
+        }
+    }
+    remove_dir_all(&path)
+        .with_context(|| format!("Unable to remove cache namespace {}", path.display()))?;
+    drop(locks);
+    Ok(())
+}
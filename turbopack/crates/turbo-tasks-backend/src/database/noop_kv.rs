@@ -49,6 +49,10 @@ impl KeyValueDatabase for NoopKvDb {
     where
         Self: 'l;
 
+    fn clear(&self) -> Result<()> {
+        Ok(())
+    }
+
     fn write_batch(
         &self,
     ) -> Result<WriteBatch<'_, Self::SerialWriteBatch<'_>, Self::ConcurrentWriteBatch<'_>>> {
@@ -0,0 +1,61 @@
+//! Shares the persistent cache through a remote store, so CI and teammates can share
+//! computation results while all reads stay local.
+//!
+//! Task ids are allocated by each database, so single entries of one database are meaningless
+//! in another one. The cache is therefore shared as a whole database, packed by
+//! [`export`](super::turbo::export): an empty local cache is seeded from the last published
+//! cache, and a cache is published by uploading it after the backend has stopped.
+
+use std::{
+    fs::remove_file,
+    path::Path,
+    thread::{self, JoinHandle},
+};
+
+use anyhow::Result;
+
+use crate::database::turbo;
+
+/// A remote store of packed caches, e.g. an HTTP cache shared by CI and developers.
+///
+/// Caches are only compatible with the same `version_info`, so it should be part of the remote
+/// key.
+pub trait RemoteCache: Send + Sync + 'static {
+    /// Writes the last published cache to `target`. Returns false when there is none.
+    fn download(&self, target: &Path) -> Result<bool>;
+
+    /// Publishes the cache packed in `source`, replacing the one that was published before.
+    /// Only a single writer, e.g. a CI job on the main branch, should upload to a remote cache.
+    fn upload(&self, source: &Path) -> Result<()>;
+}
+
+/// Seeds the database at `path` from the remote cache when it's empty. Returns whether it was
+/// seeded. A failing remote cache only leaves the database empty.
+pub fn seed(path: &Path, remote: &impl RemoteCache) -> bool {
+    match turbo::import_if_empty(path, |target| remote.download(target)) {
+        Ok(seeded) => seeded,
+        Err(err) => {
+            println!("Seeding the persistent cache from the remote cache failed: {err:?}");
+            false
+        }
+    }
+}
+
+/// Packs the database at `path` and uploads it to the remote cache in a background thread.
+/// Must only be called after the backend using the database has stopped.
+pub fn upload(path: &Path, remote: impl RemoteCache) -> Result<JoinHandle<Result<()>>> {
+    let path = path.to_path_buf();
+    let handle = thread::Builder::new()
+        .name("remote cache upload".to_string())
+        .spawn(move || {
+            let name = path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            let source = path.with_file_name(format!("{name}.upload"));
+            let result = turbo::export(&path, &source).and_then(|()| remote.upload(&source));
+            let _ = remove_file(&source);
+            result
+        })?;
+    Ok(handle)
+}
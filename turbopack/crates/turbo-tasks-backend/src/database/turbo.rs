@@ -1,33 +1,71 @@
 use std::{
     borrow::Cow,
-    path::PathBuf,
+    fs::{create_dir_all, read_dir, remove_dir_all, remove_file, rename, File},
+    path::{Path, PathBuf},
     sync::Arc,
     thread::{spawn, JoinHandle},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
-use anyhow::Result;
+use anyhow::{bail, Result};
 use parking_lot::Mutex;
 use turbo_persistence::{ArcSlice, TurboPersistence};
 
 use crate::database::{
+    db_lock::{DbLock, LOCK_FILE},
     key_value_database::{KeySpace, KeyValueDatabase},
     write_batch::{BaseWriteBatch, ConcurrentWriteBatch, WriteBatch},
 };
 
+/// Marks a database that was cleared. Its content is removed before it's opened the next time.
+const CLEARED_MARKER: &str = "CLEARED";
+
 const COMPACT_MAX_COVERAGE: f32 = 20.0;
 const COMPACT_MAX_MERGE_SEQUENCE: usize = 8;
+/// Compaction while idle is more thorough than the compaction after each write.
+const IDLE_COMPACT_MAX_COVERAGE: f32 = 2.0;
+const IDLE_COMPACT_MAX_MERGE_SEQUENCE: usize = 32;
 
 pub struct TurboKeyValueDatabase {
+    path: PathBuf,
     db: Arc<TurboPersistence>,
     compact_join_handle: Mutex<Option<JoinHandle<Result<()>>>>,
+    /// Prevents other processes from writing to the database while it's open.
+    _lock: Option<DbLock>,
 }
 
 impl TurboKeyValueDatabase {
-    pub fn new(path: PathBuf) -> Result<Self> {
-        let db = Arc::new(TurboPersistence::open(path.to_path_buf())?);
+    /// Opens the database. Recovers from a corrupted database by moving it aside and starting
+    /// with an empty database. The error that caused the recovery is returned along with the
+    /// database.
+    pub fn new_with_recovery(path: PathBuf) -> Result<(Self, Option<anyhow::Error>)> {
+        // Failing to acquire the lock is not a sign of corruption
+        let lock = DbLock::exclusive(&path)?;
+        if path.join(CLEARED_MARKER).exists() {
+            remove_content(&path)?;
+        }
+        match TurboPersistence::open(path.to_path_buf()) {
+            Ok(db) => Ok((Self::from_db(path, db, lock), None)),
+            Err(err) => {
+                let corrupted_path = move_aside_corrupted(&path)?;
+                println!(
+                    "WARNING: The persistent cache is corrupted and has been moved to {}. \
+                     Starting with an empty cache. {err:?}",
+                    corrupted_path.display()
+                );
+                let db = TurboPersistence::open(path.to_path_buf())?;
+                Ok((Self::from_db(path, db, lock), Some(err)))
+            }
+        }
+    }
+
+    fn from_db(path: PathBuf, db: TurboPersistence, lock: DbLock) -> Self {
+        let db = Arc::new(db);
         let mut this = Self {
+            path,
             db: db.clone(),
             compact_join_handle: Mutex::new(None),
+            _lock: Some(lock),
         };
         // start compaction in background if the database is not empty
         if !db.is_empty() {
@@ -35,8 +73,139 @@ impl TurboKeyValueDatabase {
                 spawn(move || db.compact(COMPACT_MAX_COVERAGE, COMPACT_MAX_MERGE_SEQUENCE));
             this.compact_join_handle.get_mut().replace(handle);
         }
-        Ok(this)
+        this
+    }
+
+    /// Opens the database without modifying it. No compaction is started and all writes fail.
+    /// Fails for a cleared database.
+    pub fn new_read_only(path: PathBuf) -> Result<Self> {
+        let lock = DbLock::shared(&path)?;
+        ensure_not_cleared(&path)?;
+        let db = Arc::new(TurboPersistence::open_read_only(path.clone())?);
+        Ok(Self {
+            path,
+            db,
+            compact_join_handle: Mutex::new(None),
+            _lock: lock,
+        })
+    }
+}
+
+/// Returns the size of all files in the database directory.
+fn disk_usage(path: &Path) -> Result<u64> {
+    let mut size = 0;
+    for entry in read_dir(path)? {
+        let metadata = entry?.metadata()?;
+        if metadata.is_file() {
+            size += metadata.len();
+        }
+    }
+    Ok(size)
+}
+
+/// Fails for a cleared database. Its content is only removed when it's opened for writing, so
+/// the remaining files must not be read anymore.
+fn ensure_not_cleared(path: &Path) -> Result<()> {
+    if path.join(CLEARED_MARKER).exists() {
+        bail!(
+            "The persistent cache at {} was cleared and is empty until it's opened for writing",
+            path.display()
+        );
     }
+    Ok(())
+}
+
+/// Removes the content of a cleared database. The marker is removed last, so an interrupted
+/// removal is continued the next time.
+fn remove_content(path: &Path) -> Result<()> {
+    for entry in read_dir(path)? {
+        let entry = entry?;
+        let name = entry.file_name();
+        if name == LOCK_FILE || name == CLEARED_MARKER {
+            continue;
+        }
+        if entry.file_type()?.is_dir() {
+            remove_dir_all(entry.path())?;
+        } else {
+            remove_file(entry.path())?;
+        }
+    }
+    remove_file(path.join(CLEARED_MARKER))?;
+    Ok(())
+}
+
+/// Moves the content of a corrupted database into a sibling directory, so it can be inspected
+/// later. The lock file stays in place, as it's held by this process. The sibling directory is
+/// removed by the database versioning eventually.
+fn move_aside_corrupted(path: &Path) -> Result<PathBuf> {
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis());
+    let corrupted_path = path.with_file_name(format!("{name}-corrupted-{timestamp}"));
+    create_dir_all(&corrupted_path)?;
+    for entry in read_dir(path)? {
+        let entry = entry?;
+        if entry.file_name() == LOCK_FILE {
+            continue;
+        }
+        rename(entry.path(), corrupted_path.join(entry.file_name()))?;
+    }
+    Ok(corrupted_path)
+}
+
+/// Packs the database at `path` into a single compressed file. Fails when another process is
+/// writing to the database or the database was cleared.
+pub fn export(path: &Path, target: &Path) -> Result<()> {
+    let _lock = DbLock::shared(path)?;
+    ensure_not_cleared(path)?;
+    let db = TurboPersistence::open_read_only(path.to_path_buf())?;
+    db.export(target)?;
+    db.shutdown()
+}
+
+/// Replaces the database at `path` with the content of a file created by [`export`]. Fails when
+/// another process is using the database.
+pub fn import(path: &Path, source: &Path) -> Result<()> {
+    let lock = DbLock::exclusive(path)?;
+    lock.clear_directory(path)?;
+    let db = TurboPersistence::import(source, path.to_path_buf())?;
+    db.shutdown()
+}
+
+/// Fills the database at `path` with the content of a file created by [`export`], but only when
+/// the database is empty. `fetch` writes the file to the given path and returns false when there
+/// is none. Returns whether the database was filled. Fails when another process is using the
+/// database.
+pub fn import_if_empty(path: &Path, fetch: impl FnOnce(&Path) -> Result<bool>) -> Result<bool> {
+    let lock = DbLock::exclusive(path)?;
+    if path.join(CLEARED_MARKER).exists() {
+        remove_content(path)?;
+    }
+    let db = TurboPersistence::open(path.to_path_buf())?;
+    let is_empty = db.is_empty();
+    db.shutdown()?;
+    drop(db);
+    if !is_empty {
+        return Ok(false);
+    }
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let source = path.with_file_name(format!("{name}.import"));
+    let result = fetch(&source).and_then(|found| {
+        if found {
+            lock.clear_directory(path)?;
+            TurboPersistence::import(&source, path.to_path_buf())?.shutdown()?;
+        }
+        Ok(found)
+    });
+    let _ = remove_file(&source);
+    result
 }
 
 impl KeyValueDatabase for TurboKeyValueDatabase {
@@ -81,18 +250,46 @@ impl KeyValueDatabase for TurboKeyValueDatabase {
     fn write_batch(
         &self,
     ) -> Result<WriteBatch<'_, Self::SerialWriteBatch<'_>, Self::ConcurrentWriteBatch<'_>>> {
-        // Wait for the compaction to finish
-        if let Some(join_handle) = self.compact_join_handle.lock().take() {
+        // Wait for the compaction to finish. The lock is held until the write batch is started,
+        // so no new compaction can start in between.
+        let mut compact_join_handle = self.compact_join_handle.lock();
+        if let Some(join_handle) = compact_join_handle.take() {
             join_handle.join().unwrap()?;
         }
         // Start a new write batch
+        let batch = self.db.write_batch()?;
+        drop(compact_join_handle);
         Ok(WriteBatch::concurrent(TurboWriteBatch {
-            batch: self.db.write_batch()?,
+            batch,
             db: &self.db,
             compact_join_handle: &self.compact_join_handle,
         }))
     }
 
+    fn compact(&self) -> Result<u64> {
+        // Holding the lock prevents write batches from starting while compacting
+        let mut compact_join_handle = self.compact_join_handle.lock();
+        if let Some(join_handle) = compact_join_handle.take() {
+            join_handle.join().unwrap()?;
+        }
+        let size_before = disk_usage(&self.path)?;
+        self.db
+            .compact(IDLE_COMPACT_MAX_COVERAGE, IDLE_COMPACT_MAX_MERGE_SEQUENCE)?;
+        drop(compact_join_handle);
+        let size_after = disk_usage(&self.path)?;
+        Ok(size_before.saturating_sub(size_after))
+    }
+
+    fn clear(&self) -> Result<()> {
+        if self.db.is_read_only() {
+            bail!("Unable to clear a read-only database");
+        }
+        // The open database keeps its files, so the content is removed on the next open. Creating
+        // the marker is a single atomic step.
+        File::create(self.path.join(CLEARED_MARKER))?.sync_all()?;
+        Ok(())
+    }
+
     fn shutdown(&self) -> Result<()> {
         // Wait for the compaction to finish
         if let Some(join_handle) = self.compact_join_handle.lock().take() {
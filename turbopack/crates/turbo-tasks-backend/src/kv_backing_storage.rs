@@ -2,19 +2,26 @@ use std::{
     borrow::{Borrow, Cow},
     cmp::max,
     collections::hash_map::Entry,
-    sync::Arc,
+    sync::{
+        atomic::{AtomicU32, AtomicU64, Ordering},
+        Arc,
+    },
+    thread::available_parallelism,
+    time::{Duration, Instant},
 };
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use parking_lot::Mutex;
 use rayon::iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator};
-use rustc_hash::FxHashMap;
+use rustc_hash::{FxHashMap, FxHashSet};
 use serde::{ser::SerializeSeq, Serialize};
 use tracing::Span;
 use turbo_tasks::{backend::CachedTaskType, turbo_tasks_scope, KeyValuePair, SessionId, TaskId};
+use turbo_tasks_hash::{DeterministicHasher, Xxh3Hash64Hasher};
 
 use crate::{
     backend::{AnyOperation, TaskDataCategory},
-    backing_storage::BackingStorage,
+    backing_storage::{BackingStorage, InvalidationKeyUpdates, RestoreStatistics},
     data::{CachedDataItem, CachedDataItemKey, CachedDataItemValue, CachedDataUpdate},
     database::{
         key_value_database::{KeySpace, KeyValueDatabase},
@@ -38,6 +45,30 @@ fn pot_de_symbol_list<'l>() -> pot::de::SymbolList<'l> {
 const META_KEY_OPERATIONS: u32 = 0;
 const META_KEY_NEXT_FREE_TASK_ID: u32 = 1;
 const META_KEY_SESSION_ID: u32 = 2;
+const META_KEY_INVALIDATION_KEY_STATES: u32 = 3;
+const META_KEY_TASK_ACCESS_SCORES: u32 = 4;
+
+/// The score a task gains when it's accessed in a session. Scores are halved every session, so
+/// tasks that are no longer accessed drop out after a few sessions.
+const TASK_ACCESS_SCORE: u32 = 1 << 10;
+
+/// Prefix of the infra keys that map invalidation keys to task ids. It keeps them apart from the
+/// [`IntKey`] keys.
+const INVALIDATION_KEY_PREFIX: &[u8] = b"invalidation-key:";
+
+fn invalidation_key(key: &str) -> Vec<u8> {
+    [INVALIDATION_KEY_PREFIX, key.as_bytes()].concat()
+}
+
+/// The infra key of the last persisted session, see [`crate::database::cache_profile`].
+pub(crate) fn session_id_key() -> Vec<u8> {
+    IntKey::new(META_KEY_SESSION_ID).as_ref().to_vec()
+}
+
+/// The infra keys that are shared by all cache profiles. Task ids are allocated for all of them.
+pub(crate) fn shared_infra_keys() -> Vec<Vec<u8>> {
+    vec![IntKey::new(META_KEY_NEXT_FREE_TASK_ID).as_ref().to_vec()]
+}
 
 struct IntKey([u8; 4]);
 
@@ -58,13 +89,65 @@ fn as_u32(bytes: impl Borrow<[u8]>) -> Result<u32> {
     Ok(n)
 }
 
+/// Upper bound for the number of threads used for serialization and database writes.
+const MAX_IO_THREADS: usize = 4;
+
 pub struct KeyValueDatabaseBackingStorage<T: KeyValueDatabase> {
     database: T,
+    /// Dedicated thread pool for serialization and database writes, so persisting never occupies
+    /// the threads used for task execution.
+    io_pool: rayon::ThreadPool,
+    restore_counters: RestoreCounters,
+    /// The position of the next task to check in [`BackingStorage::verify_tasks`].
+    verification_cursor: AtomicU32,
+}
+
+/// Counters behind [`RestoreStatistics`].
+#[derive(Default)]
+struct RestoreCounters {
+    restored_task_data: AtomicU64,
+    restored_task_cache_entries: AtomicU64,
+    bytes_read: AtomicU64,
+    duration_nanos: AtomicU64,
+    discarded: AtomicU64,
+    discarded_cells: AtomicU64,
+}
+
+impl RestoreCounters {
+    fn record_read(&self, bytes: usize, start: Instant) {
+        self.bytes_read.fetch_add(bytes as u64, Ordering::Relaxed);
+        self.duration_nanos
+            .fetch_add(start.elapsed().as_nanos() as u64, Ordering::Relaxed);
+    }
+
+    fn get(&self) -> RestoreStatistics {
+        RestoreStatistics {
+            restored_task_data: self.restored_task_data.load(Ordering::Relaxed),
+            restored_task_cache_entries: self.restored_task_cache_entries.load(Ordering::Relaxed),
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            duration: Duration::from_nanos(self.duration_nanos.load(Ordering::Relaxed)),
+            discarded: self.discarded.load(Ordering::Relaxed),
+            discarded_cells: self.discarded_cells.load(Ordering::Relaxed),
+        }
+    }
 }
 
 impl<T: KeyValueDatabase> KeyValueDatabaseBackingStorage<T> {
     pub fn new(database: T) -> Self {
-        Self { database }
+        let io_threads = available_parallelism()
+            .map_or(1, |v| v.get() / 4)
+            .clamp(1, MAX_IO_THREADS);
+        let io_pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(io_threads)
+            .thread_name(|i| format!("turbo-tasks-persist-{i}"))
+            .build()
+            .expect("Unable to create thread pool for persistence");
+        Self {
+            database,
+            io_pool,
+            restore_counters: RestoreCounters::default(),
+            verification_cursor: AtomicU32::new(0),
+        }
     }
 
     fn with_tx<R>(
@@ -83,64 +166,25 @@ impl<T: KeyValueDatabase> KeyValueDatabaseBackingStorage<T> {
     }
 }
 
-fn get_infra_u32(database: &impl KeyValueDatabase, key: u32) -> Option<u32> {
-    let tx = database.begin_read_transaction().ok()?;
-    let value = database
-        .get(&tx, KeySpace::Infra, IntKey::new(key).as_ref())
-        .ok()?
-        .map(as_u32)?
-        .ok()?;
-    Some(value)
-}
-
-impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorage
-    for KeyValueDatabaseBackingStorage<T>
-{
-    type ReadTransaction<'l> = T::ReadTransaction<'l>;
-
-    fn lower_read_transaction<'l: 'i + 'r, 'i: 'r, 'r>(
-        tx: &'r Self::ReadTransaction<'l>,
-    ) -> &'r Self::ReadTransaction<'i> {
-        T::lower_read_transaction(tx)
-    }
-
-    fn next_free_task_id(&self) -> TaskId {
-        TaskId::from(get_infra_u32(&self.database, META_KEY_NEXT_FREE_TASK_ID).unwrap_or(1))
-    }
-
-    fn next_session_id(&self) -> SessionId {
-        SessionId::from(get_infra_u32(&self.database, META_KEY_SESSION_ID).unwrap_or(0) + 1)
-    }
-
-    fn uncompleted_operations(&self) -> Vec<AnyOperation> {
-        fn get(database: &impl KeyValueDatabase) -> Result<Vec<AnyOperation>> {
-            let tx = database.begin_read_transaction()?;
-            let Some(operations) = database.get(
-                &tx,
-                KeySpace::Infra,
-                IntKey::new(META_KEY_OPERATIONS).as_ref(),
-            )?
-            else {
-                return Ok(Vec::new());
-            };
-            let operations = POT_CONFIG.deserialize(operations.borrow())?;
-            Ok(operations)
-        }
-        get(&self.database).unwrap_or_default()
-    }
-
-    fn save_snapshot(
+impl<T: KeyValueDatabase + Send + Sync + 'static> KeyValueDatabaseBackingStorage<T> {
+    /// Writes the snapshot. Must be called from within `io_pool`, as all parallel work is
+    /// spawned onto the current thread pool.
+    fn save_snapshot_in_io_pool(
         &self,
         session_id: SessionId,
         operations: Vec<Arc<AnyOperation>>,
         task_cache_updates: Vec<ChunkedVec<(Arc<CachedTaskType>, TaskId)>>,
         meta_updates: Vec<ChunkedVec<CachedDataUpdate>>,
         data_updates: Vec<ChunkedVec<CachedDataUpdate>>,
+        invalidation_key_updates: InvalidationKeyUpdates,
+        progress: &(dyn Fn(usize, u64) + Sync),
     ) -> Result<()> {
         let _span = tracing::trace_span!("save snapshot", session_id = ?session_id, operations = operations.len());
+        let invalidation_keys = merge_invalidation_keys(&self.database, invalidation_key_updates)?;
         let mut batch = self.database.write_batch()?;
         let mut task_meta_items_result = Ok(Vec::new());
         let mut task_data_items_result = Ok(Vec::new());
+        let dropped_tasks = Mutex::new(Vec::new());
 
         // Start organizing the updates in parallel
         match &mut batch {
@@ -153,6 +197,8 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorage
                             KeySpace::TaskMeta,
                             meta_updates,
                             Some(batch),
+                            &dropped_tasks,
+                            progress,
                         );
                     });
                     s.spawn(|_| {
@@ -162,6 +208,8 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorage
                             KeySpace::TaskData,
                             data_updates,
                             Some(batch),
+                            &dropped_tasks,
+                            progress,
                         );
                     });
 
@@ -181,41 +229,12 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorage
                             .into_par_iter()
                             .with_max_len(1)
                             .map(|updates| {
-                                let mut max_task_id = 0;
-
-                                let mut task_type_bytes = Vec::new();
-                                for (task_type, task_id) in updates {
-                                    let task_id: u32 = *task_id;
-                                    serialize_task_type(&task_type, &mut task_type_bytes, task_id)?;
-
-                                    batch
-                                        .put(
-                                            KeySpace::ForwardTaskCache,
-                                            Cow::Borrowed(&task_type_bytes),
-                                            Cow::Borrowed(&task_id.to_le_bytes()),
-                                        )
-                                        .with_context(|| {
-                                            anyhow!(
-                                                "Unable to write task cache {task_type:?} => \
-                                                 {task_id}"
-                                            )
-                                        })?;
-                                    batch
-                                        .put(
-                                            KeySpace::ReverseTaskCache,
-                                            Cow::Borrowed(IntKey::new(task_id).as_ref()),
-                                            Cow::Borrowed(&task_type_bytes),
-                                        )
-                                        .with_context(|| {
-                                            anyhow!(
-                                                "Unable to write task cache {task_id} => \
-                                                 {task_type:?}"
-                                            )
-                                        })?;
-                                    max_task_id = max_task_id.max(task_id + 1);
-                                }
-
-                                Ok(max_task_id)
+                                write_task_cache_entries::<
+                                    T::SerialWriteBatch<'_>,
+                                    T::ConcurrentWriteBatch<'_>,
+                                >(
+                                    &mut WriteBatchRef::concurrent(batch), updates, progress
+                                )
                             })
                             .reduce(
                                 || Ok(0),
@@ -233,6 +252,7 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorage
                         next_task_id,
                         session_id,
                         operations,
+                        invalidation_keys,
                     )?;
                     anyhow::Ok(())
                 })?;
@@ -248,6 +268,8 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorage
                             KeySpace::TaskMeta,
                             meta_updates,
                             None::<&T::ConcurrentWriteBatch<'_>>,
+                            &dropped_tasks,
+                            progress,
                         );
                     });
                     s.spawn(|_| {
@@ -256,6 +278,8 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorage
                             KeySpace::TaskData,
                             data_updates,
                             None::<&T::ConcurrentWriteBatch<'_>>,
+                            &dropped_tasks,
+                            progress,
                         );
                     });
 
@@ -271,30 +295,15 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorage
                             items = task_cache_updates.iter().map(|m| m.len()).sum::<usize>()
                         )
                         .entered();
-                        let mut task_type_bytes = Vec::new();
-                        for (task_type, task_id) in task_cache_updates.into_iter().flatten() {
-                            let task_id = *task_id;
-                            serialize_task_type(&task_type, &mut task_type_bytes, task_id)?;
-
-                            batch
-                                .put(
-                                    KeySpace::ForwardTaskCache,
-                                    Cow::Borrowed(&task_type_bytes),
-                                    Cow::Borrowed(&task_id.to_le_bytes()),
-                                )
-                                .with_context(|| {
-                                    anyhow!("Unable to write task cache {task_type:?} => {task_id}")
-                                })?;
-                            batch
-                                .put(
-                                    KeySpace::ReverseTaskCache,
-                                    Cow::Borrowed(IntKey::new(task_id).as_ref()),
-                                    Cow::Borrowed(&task_type_bytes),
-                                )
-                                .with_context(|| {
-                                    anyhow!("Unable to write task cache {task_id} => {task_type:?}")
-                                })?;
-                            next_task_id = next_task_id.max(task_id + 1);
+                        for updates in task_cache_updates {
+                            next_task_id = next_task_id.max(write_task_cache_entries::<
+                                T::SerialWriteBatch<'_>,
+                                T::ConcurrentWriteBatch<'_>,
+                            >(
+                                &mut WriteBatchRef::serial(batch),
+                                updates,
+                                progress,
+                            )?);
                         }
                     }
 
@@ -303,6 +312,7 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorage
                         next_task_id,
                         session_id,
                         operations,
+                        invalidation_keys,
                     )?;
                     anyhow::Ok(())
                 })?;
@@ -340,6 +350,239 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorage
                 .commit()
                 .with_context(|| anyhow!("Unable to commit operations"))?;
         }
+
+        let dropped_tasks = dropped_tasks.into_inner();
+        if !dropped_tasks.is_empty() {
+            // The meta and the data of a task are processed in parallel and can't be written
+            // twice in the same batch, so the other half of the dropped tasks is removed here.
+            // Until then the remaining half alone is still consistent: missing meta lets the
+            // task execute again, missing data lets it recompute its cells.
+            let _span = tracing::trace_span!("drop unreadable tasks", tasks = dropped_tasks.len())
+                .entered();
+            let mut batch = self.database.write_batch()?;
+            for task_id in &dropped_tasks {
+                for key_space in [KeySpace::TaskMeta, KeySpace::TaskData] {
+                    batch.delete(key_space, Cow::Borrowed(IntKey::new(**task_id).as_ref()))?;
+                }
+            }
+            batch
+                .commit()
+                .with_context(|| anyhow!("Unable to drop unreadable tasks"))?;
+            println!(
+                "Dropped the persisted data of {} tasks that were unreadable, they will be \
+                 recomputed",
+                dropped_tasks.len()
+            );
+        }
+        Ok(())
+    }
+}
+
+/// A persisted task with all its stored items. Returned by
+/// [`KeyValueDatabaseBackingStorage::inspect_tasks`]. The [`Display`](std::fmt::Display)
+/// implementation dumps all items in a human-readable form.
+pub struct PersistedTaskInfo {
+    pub task_id: TaskId,
+    pub task_type: Arc<CachedTaskType>,
+    meta: Vec<CachedDataItem>,
+    data: Vec<CachedDataItem>,
+}
+
+impl PersistedTaskInfo {
+    pub fn function_name(&self) -> &'static str {
+        self.task_type.get_name()
+    }
+
+    /// The number of stored items, summed over all categories.
+    pub fn item_count(&self) -> usize {
+        self.meta.len() + self.data.len()
+    }
+}
+
+impl std::fmt::Display for PersistedTaskInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Task {}: {:?}", self.task_id, self.task_type)?;
+        for (name, items) in [("meta", &self.meta), ("data", &self.data)] {
+            writeln!(f, "  {name} ({} items):", items.len())?;
+            for item in items {
+                writeln!(f, "    {item:?}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<T: KeyValueDatabase> KeyValueDatabaseBackingStorage<T> {
+    /// Iterates all persisted tasks for debugging purposes. When `function_name_filter` is set,
+    /// only tasks of functions whose name contains the filter are returned.
+    pub fn inspect_tasks<'a>(
+        &'a self,
+        function_name_filter: Option<&'a str>,
+    ) -> impl Iterator<Item = Result<PersistedTaskInfo>> + 'a {
+        let next_free_task_id =
+            get_infra_u32(&self.database, META_KEY_NEXT_FREE_TASK_ID).unwrap_or(1);
+        (1..next_free_task_id).filter_map(move |task_id| {
+            self.inspect_task(TaskId::from(task_id), function_name_filter)
+                .transpose()
+        })
+    }
+
+    fn inspect_task(
+        &self,
+        task_id: TaskId,
+        function_name_filter: Option<&str>,
+    ) -> Result<Option<PersistedTaskInfo>> {
+        let tx = self.database.begin_read_transaction()?;
+        let Some(task_type) = self.database.get(
+            &tx,
+            KeySpace::ReverseTaskCache,
+            IntKey::new(*task_id).as_ref(),
+        )?
+        else {
+            return Ok(None);
+        };
+        let task_type: Arc<CachedTaskType> = POT_CONFIG
+            .deserialize(task_type.borrow())
+            .with_context(|| anyhow!("Unable to deserialize task type of {task_id}"))?;
+        if let Some(filter) = function_name_filter {
+            if !task_type.get_name().contains(filter) {
+                return Ok(None);
+            }
+        }
+        let read_items = |key_space: KeySpace| -> Result<Vec<CachedDataItem>> {
+            let Some(bytes) = self
+                .database
+                .get(&tx, key_space, IntKey::new(*task_id).as_ref())?
+            else {
+                return Ok(Vec::new());
+            };
+            deserialize_task_data(task_id, verify_checksum(bytes.borrow())?)
+                .map(|(items, _)| items)
+                .with_context(|| anyhow!("Unable to deserialize {key_space:?} of {task_id}"))
+        };
+        let meta = read_items(KeySpace::TaskMeta)?;
+        let data = read_items(KeySpace::TaskData)?;
+        Ok(Some(PersistedTaskInfo {
+            task_id,
+            task_type,
+            meta,
+            data,
+        }))
+    }
+}
+
+fn get_infra_u32(database: &impl KeyValueDatabase, key: u32) -> Option<u32> {
+    let tx = database.begin_read_transaction().ok()?;
+    let value = database
+        .get(&tx, KeySpace::Infra, IntKey::new(key).as_ref())
+        .ok()?
+        .map(as_u32)?
+        .ok()?;
+    Some(value)
+}
+
+impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorage
+    for KeyValueDatabaseBackingStorage<T>
+{
+    type ReadTransaction<'l> = T::ReadTransaction<'l>;
+
+    fn lower_read_transaction<'l: 'i + 'r, 'i: 'r, 'r>(
+        tx: &'r Self::ReadTransaction<'l>,
+    ) -> &'r Self::ReadTransaction<'i> {
+        T::lower_read_transaction(tx)
+    }
+
+    fn next_free_task_id(&self) -> TaskId {
+        TaskId::from(get_infra_u32(&self.database, META_KEY_NEXT_FREE_TASK_ID).unwrap_or(1))
+    }
+
+    fn next_session_id(&self) -> SessionId {
+        SessionId::from(get_infra_u32(&self.database, META_KEY_SESSION_ID).unwrap_or(0) + 1)
+    }
+
+    fn uncompleted_operations(&self) -> Vec<AnyOperation> {
+        fn get(database: &impl KeyValueDatabase) -> Result<Vec<AnyOperation>> {
+            let tx = database.begin_read_transaction()?;
+            let Some(operations) = database.get(
+                &tx,
+                KeySpace::Infra,
+                IntKey::new(META_KEY_OPERATIONS).as_ref(),
+            )?
+            else {
+                return Ok(Vec::new());
+            };
+            let operations = POT_CONFIG.deserialize(operations.borrow())?;
+            Ok(operations)
+        }
+        get(&self.database).unwrap_or_default()
+    }
+
+    fn save_snapshot(
+        &self,
+        session_id: SessionId,
+        operations: Vec<Arc<AnyOperation>>,
+        task_cache_updates: Vec<ChunkedVec<(Arc<CachedTaskType>, TaskId)>>,
+        meta_updates: Vec<ChunkedVec<CachedDataUpdate>>,
+        data_updates: Vec<ChunkedVec<CachedDataUpdate>>,
+        invalidation_key_updates: InvalidationKeyUpdates,
+        progress: &(dyn Fn(usize, u64) + Sync),
+    ) -> Result<()> {
+        let span = Span::current();
+        // The io pool threads are no tokio threads, so they enter the runtime of the caller, if
+        // there is one
+        let handle = tokio::runtime::Handle::try_current().ok();
+        let turbo_tasks = turbo_tasks::turbo_tasks();
+        self.io_pool.install(move || {
+            let _span = span.entered();
+            let _guard = handle.as_ref().map(|handle| handle.enter());
+            turbo_tasks_scope(turbo_tasks, || {
+                self.save_snapshot_in_io_pool(
+                    session_id,
+                    operations,
+                    task_cache_updates,
+                    meta_updates,
+                    data_updates,
+                    invalidation_key_updates,
+                    progress,
+                )
+            })
+        })
+    }
+
+    fn can_save_task_cache(&self) -> bool {
+        true
+    }
+
+    fn save_task_cache(
+        &self,
+        task_cache_updates: Vec<ChunkedVec<(Arc<CachedTaskType>, TaskId)>>,
+    ) -> Result<()> {
+        let _span = tracing::trace_span!(
+            "save task cache",
+            items = task_cache_updates.iter().map(|m| m.len()).sum::<usize>()
+        )
+        .entered();
+        let mut batch = self.database.write_batch()?;
+        match &mut batch {
+            WriteBatch::Concurrent(ref batch, _) => {
+                save_task_cache_entries::<T::SerialWriteBatch<'_>, T::ConcurrentWriteBatch<'_>>(
+                    &mut WriteBatchRef::concurrent(batch),
+                    task_cache_updates,
+                )?;
+            }
+            WriteBatch::Serial(batch) => {
+                save_task_cache_entries::<T::SerialWriteBatch<'_>, T::ConcurrentWriteBatch<'_>>(
+                    &mut WriteBatchRef::serial(batch),
+                    task_cache_updates,
+                )?;
+            }
+        }
+        {
+            let _span = tracing::trace_span!("commit").entered();
+            batch
+                .commit()
+                .with_context(|| anyhow!("Unable to commit task cache"))?;
+        }
         Ok(())
     }
 
@@ -354,15 +597,21 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorage
     ) -> Option<TaskId> {
         fn lookup<D: KeyValueDatabase>(
             database: &D,
+            counters: &RestoreCounters,
             tx: &D::ReadTransaction<'_>,
             task_type: &CachedTaskType,
         ) -> Result<Option<TaskId>> {
+            let start = Instant::now();
             let task_type = POT_CONFIG.serialize(task_type)?;
             let Some(bytes) = database.get(tx, KeySpace::ForwardTaskCache, &task_type)? else {
                 return Ok(None);
             };
-            let bytes = bytes.borrow().try_into()?;
-            let id = TaskId::from(u32::from_le_bytes(bytes));
+            let bytes: &[u8] = bytes.borrow();
+            counters.record_read(bytes.len(), start);
+            let id = TaskId::from(u32::from_le_bytes(bytes.try_into()?));
+            counters
+                .restored_task_cache_entries
+                .fetch_add(1, Ordering::Relaxed);
             Ok(Some(id))
         }
         if self.database.is_empty() {
@@ -371,8 +620,15 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorage
             return None;
         }
         let id = self
-            .with_tx(tx, |tx| lookup(&self.database, tx, task_type))
-            .inspect_err(|err| println!("Looking up task id for {task_type:?} failed: {err:?}"))
+            .with_tx(tx, |tx| {
+                lookup(&self.database, &self.restore_counters, tx, task_type)
+            })
+            .inspect_err(|err| {
+                self.restore_counters
+                    .discarded
+                    .fetch_add(1, Ordering::Relaxed);
+                println!("Looking up task id for {task_type:?} failed: {err:?}")
+            })
             .ok()??;
         Some(id)
     }
@@ -384,9 +640,11 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorage
     ) -> Option<Arc<CachedTaskType>> {
         fn lookup<D: KeyValueDatabase>(
             database: &D,
+            counters: &RestoreCounters,
             tx: &D::ReadTransaction<'_>,
             task_id: TaskId,
         ) -> Result<Option<Arc<CachedTaskType>>> {
+            let start = Instant::now();
             let Some(bytes) = database.get(
                 tx,
                 KeySpace::ReverseTaskCache,
@@ -395,11 +653,21 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorage
             else {
                 return Ok(None);
             };
-            Ok(Some(POT_CONFIG.deserialize(bytes.borrow())?))
+            let bytes: &[u8] = bytes.borrow();
+            let task_type = POT_CONFIG.deserialize(bytes)?;
+            counters.record_read(bytes.len(), start);
+            Ok(Some(task_type))
         }
         let result = self
-            .with_tx(tx, |tx| lookup(&self.database, tx, task_id))
-            .inspect_err(|err| println!("Looking up task type for {task_id} failed: {err:?}"))
+            .with_tx(tx, |tx| {
+                lookup(&self.database, &self.restore_counters, tx, task_id)
+            })
+            .inspect_err(|err| {
+                self.restore_counters
+                    .discarded
+                    .fetch_add(1, Ordering::Relaxed);
+                println!("Looking up task type for {task_id} failed: {err:?}")
+            })
             .ok()??;
         Some(result)
     }
@@ -412,10 +680,12 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorage
     ) -> Vec<CachedDataItem> {
         fn lookup<D: KeyValueDatabase>(
             database: &D,
+            counters: &RestoreCounters,
             tx: &D::ReadTransaction<'_>,
             task_id: TaskId,
             category: TaskDataCategory,
         ) -> Result<Vec<CachedDataItem>> {
+            let start = Instant::now();
             let Some(bytes) = database.get(
                 tx,
                 match category {
@@ -428,14 +698,233 @@ impl<T: KeyValueDatabase + Send + Sync + 'static> BackingStorage
             else {
                 return Ok(Vec::new());
             };
-            let result: Vec<CachedDataItem> = POT_CONFIG.deserialize(bytes.borrow())?;
-            Ok(result)
+            let bytes: &[u8] = bytes.borrow();
+            let (items, skipped_cells) = deserialize_task_data(task_id, verify_checksum(bytes)?)?;
+            if skipped_cells > 0 {
+                println!(
+                    "Skipping {skipped_cells} unreadable cells of {task_id}, they will be \
+                     recomputed"
+                );
+            }
+            counters.record_read(bytes.len(), start);
+            counters.restored_task_data.fetch_add(1, Ordering::Relaxed);
+            counters
+                .discarded_cells
+                .fetch_add(skipped_cells as u64, Ordering::Relaxed);
+            Ok(items)
+        }
+        self.with_tx(tx, |tx| {
+            lookup(
+                &self.database,
+                &self.restore_counters,
+                tx,
+                task_id,
+                category,
+            )
+        })
+        .inspect_err(|err| {
+            self.restore_counters
+                .discarded
+                .fetch_add(1, Ordering::Relaxed);
+            println!("Looking up data for {task_id} failed: {err:?}")
+        })
+        .unwrap_or_default()
+    }
+
+    fn lookup_invalidation_key(&self, key: &str) -> Vec<TaskId> {
+        fn lookup<D: KeyValueDatabase>(database: &D, key: &str) -> Result<Vec<u32>> {
+            let tx = database.begin_read_transaction()?;
+            let Some(bytes) = database.get(&tx, KeySpace::Infra, &invalidation_key(key))? else {
+                return Ok(Vec::new());
+            };
+            Ok(POT_CONFIG.deserialize(bytes.borrow())?)
+        }
+        lookup(&self.database, key)
+            .inspect_err(|err| println!("Looking up invalidation key {key:?} failed: {err:?}"))
+            .unwrap_or_default()
+            .into_iter()
+            .map(TaskId::from)
+            .collect()
+    }
+
+    fn invalidation_key_states(&self) -> FxHashMap<String, Vec<u8>> {
+        fn get<D: KeyValueDatabase>(database: &D) -> Result<FxHashMap<String, Vec<u8>>> {
+            let tx = database.begin_read_transaction()?;
+            read_invalidation_key_states(database, &tx)
         }
-        self.with_tx(tx, |tx| lookup(&self.database, tx, task_id, category))
-            .inspect_err(|err| println!("Looking up data for {task_id} failed: {err:?}"))
+        get(&self.database)
+            .inspect_err(|err| println!("Reading invalidation key states failed: {err:?}"))
             .unwrap_or_default()
     }
 
+    fn hot_tasks(&self, limit: usize) -> Vec<TaskId> {
+        fn get<D: KeyValueDatabase>(database: &D) -> Result<Vec<(u32, u32)>> {
+            let tx = database.begin_read_transaction()?;
+            read_task_access_scores(database, &tx)
+        }
+        get(&self.database)
+            .inspect_err(|err| println!("Reading task access scores failed: {err:?}"))
+            .unwrap_or_default()
+            .into_iter()
+            .take(limit)
+            .map(|(task_id, _)| TaskId::from(task_id))
+            .collect()
+    }
+
+    fn save_task_accesses(&self, accessed_tasks: Vec<TaskId>, limit: usize) -> Result<()> {
+        let mut scores: FxHashMap<u32, u32> = {
+            let tx = self.database.begin_read_transaction()?;
+            read_task_access_scores(&self.database, &tx)?
+                .into_iter()
+                .map(|(task_id, score)| (task_id, score / 2))
+                .collect()
+        };
+        for task_id in accessed_tasks {
+            *scores.entry(*task_id).or_default() += TASK_ACCESS_SCORE;
+        }
+        let mut scores = scores
+            .into_iter()
+            .filter(|&(_, score)| score > 0)
+            .collect::<Vec<_>>();
+        scores.sort_unstable_by(|(a_id, a_score), (b_id, b_score)| {
+            b_score.cmp(a_score).then(a_id.cmp(b_id))
+        });
+        scores.truncate(limit);
+        let value = POT_CONFIG
+            .serialize(&scores)
+            .with_context(|| anyhow!("Unable to serialize task access scores"))?;
+        let mut batch = self.database.write_batch()?;
+        batch
+            .put(
+                KeySpace::Infra,
+                Cow::Borrowed(IntKey::new(META_KEY_TASK_ACCESS_SCORES).as_ref()),
+                value.into(),
+            )
+            .with_context(|| anyhow!("Unable to write task access scores"))?;
+        batch
+            .commit()
+            .with_context(|| anyhow!("Unable to commit task access scores"))
+    }
+
+    fn persisted_tasks_matching(&self, predicate: &dyn Fn(&CachedTaskType) -> bool) -> Vec<TaskId> {
+        fn lookup<D: KeyValueDatabase>(
+            database: &D,
+            predicate: &dyn Fn(&CachedTaskType) -> bool,
+        ) -> Result<Vec<TaskId>> {
+            let next_free_task_id =
+                get_infra_u32(database, META_KEY_NEXT_FREE_TASK_ID).unwrap_or(1);
+            let tx = database.begin_read_transaction()?;
+            let mut task_ids = Vec::new();
+            for task_id in 1..next_free_task_id {
+                let Some(bytes) = database.get(
+                    &tx,
+                    KeySpace::ReverseTaskCache,
+                    IntKey::new(task_id).as_ref(),
+                )?
+                else {
+                    continue;
+                };
+                // A single unreadable entry must not prevent invalidating all other tasks
+                let task_type: CachedTaskType = match POT_CONFIG.deserialize(bytes.borrow()) {
+                    Ok(task_type) => task_type,
+                    Err(err) => {
+                        println!("Skipping unreadable task type of task {task_id}: {err:?}");
+                        continue;
+                    }
+                };
+                if predicate(&task_type) {
+                    task_ids.push(TaskId::from(task_id));
+                }
+            }
+            Ok(task_ids)
+        }
+        lookup(&self.database, predicate)
+            .inspect_err(|err| println!("Looking up persisted tasks failed: {err:?}"))
+            .unwrap_or_default()
+    }
+
+    fn compact(&self) -> Result<u64> {
+        self.database.compact()
+    }
+
+    fn find_orphaned_data(&self) -> Result<Vec<TaskId>> {
+        let next_free_task_id =
+            get_infra_u32(&self.database, META_KEY_NEXT_FREE_TASK_ID).unwrap_or(1);
+        let tx = self.database.begin_read_transaction()?;
+        let mut candidates = Vec::new();
+        for task_id in 1..next_free_task_id {
+            if check_orphaned_task(&self.database, &tx, task_id)?.is_some() {
+                candidates.push(TaskId::from(task_id));
+            }
+        }
+        Ok(candidates)
+    }
+
+    fn remove_orphaned_data(&self, candidates: Vec<TaskId>) -> Result<usize> {
+        let mut orphaned_data = Vec::new();
+        let mut missing_forward_entries = Vec::new();
+        {
+            // A snapshot since the scan could have written the missing entries
+            let tx = self.database.begin_read_transaction()?;
+            for task_id in candidates {
+                match check_orphaned_task(&self.database, &tx, *task_id)? {
+                    Some(OrphanedTask::Data) => orphaned_data.push(*task_id),
+                    Some(OrphanedTask::MissingForwardEntry(task_type)) => {
+                        missing_forward_entries.push((task_type, *task_id))
+                    }
+                    None => {}
+                }
+            }
+        }
+        if orphaned_data.is_empty() && missing_forward_entries.is_empty() {
+            return Ok(0);
+        }
+        let mut batch = self.database.write_batch()?;
+        for &task_id in &orphaned_data {
+            let key = IntKey::new(task_id);
+            batch.delete(KeySpace::TaskMeta, Cow::Borrowed(key.as_ref()))?;
+            batch.delete(KeySpace::TaskData, Cow::Borrowed(key.as_ref()))?;
+        }
+        for (task_type, task_id) in &missing_forward_entries {
+            batch.put(
+                KeySpace::ForwardTaskCache,
+                Cow::Borrowed(task_type),
+                Cow::Borrowed(IntKey::new(*task_id).as_ref()),
+            )?;
+        }
+        batch
+            .commit()
+            .with_context(|| anyhow!("Unable to commit removal of orphaned data"))?;
+        Ok(orphaned_data.len() + missing_forward_entries.len())
+    }
+
+    fn verify_tasks(&self, count: usize) -> Result<Vec<(TaskId, String)>> {
+        let next_free_task_id =
+            get_infra_u32(&self.database, META_KEY_NEXT_FREE_TASK_ID).unwrap_or(1);
+        let persisted_tasks = next_free_task_id.saturating_sub(1);
+        if persisted_tasks == 0 {
+            return Ok(Vec::new());
+        }
+        let tx = self.database.begin_read_transaction()?;
+        let mut problems = Vec::new();
+        for _ in 0..count.min(persisted_tasks as usize) {
+            let position = self.verification_cursor.fetch_add(1, Ordering::Relaxed);
+            let task_id = position % persisted_tasks + 1;
+            if let Err(err) = verify_task(&self.database, &tx, task_id) {
+                problems.push((TaskId::from(task_id), format!("{err:#}")));
+            }
+        }
+        Ok(problems)
+    }
+
+    fn clear(&self) -> Result<()> {
+        self.database.clear()
+    }
+
+    fn restore_statistics(&self) -> RestoreStatistics {
+        self.restore_counters.get()
+    }
+
     fn shutdown(&self) -> Result<()> {
         self.database.shutdown()
     }
@@ -459,11 +948,74 @@ where
     )
 }
 
+/// Writes task cache entries in both directions and returns the task id after the highest
+/// written one. `progress` is called with the number of entries and the bytes they occupy.
+fn write_task_cache_entries<'a, S, C>(
+    batch: &mut WriteBatchRef<'_, 'a, S, C>,
+    updates: ChunkedVec<(Arc<CachedTaskType>, TaskId)>,
+    progress: &(dyn Fn(usize, u64) + Sync),
+) -> Result<u32>
+where
+    S: SerialWriteBatch<'a>,
+    C: ConcurrentWriteBatch<'a>,
+{
+    let mut max_task_id = 0;
+    let items = updates.len();
+    let mut bytes = 0;
+    let mut task_type_bytes = Vec::new();
+    for (task_type, task_id) in updates {
+        let task_id = *task_id;
+        serialize_task_type(&task_type, &mut task_type_bytes, task_id)?;
+        batch
+            .put(
+                KeySpace::ForwardTaskCache,
+                Cow::Borrowed(&task_type_bytes),
+                Cow::Borrowed(&task_id.to_le_bytes()),
+            )
+            .with_context(|| anyhow!("Unable to write task cache {task_type:?} => {task_id}"))?;
+        batch
+            .put(
+                KeySpace::ReverseTaskCache,
+                Cow::Borrowed(IntKey::new(task_id).as_ref()),
+                Cow::Borrowed(&task_type_bytes),
+            )
+            .with_context(|| anyhow!("Unable to write task cache {task_id} => {task_type:?}"))?;
+        max_task_id = max_task_id.max(task_id + 1);
+        bytes += 2 * (task_type_bytes.len() + 4) as u64;
+    }
+    progress(items, bytes);
+    Ok(max_task_id)
+}
+
+/// Writes task cache entries in both directions and updates the next free task id.
+fn save_task_cache_entries<'a, S, C>(
+    batch: &mut WriteBatchRef<'_, 'a, S, C>,
+    task_cache_updates: Vec<ChunkedVec<(Arc<CachedTaskType>, TaskId)>>,
+) -> Result<()>
+where
+    S: SerialWriteBatch<'a>,
+    C: ConcurrentWriteBatch<'a>,
+{
+    let mut next_task_id = get_next_free_task_id(batch)?;
+    for updates in task_cache_updates {
+        next_task_id = next_task_id.max(write_task_cache_entries(batch, updates, &|_, _| {})?);
+    }
+    batch
+        .put(
+            KeySpace::Infra,
+            Cow::Borrowed(IntKey::new(META_KEY_NEXT_FREE_TASK_ID).as_ref()),
+            Cow::Borrowed(&next_task_id.to_le_bytes()),
+        )
+        .with_context(|| anyhow!("Unable to write next free task id"))?;
+    Ok(())
+}
+
 fn save_infra<'a, S, C>(
     batch: &mut WriteBatchRef<'_, 'a, S, C>,
     next_task_id: u32,
     session_id: SessionId,
     operations: Vec<Arc<AnyOperation>>,
+    invalidation_keys: Vec<(Vec<u8>, Vec<u8>)>,
 ) -> Result<(), anyhow::Error>
 where
     S: SerialWriteBatch<'a>,
@@ -502,9 +1054,111 @@ where
             )
             .with_context(|| anyhow!("Unable to write operations"))?;
     }
+    {
+        let _span =
+            tracing::trace_span!("update invalidation keys", keys = invalidation_keys.len())
+                .entered();
+        for (key, value) in invalidation_keys {
+            batch
+                .put(KeySpace::Infra, key.into(), value.into())
+                .with_context(|| anyhow!("Unable to write invalidation keys"))?;
+        }
+    }
     Ok(())
 }
 
+fn read_invalidation_key_states<D: KeyValueDatabase>(
+    database: &D,
+    tx: &D::ReadTransaction<'_>,
+) -> Result<FxHashMap<String, Vec<u8>>> {
+    let Some(bytes) = database.get(
+        tx,
+        KeySpace::Infra,
+        IntKey::new(META_KEY_INVALIDATION_KEY_STATES).as_ref(),
+    )?
+    else {
+        return Ok(FxHashMap::default());
+    };
+    Ok(POT_CONFIG.deserialize(bytes.borrow())?)
+}
+
+/// Reads the access scores of tasks, the highest score first.
+fn read_task_access_scores<D: KeyValueDatabase>(
+    database: &D,
+    tx: &D::ReadTransaction<'_>,
+) -> Result<Vec<(u32, u32)>> {
+    let Some(bytes) = database.get(
+        tx,
+        KeySpace::Infra,
+        IntKey::new(META_KEY_TASK_ACCESS_SCORES).as_ref(),
+    )?
+    else {
+        return Ok(Vec::new());
+    };
+    Ok(POT_CONFIG.deserialize(bytes.borrow())?)
+}
+
+/// Merges the new invalidation key registrations and states with the persisted ones. Returns the
+/// serialized key value pairs to write.
+fn merge_invalidation_keys(
+    database: &impl KeyValueDatabase,
+    updates: InvalidationKeyUpdates,
+) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+    if updates.is_empty() {
+        return Ok(Vec::new());
+    }
+    let InvalidationKeyUpdates { tasks, states } = updates;
+    // The registered and the removed tasks per key
+    let mut map: FxHashMap<String, (Vec<u32>, FxHashSet<u32>)> = FxHashMap::default();
+    for ((key, task_id), registered) in tasks {
+        let (registered_tasks, removed_tasks) = map.entry(key).or_default();
+        if registered {
+            registered_tasks.push(*task_id);
+        } else {
+            removed_tasks.insert(*task_id);
+        }
+    }
+    let tx = database.begin_read_transaction()?;
+    let mut result = Vec::with_capacity(map.len() + 1);
+    if !states.is_empty() {
+        let mut persisted_states = read_invalidation_key_states(database, &tx)?;
+        for (key, state) in states {
+            if let Some(state) = state {
+                persisted_states.insert(key, state);
+            } else {
+                persisted_states.remove(&key);
+            }
+        }
+        let value = POT_CONFIG
+            .serialize(&persisted_states)
+            .with_context(|| anyhow!("Unable to serialize invalidation key states"))?;
+        result.push((
+            IntKey::new(META_KEY_INVALIDATION_KEY_STATES)
+                .as_ref()
+                .to_vec(),
+            value,
+        ));
+    }
+    for (key, (mut task_ids, removed_task_ids)) in map {
+        let key = invalidation_key(&key);
+        if let Some(bytes) = database.get(&tx, KeySpace::Infra, &key)? {
+            let persisted: Vec<u32> = POT_CONFIG.deserialize(bytes.borrow())?;
+            task_ids.extend(
+                persisted
+                    .into_iter()
+                    .filter(|task_id| !removed_task_ids.contains(task_id)),
+            );
+        }
+        task_ids.sort_unstable();
+        task_ids.dedup();
+        let value = POT_CONFIG
+            .serialize(&task_ids)
+            .with_context(|| anyhow!("Unable to serialize invalidation key"))?;
+        result.push((key, value));
+    }
+    Ok(result)
+}
+
 fn serialize_task_type(
     task_type: &Arc<CachedTaskType>,
     mut task_type_bytes: &mut Vec<u8>,
@@ -536,17 +1190,21 @@ fn process_task_data<'a, B: ConcurrentWriteBatch<'a> + Send + Sync>(
     key_space: KeySpace,
     updates: Vec<ChunkedVec<CachedDataUpdate>>,
     batch: Option<&B>,
+    dropped_tasks: &Mutex<Vec<TaskId>>,
+    progress: &(dyn Fn(usize, u64) + Sync),
 ) -> Result<SerializedTasks> {
     let span = Span::current();
     let turbo_tasks = turbo_tasks::turbo_tasks();
-    let handle = tokio::runtime::Handle::current();
+    let handle = tokio::runtime::Handle::try_current().ok();
     updates
         .into_par_iter()
         .with_max_len(1)
         .map(|updates| {
             let _span = span.clone().entered();
-            let _guard = handle.clone().enter();
+            let _guard = handle.as_ref().map(|handle| handle.enter());
             turbo_tasks_scope(turbo_tasks.clone(), || {
+                let items = updates.len();
+                let mut bytes = 0;
                 let mut task_updates: FxHashMap<TaskId, TaskUpdates> =
                     FxHashMap::with_capacity_and_hasher(updates.len(), Default::default());
 
@@ -675,23 +1333,36 @@ fn process_task_data<'a, B: ConcurrentWriteBatch<'a> + Send + Sync>(
                 };
                 for (task, mut updates) in task_updates {
                     // Restore the old task data
-                    if let Some(old_data) =
-                        database.get(&tx, key_space, IntKey::new(*task).as_ref())?
-                    {
-                        let old_data: Vec<CachedDataItem> = match POT_CONFIG
-                            .deserialize(old_data.borrow())
-                        {
-                            Ok(d) => d,
-                            Err(_) => serde_path_to_error::deserialize(
-                                &mut pot_de_symbol_list()
-                                    .deserializer_for_slice(old_data.borrow())?,
-                            )
-                            .with_context(|| {
-                                let old_data: &[u8] = old_data.borrow();
-                                anyhow!("Unable to deserialize old value of {task}: {old_data:?}")
-                            })?,
-                        };
-
+                    let old_data = database.get(&tx, key_space, IntKey::new(*task).as_ref())?;
+                    let old_data = old_data
+                        .as_ref()
+                        .map(|old_data| {
+                            verify_checksum(old_data.borrow())
+                                .context("Corrupted data")
+                                .and_then(|old_data| deserialize_task_data(task, old_data))
+                                .map(|(items, skipped_cells)| {
+                                    if skipped_cells > 0 {
+                                        println!(
+                                            "Dropping {skipped_cells} unreadable cells of {task}, \
+                                             they will be recomputed"
+                                        );
+                                    }
+                                    items
+                                })
+                        })
+                        .transpose();
+                    let old_data = match old_data {
+                        Ok(old_data) => old_data,
+                        Err(err) => {
+                            // The updates alone would be an incomplete state of the task, so the
+                            // task is dropped and recomputed. Corrupted data or data of another
+                            // version shouldn't fail the whole snapshot.
+                            println!("Dropping unreadable {key_space:?} of {task}: {err:?}");
+                            dropped_tasks.lock().push(task);
+                            continue;
+                        }
+                    };
+                    if let Some(old_data) = old_data {
                         // Reserve capacity to avoid rehashing later
                         updates.reserve(old_data.len());
 
@@ -707,7 +1378,8 @@ fn process_task_data<'a, B: ConcurrentWriteBatch<'a> + Send + Sync>(
                     updates.retain(|_, (_, value)| value.is_some());
 
                     // Serialize new data
-                    let value = serialize(task, &mut updates)?;
+                    let value = append_checksum(serialize(task, &mut updates)?);
+                    bytes += (value.len() + 4) as u64;
 
                     if let Some(batch) = batch {
                         batch.put(
@@ -722,12 +1394,158 @@ fn process_task_data<'a, B: ConcurrentWriteBatch<'a> + Send + Sync>(
                 }
 
                 span.record("restored_tasks", restored_tasks);
+                progress(items, bytes);
                 Ok(tasks)
             })
         })
         .collect::<Result<Vec<_>>>()
 }
 
+enum OrphanedTask {
+    /// The task has data, but no task type to restore it with.
+    Data,
+    /// The task type is persisted, but the task can't be looked up by it. Contains the
+    /// serialized task type.
+    MissingForwardEntry(Vec<u8>),
+}
+
+/// Checks whether the persisted entries of a task are orphaned, see
+/// [`BackingStorage::find_orphaned_data`].
+fn check_orphaned_task<D: KeyValueDatabase>(
+    database: &D,
+    tx: &D::ReadTransaction<'_>,
+    task_id: u32,
+) -> Result<Option<OrphanedTask>> {
+    let key = IntKey::new(task_id);
+    let Some(bytes) = database.get(tx, KeySpace::ReverseTaskCache, key.as_ref())? else {
+        // Without a task type the data can't be restored
+        let has_data = database
+            .get(tx, KeySpace::TaskMeta, key.as_ref())?
+            .is_some()
+            || database
+                .get(tx, KeySpace::TaskData, key.as_ref())?
+                .is_some();
+        return Ok(has_data.then_some(OrphanedTask::Data));
+    };
+    // Forward entries can't be enumerated, so only those of known task types are checked. A task
+    // type that can't be read is left to the lookups to report.
+    let Ok(task_type) = POT_CONFIG.deserialize::<CachedTaskType>(bytes.borrow()) else {
+        return Ok(None);
+    };
+    let task_type = POT_CONFIG.serialize(&task_type)?;
+    if database
+        .get(tx, KeySpace::ForwardTaskCache, &task_type)?
+        .is_none()
+    {
+        // The task is still referenced by the edges of other tasks, so the entry is restored
+        // instead of removing the task
+        return Ok(Some(OrphanedTask::MissingForwardEntry(task_type)));
+    }
+    Ok(None)
+}
+
+/// Appends a checksum to serialized task data, so corrupted data can be detected when reading it.
+fn append_checksum(mut value: Vec<u8>) -> Vec<u8> {
+    let mut hasher = Xxh3Hash64Hasher::new();
+    hasher.write_bytes(&value);
+    value.extend_from_slice(&hasher.finish().to_le_bytes());
+    value
+}
+
+/// Verifies the checksum appended by [`append_checksum`] and returns the data without it.
+fn verify_checksum(bytes: &[u8]) -> Result<&[u8]> {
+    let Some((data, checksum)) = bytes.split_last_chunk::<8>() else {
+        bail!("Task data is too short to contain a checksum");
+    };
+    let mut hasher = Xxh3Hash64Hasher::new();
+    hasher.write_bytes(data);
+    if hasher.finish() != u64::from_le_bytes(*checksum) {
+        bail!("Task data checksum mismatch");
+    }
+    Ok(data)
+}
+
+/// Checks the persisted entries of a task, see [`BackingStorage::verify_tasks`].
+fn verify_task<D: KeyValueDatabase>(
+    database: &D,
+    tx: &D::ReadTransaction<'_>,
+    task_id: u32,
+) -> Result<()> {
+    let key = IntKey::new(task_id);
+    let task_type = database
+        .get(tx, KeySpace::ReverseTaskCache, key.as_ref())?
+        .map(|bytes| POT_CONFIG.deserialize::<CachedTaskType>(bytes.borrow()))
+        .transpose()
+        .context("Unreadable task type")?;
+    if let Some(task_type) = &task_type {
+        let task_type = POT_CONFIG.serialize(task_type)?;
+        let forward_task_id = database
+            .get(tx, KeySpace::ForwardTaskCache, &task_type)?
+            .map(as_u32)
+            .transpose()
+            .context("Unreadable task cache entry")?;
+        match forward_task_id {
+            None => bail!("The task cache entry of the task type is missing"),
+            Some(forward_task_id) if forward_task_id != task_id => {
+                bail!("The task cache entry of the task type points to task {forward_task_id}")
+            }
+            Some(_) => {}
+        }
+    }
+    for key_space in [KeySpace::TaskMeta, KeySpace::TaskData] {
+        let Some(bytes) = database.get(tx, key_space, key.as_ref())? else {
+            continue;
+        };
+        if task_type.is_none() {
+            bail!("{key_space:?} is persisted without a task type");
+        }
+        let bytes =
+            verify_checksum(bytes.borrow()).with_context(|| anyhow!("Corrupted {key_space:?}"))?;
+        let (_, skipped_cells) = deserialize_task_data(TaskId::from(task_id), bytes)
+            .with_context(|| anyhow!("Unreadable {key_space:?}"))?;
+        if skipped_cells > 0 {
+            bail!("{skipped_cells} cells of {key_space:?} are unreadable");
+        }
+    }
+    Ok(())
+}
+
+/// Deserializes the persisted items of a task. Data that was written by another version can
+/// contain unknown item types or value types. Cells can be recomputed, so unreadable cells are
+/// skipped. Any other unreadable item fails the task, which is then recomputed as a whole, while
+/// the other tasks are still restored. Returns the items and the number of skipped cells.
+fn deserialize_task_data(task: TaskId, bytes: &[u8]) -> Result<(Vec<CachedDataItem>, usize)> {
+    if let Ok(items) = POT_CONFIG.deserialize(bytes) {
+        return Ok((items, 0));
+    }
+    // Slow path: deserialize the items one by one to find out which ones are unreadable
+    let values: Vec<pot::Value<'_>> = POT_CONFIG.deserialize(bytes)?;
+    let mut items = Vec::with_capacity(values.len());
+    let mut skipped_cells = 0;
+    for value in &values {
+        match value.deserialize_as::<CachedDataItem>() {
+            Ok(item) => items.push(item),
+            Err(_) if is_cell_data(value) => skipped_cells += 1,
+            Err(err) => {
+                return Err(err).with_context(|| anyhow!("Unable to deserialize item of {task}"));
+            }
+        }
+    }
+    Ok((items, skipped_cells))
+}
+
+/// Returns true when the serialized item is a `CachedDataItem::CellData`. Enum variants are
+/// serialized as a mapping from the variant name to the content.
+fn is_cell_data(value: &pot::Value<'_>) -> bool {
+    match value {
+        pot::Value::Mappings(mappings) => matches!(
+            mappings.as_slice(),
+            [(pot::Value::String(variant), _)] if variant == "CellData"
+        ),
+        _ => false,
+    }
+}
+
 fn serialize(task: TaskId, data: &mut TaskUpdates) -> Result<Vec<u8>> {
     Ok(
         match POT_CONFIG.serialize(&SerializeLikeVecOfCachedDataItem(data)) {
@@ -2,6 +2,7 @@
 #![feature(associated_type_defaults)]
 #![feature(iter_collect_into)]
 #![feature(box_patterns)]
+#![feature(file_lock)]
 
 mod backend;
 mod backing_storage;
@@ -11,16 +12,28 @@ mod database;
 mod kv_backing_storage;
 mod utils;
 
-use std::path::Path;
+use std::{path::Path, thread::JoinHandle};
 
 use anyhow::Result;
 
 pub use self::{
-    backend::{BackendOptions, StorageMode, TurboTasksBackend},
-    kv_backing_storage::KeyValueDatabaseBackingStorage,
+    backend::{
+        AggregationOptions, BackendEvent, BackendEventListener, BackendEventStream, BackendOptions,
+        ChangedTask, FunctionExecutionStatistics, LatencyHistogram, RetryPolicy, SnapshotDiff,
+        SnapshotInfo, SnapshotInterval, SnapshotProgress, StorageMode, TaskCacheLookup,
+        TaskIdSpaceExhausted, TaskInvalidationChurn, TurboTasksBackend,
+    },
+    backing_storage::RestoreStatistics,
+    data::TaskError,
+    database::{
+        namespace::{clear_namespace, namespace_path, namespace_statistics, NamespaceStatistics},
+        remote_cache::RemoteCache,
+    },
+    kv_backing_storage::{KeyValueDatabaseBackingStorage, PersistedTaskInfo},
 };
 use crate::database::{
-    db_versioning::handle_db_versioning, noop_kv::NoopKvDb, turbo::TurboKeyValueDatabase,
+    cache_profile::CacheProfileLayer, db_versioning::handle_db_versioning, noop_kv::NoopKvDb,
+    turbo::TurboKeyValueDatabase,
 };
 
 #[cfg(feature = "lmdb")]
@@ -42,7 +55,7 @@ pub fn lmdb_backing_storage(path: &Path, version_info: &str) -> Result<LmdbBacki
         startup_cache::StartupCacheLayer,
     };
 
-    let path = handle_db_versioning(path, version_info)?;
+    let path = handle_db_versioning(path, version_info, false)?;
     let fresh_db = is_fresh(&path);
     let database = crate::database::lmdb::LmbdKeyValueDatabase::new(&path)?;
     let database = FreshDbOptimization::new(database, fresh_db);
@@ -53,12 +66,112 @@ pub fn lmdb_backing_storage(path: &Path, version_info: &str) -> Result<LmdbBacki
 
 pub type TurboBackingStorage = KeyValueDatabaseBackingStorage<TurboKeyValueDatabase>;
 
+/// Opens the backing storage. A corrupted database is replaced by an empty one, see
+/// [`turbo_backing_storage_with_recovery`].
 pub fn turbo_backing_storage(path: &Path, version_info: &str) -> Result<TurboBackingStorage> {
-    let path = handle_db_versioning(path, version_info)?;
-    let database = TurboKeyValueDatabase::new(path)?;
+    let (storage, _) = turbo_backing_storage_with_recovery(path, version_info)?;
+    Ok(storage)
+}
+
+/// Opens the backing storage. When the database is corrupted, it's moved aside and an empty
+/// database is used instead. In that case the error is returned along with the storage, so the
+/// embedder can inform the user.
+pub fn turbo_backing_storage_with_recovery(
+    path: &Path,
+    version_info: &str,
+) -> Result<(TurboBackingStorage, Option<anyhow::Error>)> {
+    let path = handle_db_versioning(path, version_info, false)?;
+    let (database, recovered_error) = TurboKeyValueDatabase::new_with_recovery(path)?;
+    Ok((
+        KeyValueDatabaseBackingStorage::new(database),
+        recovered_error,
+    ))
+}
+
+/// Opens the backing storage of a namespace within a cache directory that is shared by multiple
+/// projects, see [`namespace_path`]. Each namespace is versioned, locked and cleared
+/// independently.
+pub fn turbo_backing_storage_in_namespace(
+    path: &Path,
+    namespace: &str,
+    version_info: &str,
+) -> Result<TurboBackingStorage> {
+    turbo_backing_storage(&namespace_path(path, namespace)?, version_info)
+}
+
+pub type ProfiledBackingStorage =
+    KeyValueDatabaseBackingStorage<CacheProfileLayer<TurboKeyValueDatabase>>;
+
+/// Opens the backing storage for a cache profile, so that commands with a different environment
+/// can share the cache directory, e.g. `next build` reuses the still valid work of `next dev`.
+/// The default profile is opened with [`turbo_backing_storage`]. Another profile reads the data
+/// of the default profile where it has none of its own, but keeps its changes apart.
+pub fn turbo_backing_storage_with_profile(
+    path: &Path,
+    version_info: &str,
+    profile: &str,
+) -> Result<ProfiledBackingStorage> {
+    let path = handle_db_versioning(path, version_info, false)?;
+    let (database, _) = TurboKeyValueDatabase::new_with_recovery(path)?;
+    let database = CacheProfileLayer::new(
+        database,
+        profile,
+        &kv_backing_storage::session_id_key(),
+        kv_backing_storage::shared_infra_keys(),
+    )?;
+    Ok(KeyValueDatabaseBackingStorage::new(database))
+}
+
+/// Opens the backing storage. An empty local cache is first seeded with the cache that was last
+/// published to the remote cache, see [`RemoteCache`].
+pub fn turbo_backing_storage_with_remote_cache(
+    path: &Path,
+    version_info: &str,
+    remote: &impl RemoteCache,
+) -> Result<TurboBackingStorage> {
+    let path = handle_db_versioning(path, version_info, false)?;
+    database::remote_cache::seed(&path, remote);
+    let (database, _) = TurboKeyValueDatabase::new_with_recovery(path)?;
     Ok(KeyValueDatabaseBackingStorage::new(database))
 }
 
+/// Publishes the cache to the remote cache in a background thread. Must only be called after the
+/// backend using the cache has stopped.
+pub fn upload_cache(
+    path: &Path,
+    version_info: &str,
+    remote: impl RemoteCache,
+) -> Result<JoinHandle<Result<()>>> {
+    let path = handle_db_versioning(path, version_info, true)?;
+    database::remote_cache::upload(&path, remote)
+}
+
+/// Opens the backing storage without modifying it, e.g. for tools that inspect the cache. All
+/// writes to the storage fail, so it should be used with [`StorageMode::ReadOnly`]. Fails when
+/// the cache was cleared, since its content is only removed when it's opened for writing.
+pub fn turbo_backing_storage_read_only(
+    path: &Path,
+    version_info: &str,
+) -> Result<TurboBackingStorage> {
+    let path = handle_db_versioning(path, version_info, true)?;
+    let database = TurboKeyValueDatabase::new_read_only(path)?;
+    Ok(KeyValueDatabaseBackingStorage::new(database))
+}
+
+/// Packs the persisted cache into a single compressed file, e.g. to restore it in another CI job
+/// with [`import_cache`]. Fails when the cache was cleared.
+pub fn export_cache(path: &Path, version_info: &str, target: &Path) -> Result<()> {
+    let path = handle_db_versioning(path, version_info, true)?;
+    database::turbo::export(&path, target)
+}
+
+/// Restores a cache that was packed by [`export_cache`]. The existing cache for this version is
+/// replaced.
+pub fn import_cache(path: &Path, version_info: &str, source: &Path) -> Result<()> {
+    let path = handle_db_versioning(path, version_info, false)?;
+    database::turbo::import(&path, source)
+}
+
 pub type NoopBackingStorage = KeyValueDatabaseBackingStorage<NoopKvDb>;
 
 pub fn noop_backing_storage() -> NoopBackingStorage {
@@ -1,6 +1,10 @@
-use std::{borrow::Borrow, hash::Hash};
+use std::{
+    borrow::Borrow,
+    hash::{BuildHasherDefault, Hash},
+};
 
-use dashmap::mapref::entry::Entry;
+use dashmap::{iter::Iter, mapref::entry::Entry};
+use rustc_hash::FxHasher;
 use turbo_tasks::FxDashMap;
 
 /// A bidirectional [`FxDashMap`] that allows lookup by key or value.
@@ -17,10 +21,12 @@ where
     K: Eq + Hash + Clone,
     V: Eq + Hash + Clone,
 {
-    pub fn new() -> Self {
+    /// Creates a map with the given number of shards in each direction. More shards reduce lock
+    /// contention when many threads access the map concurrently. Must be a power of two.
+    pub fn with_shard_amount(shard_amount: usize) -> Self {
         Self {
-            forward: FxDashMap::default(),
-            reverse: FxDashMap::default(),
+            forward: FxDashMap::with_hasher_and_shard_amount(Default::default(), shard_amount),
+            reverse: FxDashMap::with_hasher_and_shard_amount(Default::default(), shard_amount),
         }
     }
 
@@ -40,6 +46,11 @@ where
         self.reverse.get(key).map(|v| v.value().clone())
     }
 
+    /// Iterates all entries. See [`dashmap::DashMap::iter`] for the locking behavior.
+    pub fn iter(&self) -> Iter<'_, K, V, BuildHasherDefault<FxHasher>> {
+        self.forward.iter()
+    }
+
     pub fn try_insert(&self, key: K, value: V) -> Result<(), V> {
         match self.forward.entry(key) {
             Entry::Occupied(e) => Err(e.get().clone()),
@@ -38,6 +38,13 @@ impl<T, H> Sharded<T, H> {
         self.data[shard as usize].lock()
     }
 
+    /// Visits all shards. The shards are locked one after another.
+    pub fn for_each(&self, mut f: impl FnMut(&T)) {
+        for m in self.data.iter() {
+            f(&m.lock());
+        }
+    }
+
     pub fn take<R>(&self, map: impl Fn(T) -> R) -> Vec<R>
     where
         T: Default,
@@ -0,0 +1 @@
+../../turbo-tasks-testing/tests/wait_cycle.rs
\ No newline at end of file
@@ -246,8 +246,11 @@ impl DiskFileSystemInner {
     /// has to be called within a turbo-tasks function
     fn register_read_invalidator(&self, path: &Path) -> Result<()> {
         let invalidator = turbo_tasks::get_invalidator();
-        self.invalidator_map
-            .insert(path_to_key(path), invalidator, None);
+        let key = path_to_key(path);
+        // The invalidator only lives in memory, the key allows to invalidate the task after a
+        // restart
+        turbo_tasks::register_invalidation_key(key.clone());
+        self.invalidator_map.insert(key, invalidator, None);
         #[cfg(not(any(target_os = "macos", target_os = "windows")))]
         if let Some(dir) = path.parent() {
             self.watcher.ensure_watching(dir, self.root_path())?;
@@ -288,8 +291,9 @@ impl DiskFileSystemInner {
     /// has to be called within a turbo-tasks function
     fn register_dir_invalidator(&self, path: &Path) -> Result<()> {
         let invalidator = turbo_tasks::get_invalidator();
-        self.dir_invalidator_map
-            .insert(path_to_key(path), invalidator, None);
+        let key = path_to_key(path);
+        turbo_tasks::register_invalidation_key(key.clone());
+        self.dir_invalidator_map.insert(key, invalidator, None);
         #[cfg(not(any(target_os = "macos", target_os = "windows")))]
         self.watcher.ensure_watching(path, self.root_path())?;
         Ok(())
@@ -0,0 +1,15 @@
+#![feature(arbitrary_self_types)]
+#![feature(arbitrary_self_types_pointers)]
+
+use turbo_tasks::Vc;
+
+#[turbo_tasks::function(local, memory_only)]
+fn memory_only_local() -> Vc<u32> {
+    Vc::cell(0)
+}
+
+fn main() {
+    // the macro should be error-tolerent and this function should still be created
+    // despite the earlier compilation error, so this line should not also error
+    let _ = memory_only_local();
+}
@@ -764,6 +764,8 @@ pub struct FunctionArguments {
     /// task-local state. The function call itself will not be cached, but cells will be created on
     /// the parent task.
     pub local: Option<Span>,
+    /// The cell contents of the task are not persisted and are recomputed after a restart.
+    pub memory_only: Option<Span>,
 }
 
 impl Parse for FunctionArguments {
@@ -791,11 +793,14 @@ impl Parse for FunctionArguments {
                 ("local", Meta::Path(_)) => {
                     parsed_args.local = Some(meta.span());
                 }
+                ("memory_only", Meta::Path(_)) => {
+                    parsed_args.memory_only = Some(meta.span());
+                }
                 (_, meta) => {
                     return Err(syn::Error::new_spanned(
                         meta,
                         "unexpected token, expected one of: \"fs\", \"network\", \"operation\", \
-                         \"local\"",
+                         \"local\", \"memory_only\"",
                     ))
                 }
             }
@@ -806,6 +811,13 @@ impl Parse for FunctionArguments {
                 "\"operation\" is mutually exclusive with the \"local\" option",
             ));
         }
+        if let (Some(_), Some(span)) = (parsed_args.local, parsed_args.memory_only) {
+            return Err(syn::Error::new(
+                span,
+                "\"memory_only\" is mutually exclusive with the \"local\" option, local functions \
+                 don't have cells of their own",
+            ));
+        }
         Ok(parsed_args)
     }
 }
@@ -1089,6 +1101,7 @@ pub struct NativeFn {
     pub is_method: bool,
     pub filter_trait_call_args: Option<FilterTraitCallArgsTokens>,
     pub local: bool,
+    pub memory_only: bool,
 }
 
 impl NativeFn {
@@ -1103,6 +1116,7 @@ impl NativeFn {
             is_method,
             filter_trait_call_args,
             local,
+            memory_only,
         } = self;
 
         if *is_method {
@@ -1127,6 +1141,7 @@ impl NativeFn {
                         #function_path_string.to_owned(),
                         turbo_tasks::macro_helpers::FunctionMeta {
                             local: #local,
+                            memory_only: #memory_only,
                         },
                         #arg_filter,
                         #function_path,
@@ -1141,6 +1156,7 @@ impl NativeFn {
                         #function_path_string.to_owned(),
                         turbo_tasks::macro_helpers::FunctionMeta {
                             local: #local,
+                            memory_only: #memory_only,
                         },
                         #function_path,
                     )
@@ -40,6 +40,7 @@ pub fn function(args: TokenStream, input: TokenStream) -> TokenStream {
         .inspect_err(|err| errors.push(err.to_compile_error()))
         .unwrap_or_default();
     let local = args.local.is_some();
+    let memory_only = args.memory_only.is_some();
 
     let Some(turbo_fn) = TurboFn::new(&sig, DefinitionContext::NakedFn, args) else {
         return quote! {
@@ -60,6 +61,7 @@ pub fn function(args: TokenStream, input: TokenStream) -> TokenStream {
         is_method: turbo_fn.is_method(),
         filter_trait_call_args: None, // not a trait method
         local,
+        memory_only,
     };
     let native_function_ident = get_native_function_ident(ident);
     let native_function_ty = native_fn.ty();
@@ -9,7 +9,7 @@ use syn::{
     parse_macro_input, parse_quote,
     punctuated::Punctuated,
     spanned::Spanned,
-    Error, Fields, FieldsUnnamed, Generics, Item, ItemEnum, ItemStruct, Lit, LitStr, Meta,
+    Error, Fields, FieldsUnnamed, Generics, Item, ItemEnum, ItemStruct, Lit, LitInt, LitStr, Meta,
     MetaNameValue, Result, Token,
 };
 use turbo_tasks_macros_shared::{
@@ -110,6 +110,7 @@ struct ValueArguments {
     cell_mode: CellMode,
     manual_eq: bool,
     transparent: bool,
+    serialization_version: Option<LitInt>,
     /// Should we `#[derive(turbo_tasks::OperationValue)]`?
     operation: Option<Span>,
 }
@@ -122,6 +123,7 @@ impl Parse for ValueArguments {
             cell_mode: CellMode::Shared,
             manual_eq: false,
             transparent: false,
+            serialization_version: None,
             operation: None,
         };
         let punctuated: Punctuated<Meta, Token![,]> = input.parse_terminated(Meta::parse)?;
@@ -177,6 +179,15 @@ impl Parse for ValueArguments {
                 ("transparent", Meta::Path(_)) => {
                     result.transparent = true;
                 }
+                (
+                    "serialization_version",
+                    Meta::NameValue(MetaNameValue {
+                        lit: Lit::Int(int), ..
+                    }),
+                ) => {
+                    int.base10_parse::<u32>()?;
+                    result.serialization_version = Some(int);
+                }
                 ("operation", Meta::Path(path)) => {
                     result.operation = Some(path.span());
                 }
@@ -185,7 +196,8 @@ impl Parse for ValueArguments {
                         &meta,
                         format!(
                             "unexpected {:?}, expected \"shared\", \"into\", \"serialization\", \
-                             \"cell\", \"eq\", \"transparent\", or \"operation\"",
+                             \"cell\", \"eq\", \"transparent\", \"serialization_version\", or \
+                             \"operation\"",
                             meta
                         ),
                     ))
@@ -205,6 +217,7 @@ pub fn value(args: TokenStream, input: TokenStream) -> TokenStream {
         cell_mode,
         manual_eq,
         transparent,
+        serialization_version,
         operation,
     } = parse_macro_input!(args as ValueArguments);
 
@@ -399,6 +412,22 @@ pub fn value(args: TokenStream, input: TokenStream) -> TokenStream {
         }
     };
 
+    let new_value_type = if let Some(version) = serialization_version {
+        quote! {
+            #new_value_type.with_serialization_version(#version)
+        }
+    } else {
+        new_value_type
+    };
+
+    let new_value_type = if manual_eq {
+        new_value_type
+    } else {
+        quote! {
+            #new_value_type.with_eq::<#ident>()
+        }
+    };
+
     let for_input_marker = match serialization_mode {
         SerializationMode::None | SerializationMode::Auto | SerializationMode::Custom => quote! {},
         SerializationMode::AutoForInput | SerializationMode::CustomForInput => quote! {
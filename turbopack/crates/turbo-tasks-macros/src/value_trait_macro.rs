@@ -126,6 +126,7 @@ pub fn value_trait(args: TokenStream, input: TokenStream) -> TokenStream {
                 //   argument. (This could be fixed)
                 // - This only makes sense when a default implementation is present.
                 local: false,
+                memory_only: false,
             };
 
             let native_function_ident = get_trait_default_impl_function_ident(trait_ident, ident);
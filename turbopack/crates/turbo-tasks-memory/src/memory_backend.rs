@@ -20,7 +20,7 @@ use turbo_prehash::{BuildHasherExt, PassThroughHash, PreHashed};
 use turbo_tasks::{
     backend::{
         Backend, BackendJobId, CachedTaskType, CellContent, TaskCollectiblesMap, TaskExecutionSpec,
-        TransientTaskType, TypedCellContent,
+        TransientTaskType, TurboTasksPanic, TypedCellContent,
     },
     event::EventListener,
     task_statistics::TaskStatisticsApi,
@@ -407,7 +407,7 @@ impl Backend for MemoryBackend {
     fn task_execution_result(
         &self,
         task_id: TaskId,
-        result: Result<Result<RawVc>, Option<Cow<'static, str>>>,
+        result: Result<Result<RawVc>, TurboTasksPanic>,
         turbo_tasks: &dyn TurboTasksBackendApi<MemoryBackend>,
     ) {
         self.with_task(task_id, |task| {
@@ -417,6 +417,7 @@ impl Backend for MemoryBackend {
                     panic!("Task {} returned itself as output", task.get_description());
                 }
             }
+            let result = result.map_err(|panic| Some(Cow::Owned(panic.to_string())));
             task.execution_result(result, self, turbo_tasks);
         })
     }
@@ -160,6 +160,10 @@ impl TurboTasksApi for VcStorage {
         // ingore
     }
 
+    fn register_invalidation_key(&self, _task: TaskId, _key: String) {
+        // ignore
+    }
+
     fn notify_scheduled_tasks(&self) {
         // ignore
     }
@@ -173,6 +173,31 @@ async fn test_dyn_trait_methods() -> Result<()> {
     .await
 }
 
+#[tokio::test]
+async fn test_hit_ratio() -> Result<()> {
+    run_without_cache_check(&REGISTRATION, async move {
+        enable_stats();
+        for i in 0..4 {
+            double(i).await.unwrap();
+            // use cached results
+            double(i).await.unwrap();
+            double(i).await.unwrap();
+        }
+        let stats = turbo_tasks::turbo_tasks()
+            .task_statistics()
+            .get()
+            .unwrap()
+            .get();
+        assert_eq!(stats.len(), 1);
+        let (_, double_stats) = stats[0];
+        assert_eq!(double_stats.cache_miss, 4);
+        assert_eq!(double_stats.cache_hit, 8);
+        assert!((double_stats.hit_ratio() - 2.0 / 3.0).abs() < f64::EPSILON);
+        Ok(())
+    })
+    .await
+}
+
 // creates Vcs, but doesn't ever execute them
 #[tokio::test]
 async fn test_no_execution() -> Result<()> {
@@ -0,0 +1,71 @@
+#![feature(arbitrary_self_types)]
+#![feature(arbitrary_self_types_pointers)]
+#![allow(clippy::needless_return)] // tokio macro-generated code doesn't respect this
+
+use anyhow::Result;
+use tokio::time::{timeout, Duration};
+use turbo_tasks::Vc;
+use turbo_tasks_testing::{register, run, Registration};
+
+static REGISTRATION: Registration = register!();
+
+#[tokio::test]
+async fn cycle_is_reported() {
+    run(&REGISTRATION, || async {
+        // timeout: a cycle that isn't detected would hang forever
+        let result = timeout(Duration::from_secs(5), cycle_a().strongly_consistent())
+            .await
+            .expect("the cycle should fail instead of hanging");
+        let error = format!("{:?}", result.expect_err("the cycle should fail"));
+        assert!(
+            error.contains("Dependency cycle detected"),
+            "unexpected error: {error}"
+        );
+        anyhow::Ok(())
+    })
+    .await
+    .unwrap();
+}
+
+#[tokio::test]
+async fn resolved_waits_are_not_reported() {
+    run(&REGISTRATION, || async {
+        // Both branches wait for the shared leaf. After it's done, the later reads must not see
+        // the earlier waits.
+        let output = timeout(Duration::from_secs(5), diamond().strongly_consistent())
+            .await
+            .expect("the diamond should not hang")?;
+        assert_eq!(*output, 3);
+        anyhow::Ok(())
+    })
+    .await
+    .unwrap();
+}
+
+#[turbo_tasks::function]
+async fn cycle_a() -> Result<Vc<u32>> {
+    Ok(Vc::cell(*cycle_b().await? + 1))
+}
+
+#[turbo_tasks::function]
+async fn cycle_b() -> Result<Vc<u32>> {
+    Ok(Vc::cell(*cycle_a().await? + 1))
+}
+
+#[turbo_tasks::function]
+async fn diamond() -> Result<Vc<u32>> {
+    let left = *branch(1).await?;
+    let right = *branch(2).await?;
+    Ok(Vc::cell(left + right))
+}
+
+#[turbo_tasks::function]
+async fn branch(offset: u32) -> Result<Vc<u32>> {
+    let leaf = *leaf().await?;
+    Ok(Vc::cell(leaf + offset - 1))
+}
+
+#[turbo_tasks::function]
+fn leaf() -> Vc<u32> {
+    Vc::cell(1)
+}
@@ -10,6 +10,7 @@ use std::{
 use anyhow::{anyhow, Result};
 use auto_hash_map::AutoMap;
 use rustc_hash::FxHasher;
+use serde::{Deserialize, Serialize};
 use tracing::Span;
 
 pub use crate::id::BackendJobId;
@@ -120,8 +121,9 @@ mod ser {
             } else {
                 None
             };
-            let mut state = serializer.serialize_tuple(3)?;
+            let mut state = serializer.serialize_tuple(4)?;
             state.serialize_element(registry::get_value_type_global_name(self.0))?;
+            state.serialize_element(&value_type.serialization_version())?;
             if let Some(serializable) = serializable {
                 state.serialize_element(&true)?;
                 state.serialize_element(serializable)?;
@@ -156,9 +158,17 @@ mod ser {
                         .ok_or_else(|| de::Error::invalid_length(0, &self))?;
                     let value_type = registry::get_value_type_id_by_global_name(value_type)
                         .ok_or_else(|| de::Error::custom("Unknown value type"))?;
-                    let has_value: bool = seq
+                    let version: u32 = seq
                         .next_element()?
                         .ok_or_else(|| de::Error::invalid_length(1, &self))?;
+                    // A different layout might deserialize into a wrong value, so the cell is
+                    // dropped before reading the value
+                    if version != registry::get_value_type(value_type).serialization_version() {
+                        return Err(de::Error::custom("Outdated serialization version"));
+                    }
+                    let has_value: bool = seq
+                        .next_element()?
+                        .ok_or_else(|| de::Error::invalid_length(2, &self))?;
                     if has_value {
                         let seed = registry::get_value_type(value_type)
                             .get_any_deserialize_seed()
@@ -167,7 +177,7 @@ mod ser {
                             })?;
                         let value = seq
                             .next_element_seed(seed)?
-                            .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+                            .ok_or_else(|| de::Error::invalid_length(3, &self))?;
                         let arc = triomphe::Arc::<dyn Any + Send + Sync>::from(value);
                         Ok(TypedCellContent(
                             value_type,
@@ -176,13 +186,13 @@ mod ser {
                     } else {
                         let () = seq
                             .next_element()?
-                            .ok_or_else(|| de::Error::invalid_length(2, &self))?;
+                            .ok_or_else(|| de::Error::invalid_length(3, &self))?;
                         Ok(TypedCellContent(value_type, CellContent(None)))
                     }
                 }
             }
 
-            deserializer.deserialize_tuple(2, Visitor)
+            deserializer.deserialize_tuple(4, Visitor)
         }
     }
 
@@ -291,6 +301,28 @@ mod ser {
     }
 }
 
+/// A panic that was caught while executing a task.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TurboTasksPanic {
+    /// The panic payload, when it is a string.
+    pub message: Option<Cow<'static, str>>,
+    /// The source location of the panic, when it is known.
+    pub location: Option<String>,
+}
+
+impl Display for TurboTasksPanic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.message {
+            Some(message) => write!(f, "{message}")?,
+            None => write!(f, "unknown panic payload")?,
+        }
+        if let Some(location) = &self.location {
+            write!(f, " at {location}")?;
+        }
+        Ok(())
+    }
+}
+
 pub struct TaskExecutionSpec<'a> {
     pub future: Pin<Box<dyn Future<Output = Result<RawVc>> + Send + 'a>>,
     pub span: Span,
@@ -422,8 +454,26 @@ pub trait Backend: Sync + Send {
     ) {
     }
 
+    /// Registers `task` to be invalidated when the external resource `key` changes. Backends that
+    /// persist the registration can invalidate the task by key after a restart. The registrations
+    /// of a task are dropped when it executes again.
+    fn register_invalidation_key(
+        &self,
+        _task: TaskId,
+        _key: String,
+        _turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) {
+    }
+
     fn get_task_description(&self, task: TaskId) -> String;
 
+    /// A human-readable name of a job scheduled with
+    /// [`TurboTasksBackendApi::schedule_backend_background_job`] or
+    /// [`TurboTasksBackendApi::schedule_backend_foreground_job`], e.g. for tokio-console.
+    fn get_backend_job_description(&self, job: BackendJobId) -> String {
+        format!("backend job {job}")
+    }
+
     /// Task-local state that stored inside of [`TurboTasksBackendApi`]. Constructed with
     /// [`Self::new_task_state`].
     ///
@@ -456,7 +506,7 @@ pub trait Backend: Sync + Send {
     fn task_execution_result(
         &self,
         task: TaskId,
-        result: Result<Result<RawVc>, Option<Cow<'static, str>>>,
+        result: Result<Result<RawVc>, TurboTasksPanic>,
         turbo_tasks: &dyn TurboTasksBackendApi<Self>,
     );
 
@@ -470,6 +520,12 @@ pub trait Backend: Sync + Send {
         turbo_tasks: &dyn TurboTasksBackendApi<Self>,
     ) -> bool;
 
+    /// Called when a started task execution is dropped before it completed, e.g. when the
+    /// runtime shuts down. The backend should roll back the in-progress state and wake up
+    /// everything waiting for the task, so it can be executed again later.
+    #[allow(unused_variables)]
+    fn task_execution_canceled(&self, task: TaskId, turbo_tasks: &dyn TurboTasksBackendApi<Self>) {}
+
     fn run_backend_job<'a>(
         &'a self,
         id: BackendJobId,
@@ -585,6 +641,19 @@ pub trait Backend: Sync + Send {
         turbo_tasks: &dyn TurboTasksBackendApi<Self>,
     );
 
+    /// Connects many tasks to the parent task at once. Backends can override this to batch the
+    /// work, e.g. to lock the parent task only once.
+    fn connect_tasks(
+        &self,
+        tasks: &[TaskId],
+        parent_task: TaskId,
+        turbo_tasks: &dyn TurboTasksBackendApi<Self>,
+    ) {
+        for &task in tasks {
+            self.connect_task(task, parent_task, turbo_tasks);
+        }
+    }
+
     fn mark_own_task_as_finished(
         &self,
         _task: TaskId,
@@ -616,6 +685,8 @@ pub trait Backend: Sync + Send {
         turbo_tasks: &dyn TurboTasksBackendApi<Self>,
     ) -> TaskId;
 
+    /// Called when a root task is no longer needed. For a once task this is called after its
+    /// output was read, so the backend may drop the state that is only needed for execution.
     fn dispose_root_task(&self, task: TaskId, turbo_tasks: &dyn TurboTasksBackendApi<Self>);
 
     fn task_statistics(&self) -> &TaskStatisticsApi;
@@ -34,13 +34,22 @@ where
     ///
     /// Panics (best-effort) if the id type overflows.
     pub fn get(&self) -> T {
-        let new_id = self.next_id.fetch_add(1, Ordering::Relaxed);
-
-        if new_id > self.max_id {
+        self.try_get().unwrap_or_else(|| {
             panic!(
                 "Max id limit hit while attempting to generate a unique {}",
                 type_name::<T>(),
             )
+        })
+    }
+
+    /// Return a unique new id, or `None` when the max id is reached.
+    ///
+    /// Panics (best-effort) if the id type overflows.
+    pub fn try_get(&self) -> Option<T> {
+        let new_id = self.next_id.fetch_add(1, Ordering::Relaxed);
+
+        if new_id > self.max_id {
+            return None;
         }
 
         // Safety: u64 will not overflow. This is *very* unlikely to happen (would take
@@ -50,7 +59,7 @@ where
         // Use the extra bits of the AtomicU64 as cheap overflow detection when the
         // value is less than 64 bits.
         match new_id.try_into() {
-            Ok(id) => id,
+            Ok(id) => Some(id),
             Err(_) => panic!(
                 "Overflow detected while attempting to generate a unique {}",
                 type_name::<T>(),
@@ -66,6 +75,13 @@ pub struct IdFactoryWithReuse<T> {
     free_ids: ConcurrentQueue<T>,
 }
 
+impl<T> IdFactory<T> {
+    /// The number of ids that can still be generated.
+    pub fn remaining(&self) -> u64 {
+        (self.max_id + 1).saturating_sub(self.next_id.load(Ordering::Relaxed))
+    }
+}
+
 impl<T> IdFactoryWithReuse<T> {
     pub const fn new(start: u64, max: u64) -> Self {
         Self {
@@ -73,6 +89,11 @@ impl<T> IdFactoryWithReuse<T> {
             free_ids: ConcurrentQueue::unbounded(),
         }
     }
+
+    /// The number of ids that can still be returned, including ids on the free list.
+    pub fn remaining(&self) -> u64 {
+        self.factory.remaining() + self.free_ids.len() as u64
+    }
 }
 
 impl<T> IdFactoryWithReuse<T>
@@ -86,6 +107,13 @@ where
         self.free_ids.pop().unwrap_or_else(|_| self.factory.get())
     }
 
+    /// Return a new or potentially reused id, or `None` when all ids are used.
+    ///
+    /// Panics (best-effort) if the id type overflows.
+    pub fn try_get(&self) -> Option<T> {
+        self.free_ids.pop().ok().or_else(|| self.factory.try_get())
+    }
+
     /// Add an id to the free list, allowing it to be re-used on a subsequent
     /// call to [`IdFactoryWithReuse::get`].
     ///
@@ -104,6 +132,19 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_try_get_exhausted() {
+        let factory = IdFactoryWithReuse::<NonZeroU8>::new(1, 2);
+        assert_eq!(factory.remaining(), 2);
+        let first = factory.try_get().unwrap();
+        assert_eq!(factory.try_get(), NonZeroU8::new(2));
+        assert_eq!(factory.remaining(), 0);
+        assert_eq!(factory.try_get(), None);
+        unsafe { factory.reuse(first) };
+        assert_eq!(factory.remaining(), 1);
+        assert_eq!(factory.try_get(), Some(first));
+    }
+
     #[test]
     #[should_panic(expected = "Overflow detected")]
     fn test_overflow() {
@@ -62,6 +62,7 @@ mod native_function;
 mod no_move_vec;
 mod once_map;
 mod output;
+mod panic_capture;
 pub mod persisted_graph;
 pub mod primitives;
 mod raw_vc;
@@ -103,10 +104,10 @@ pub use key_value_pair::KeyValuePair;
 pub use magic_any::MagicAny;
 pub use manager::{
     dynamic_call, emit, mark_finished, mark_root, mark_session_dependent, mark_stateful,
-    prevent_gc, run_once, run_once_with_reason, spawn_blocking, spawn_thread, trait_call,
-    turbo_tasks, turbo_tasks_scope, CurrentCellRef, ReadConsistency, TaskPersistence, TurboTasks,
-    TurboTasksApi, TurboTasksBackendApi, TurboTasksBackendApiExt, TurboTasksCallApi, Unused,
-    UpdateInfo,
+    prevent_gc, register_invalidation_key, run_once, run_once_with_reason, spawn_blocking,
+    spawn_thread, trait_call, turbo_tasks, turbo_tasks_scope, CurrentCellRef, ReadConsistency,
+    TaskPersistence, TurboTasks, TurboTasksApi, TurboTasksBackendApi, TurboTasksBackendApiExt,
+    TurboTasksCallApi, Unused, UpdateInfo,
 };
 pub use output::OutputContent;
 pub use raw_vc::{CellId, RawVc, ReadRawVcFuture, ResolveTypeError};
@@ -243,6 +244,15 @@ macro_rules! fxindexset {
 ///   [`TypedForInput`].
 /// - **`"none"`:** Disables serialization and prevents deriving the traits.
 ///
+/// ## `serialization_version = ...`
+///
+/// The version of the serialized layout, `0` by default. It's persisted with every cell of this
+/// type. Cells that were persisted with a different version are dropped on restore and recomputed,
+/// while the rest of the persistent cache is kept. Increase it when the layout changes in a way
+/// that can't be deserialized anymore.
+///
+/// Example: `#[turbo_tasks::value(serialization_version = 2)]`
+///
 /// ## `shared`
 ///
 /// Sets both `cell = "shared"` *(already the default)* and `into = "shared"`, exposing the
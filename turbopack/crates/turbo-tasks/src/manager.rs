@@ -3,7 +3,7 @@ use std::{
     borrow::Cow,
     future::Future,
     hash::BuildHasherDefault,
-    mem::take,
+    mem::{forget, take},
     panic::AssertUnwindSafe,
     pin::Pin,
     sync::{
@@ -34,6 +34,7 @@ use crate::{
     id::{BackendJobId, FunctionId, LocalTaskId, TraitTypeId, TRANSIENT_TASK_BIT},
     id_factory::IdFactoryWithReuse,
     magic_any::MagicAny,
+    panic_capture::{capture_panic, install_panic_hook},
     raw_vc::{CellId, RawVc},
     registry,
     serialization_invalidation::SerializationInvalidator,
@@ -100,6 +101,8 @@ pub trait TurboTasksApi: TurboTasksCallApi + Sync + Send {
 
     fn invalidate_serialization(&self, task: TaskId);
 
+    fn register_invalidation_key(&self, task: TaskId, key: String);
+
     /// Eagerly notifies all tasks that were scheduled for notifications via
     /// `schedule_notify_tasks_set()`
     fn notify_scheduled_tasks(&self);
@@ -171,6 +174,14 @@ pub trait TurboTasksApi: TurboTasksCallApi + Sync + Send {
 
     fn connect_task(&self, task: TaskId);
 
+    /// Connects many tasks to the current task. This is cheaper than calling
+    /// [`TurboTasksApi::connect_task`] for every task.
+    fn connect_tasks(&self, tasks: &[TaskId]) {
+        for &task in tasks {
+            self.connect_task(task);
+        }
+    }
+
     /// Wraps the given future in the current task.
     ///
     /// Beware: this method is not safe to use in production code. It is only intended for use in
@@ -361,6 +372,25 @@ pub struct TurboTasks<B: Backend + 'static> {
     program_start: Instant,
 }
 
+/// Calls [`Backend::task_execution_canceled`] when dropped. It is forgotten when the execution
+/// completes.
+struct ExecutionCancelGuard<'a, B: Backend + 'static> {
+    turbo_tasks: &'a Arc<TurboTasks<B>>,
+    task_id: TaskId,
+}
+
+impl<B: Backend + 'static> Drop for ExecutionCancelGuard<'_, B> {
+    fn drop(&mut self) {
+        // Panics are captured, so this only happens when the backend itself panicked
+        if thread::panicking() {
+            return;
+        }
+        self.turbo_tasks
+            .backend
+            .task_execution_canceled(self.task_id, &**self.turbo_tasks);
+    }
+}
+
 /// Information about a non-local task. A non-local task can contain multiple "local" tasks, which
 /// all share the same non-local task state.
 ///
@@ -452,6 +482,7 @@ impl<B: Backend + 'static> TurboTasks<B> {
     // so we probably want to make sure that all tasks are joined
     // when trying to drop turbo tasks
     pub fn new(backend: B) -> Arc<Self> {
+        install_panic_hook();
         let task_id_factory = IdFactoryWithReuse::new(1, (TRANSIENT_TASK_BIT - 1) as u64);
         let transient_task_id_factory =
             IdFactoryWithReuse::new(TRANSIENT_TASK_BIT as u64, u32::MAX as u64);
@@ -537,15 +568,22 @@ impl<B: Backend + 'static> TurboTasks<B> {
                 .map_err(|_| anyhow!("unable to send result"))?;
             Ok(Completion::new())
         });
-        // INVALIDATION: A Once task will never invalidate, therefore we don't need to
-        // track a dependency
-        let raw_result =
-            read_task_output_untracked(self, task_id, ReadConsistency::Eventual).await?;
-        turbo_tasks_future_scope(
-            self.pin(),
-            ReadVcFuture::<Completion>::from(raw_result.into_read().untracked()),
-        )
-        .await?;
+        let read_result = async {
+            // INVALIDATION: A Once task will never invalidate, therefore we don't need to
+            // track a dependency
+            let raw_result =
+                read_task_output_untracked(self, task_id, ReadConsistency::Eventual).await?;
+            turbo_tasks_future_scope(
+                self.pin(),
+                ReadVcFuture::<Completion>::from(raw_result.into_read().untracked()),
+            )
+            .await
+        }
+        .await;
+        // The output is read and the task is never executed again, so the backend can release its
+        // execution state
+        self.dispose_root_task(task_id);
+        read_result?;
 
         Ok(rx.await?)
     }
@@ -656,9 +694,19 @@ impl<B: Backend + 'static> TurboTasks<B> {
                         return false;
                     };
 
+                    // Notifies the backend when this future is dropped before the execution
+                    // completed
+                    let cancel_guard = ExecutionCancelGuard {
+                        turbo_tasks: &this,
+                        task_id,
+                    };
+
                     async {
                         let (result, duration, memory_usage) =
                             CaptureFuture::new(AssertUnwindSafe(future).catch_unwind()).await;
+                        // The panic location is recorded per thread, so it must be read before
+                        // the next await, which might continue on another thread
+                        let result = result.map_err(capture_panic);
 
                         // wait for all spawned local tasks using `local` to finish
                         let ltt = CURRENT_TASK_STATE
@@ -666,13 +714,6 @@ impl<B: Backend + 'static> TurboTasks<B> {
                         ltt.close();
                         ltt.wait().await;
 
-                        let result = result.map_err(|any| match any.downcast::<String>() {
-                            Ok(owned) => Some(Cow::Owned(*owned)),
-                            Err(any) => match any.downcast::<&'static str>() {
-                                Ok(str) => Some(Cow::Borrowed(*str)),
-                                Err(_) => None,
-                            },
-                        });
                         this.backend.task_execution_result(task_id, result, &*this);
                         let stateful = this.finish_current_task_state();
                         let cell_counters = CURRENT_TASK_STATE
@@ -685,6 +726,7 @@ impl<B: Backend + 'static> TurboTasks<B> {
                             stateful,
                             &*this,
                         );
+                        forget(cancel_guard);
                         // task_execution_completed might need to notify tasks
                         this.notify_scheduled_tasks();
                         schedule_again
@@ -702,16 +744,7 @@ impl<B: Backend + 'static> TurboTasks<B> {
 
         let future = TURBO_TASKS.scope(self.pin(), future).in_current_span();
 
-        #[cfg(feature = "tokio_tracing")]
-        {
-            let description = self.backend.get_task_description(task_id);
-            tokio::task::Builder::new()
-                .name(&description)
-                .spawn(future)
-                .unwrap();
-        }
-        #[cfg(not(feature = "tokio_tracing"))]
-        tokio::task::spawn(future);
+        spawn_named(|| self.backend.get_task_description(task_id), future);
     }
 
     fn schedule_local_task(
@@ -735,12 +768,16 @@ impl<B: Backend + 'static> TurboTasks<B> {
             (Arc::clone(gts), local_task_id, gts_write.task_id)
         });
 
-        #[cfg(feature = "tokio_tracing")]
-        let description = format!(
-            "[local] (parent: {}) {}",
-            self.backend.get_task_description(parent_task_id),
-            ty,
-        );
+        let description = {
+            let ty = Arc::clone(&ty);
+            move || {
+                format!(
+                    "[local] (parent: {}) {}",
+                    self.backend.get_task_description(parent_task_id),
+                    ty,
+                )
+            }
+        };
 
         let this = self.pin();
         let future = async move {
@@ -750,18 +787,14 @@ impl<B: Backend + 'static> TurboTasks<B> {
                 let (result, _duration, _memory_usage) =
                     CaptureFuture::new(AssertUnwindSafe(future).catch_unwind()).await;
 
-                let result = result.map_err(|any| match any.downcast::<String>() {
-                    Ok(owned) => Some(Cow::Owned(*owned)),
-                    Err(any) => match any.downcast::<&'static str>() {
-                        Ok(str) => Some(Cow::Borrowed(*str)),
-                        Err(_) => None,
-                    },
-                });
+                let result = result.map_err(capture_panic);
                 let local_task = LocalTask::Done {
                     output: match result {
                         Ok(Ok(raw_vc)) => OutputContent::Link(raw_vc),
                         Ok(Err(err)) => OutputContent::Error(err.into()),
-                        Err(panic_err) => OutputContent::Panic(panic_err.map(Box::new)),
+                        Err(panic) => {
+                            OutputContent::Panic(Some(Box::new(Cow::Owned(panic.to_string()))))
+                        }
                     },
                 };
 
@@ -787,13 +820,7 @@ impl<B: Backend + 'static> TurboTasks<B> {
         let future = CURRENT_TASK_STATE.scope(global_task_state, future);
         let future = TURBO_TASKS.scope(self.pin(), future).in_current_span();
 
-        #[cfg(feature = "tokio_tracing")]
-        tokio::task::Builder::new()
-            .name(&description)
-            .spawn(future)
-            .unwrap();
-        #[cfg(not(feature = "tokio_tracing"))]
-        tokio::task::spawn(future);
+        spawn_named(description, future);
 
         RawVc::LocalOutput(parent_task_id, local_task_id)
     }
@@ -1029,7 +1056,8 @@ impl<B: Backend + 'static> TurboTasks<B> {
                 listener.await;
             }
         }
-        self.backend.stop(self);
+        // The backend might need access to turbo-tasks, e.g. to serialize task data
+        turbo_tasks_scope(self.pin(), || self.backend.stop(self));
     }
 
     #[track_caller]
@@ -1038,12 +1066,14 @@ impl<B: Backend + 'static> TurboTasks<B> {
         F: Future<Output = ()> + Send + 'static,
     >(
         &self,
+        description: impl FnOnce() -> String,
         func: T,
     ) {
         let this = self.pin();
         self.currently_scheduled_background_jobs
             .fetch_add(1, Ordering::AcqRel);
-        tokio::spawn(
+        spawn_named(
+            description,
             TURBO_TASKS
                 .scope(this.clone(), async move {
                     while this.currently_scheduled_tasks.load(Ordering::Acquire) != 0 {
@@ -1076,11 +1106,13 @@ impl<B: Backend + 'static> TurboTasks<B> {
         F: Future<Output = ()> + Send + 'static,
     >(
         &self,
+        description: impl FnOnce() -> String,
         func: T,
     ) {
         let this = self.pin();
         this.begin_foreground_job();
-        tokio::spawn(
+        spawn_named(
+            description,
             TURBO_TASKS
                 .scope(this.clone(), async move {
                     if !this.stopped.load(Ordering::Acquire) {
@@ -1208,6 +1240,10 @@ impl<B: Backend + 'static> TurboTasksApi for TurboTasks<B> {
         self.backend.invalidate_serialization(task, self);
     }
 
+    fn register_invalidation_key(&self, task: TaskId, key: String) {
+        self.backend.register_invalidation_key(task, key, self);
+    }
+
     fn notify_scheduled_tasks(&self) {
         let _ = CURRENT_TASK_STATE.try_with(|cell| {
             let tasks = {
@@ -1353,6 +1389,11 @@ impl<B: Backend + 'static> TurboTasksApi for TurboTasks<B> {
             .connect_task(task, current_task("connecting task"), self);
     }
 
+    fn connect_tasks(&self, tasks: &[TaskId]) {
+        self.backend
+            .connect_tasks(tasks, current_task("connecting tasks"), self);
+    }
+
     fn mark_own_task_as_finished(&self, task: TaskId) {
         self.backend.mark_own_task_as_finished(task, self);
     }
@@ -1407,16 +1448,32 @@ impl<B: Backend + 'static> TurboTasksBackendApi<B> for TurboTasks<B> {
 
     #[track_caller]
     fn schedule_backend_background_job(&self, id: BackendJobId) {
-        self.schedule_background_job(move |this| async move {
-            this.backend.run_backend_job(id, &*this).await;
-        })
+        self.schedule_background_job(
+            || {
+                format!(
+                    "[background] {}",
+                    self.backend.get_backend_job_description(id)
+                )
+            },
+            move |this| async move {
+                this.backend.run_backend_job(id, &*this).await;
+            },
+        )
     }
 
     #[track_caller]
     fn schedule_backend_foreground_job(&self, id: BackendJobId) {
-        self.schedule_foreground_job(move |this| async move {
-            this.backend.run_backend_job(id, &*this).await;
-        })
+        self.schedule_foreground_job(
+            || {
+                format!(
+                    "[foreground] {}",
+                    self.backend.get_backend_job_description(id)
+                )
+            },
+            move |this| async move {
+                this.backend.run_backend_job(id, &*this).await;
+            },
+        )
     }
 
     fn try_foreground_done(&self) -> Result<(), EventListener> {
@@ -1527,6 +1584,25 @@ impl<B: Backend + 'static> TurboTasksBackendApi<B> for TurboTasks<B> {
     }
 }
 
+/// Spawns a future on the tokio runtime. With the `tokio_tracing` feature the tokio task is named
+/// with the `description`, so tools like tokio-console show it instead of an anonymous future.
+#[track_caller]
+fn spawn_named<F>(description: impl FnOnce() -> String, future: F)
+where
+    F: Future<Output: Send + 'static> + Send + 'static,
+{
+    #[cfg(feature = "tokio_tracing")]
+    tokio::task::Builder::new()
+        .name(&description())
+        .spawn(future)
+        .unwrap();
+    #[cfg(not(feature = "tokio_tracing"))]
+    {
+        let _ = description;
+        tokio::task::spawn(future);
+    }
+}
+
 pub(crate) fn current_task(from: &str) -> TaskId {
     match CURRENT_TASK_STATE.try_with(|ts| ts.read().unwrap().task_id) {
         Ok(id) => id,
@@ -1661,6 +1737,18 @@ pub fn mark_session_dependent() {
     });
 }
 
+/// Registers the current task to be invalidated when the external resource `key` changes, e.g. the
+/// path of a watched file. Unlike an [`Invalidator`][crate::Invalidator], the registration can be
+/// persisted, so the task can still be invalidated by the key after a restart.
+pub fn register_invalidation_key(key: impl Into<String>) {
+    with_turbo_tasks(|tt| {
+        tt.register_invalidation_key(
+            current_task("turbo_tasks::register_invalidation_key()"),
+            key.into(),
+        )
+    });
+}
+
 /// Marks the current task as finished. This excludes it from waiting for
 /// strongly consistency.
 pub fn mark_root() {
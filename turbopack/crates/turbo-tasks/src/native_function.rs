@@ -143,6 +143,9 @@ pub struct FunctionMeta {
     /// task-local state. The function call itself will not be cached, but cells will be created on
     /// the parent task.
     pub local: bool,
+    /// The cell contents of the task are never persisted, e.g. because they are huge or machine
+    /// specific. They are recomputed when read after a restart.
+    pub memory_only: bool,
 }
 
 /// A native (rust) turbo-tasks function. It's used internally by
@@ -0,0 +1,37 @@
+use std::{any::Any, borrow::Cow, cell::RefCell, panic, sync::Once};
+
+use crate::backend::TurboTasksPanic;
+
+thread_local! {
+    /// The location of the last panic on this thread.
+    static LAST_PANIC_LOCATION: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// Installs a panic hook that records the location of panics, so it can be reported for panicked
+/// tasks. The previously installed hook is still called.
+pub(crate) fn install_panic_hook() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let location = info.location().map(|location| location.to_string());
+            LAST_PANIC_LOCATION.with(|last| *last.borrow_mut() = location);
+            previous(info);
+        }));
+    });
+}
+
+/// Converts the payload of a caught panic into a [`TurboTasksPanic`]. Must be called on the thread
+/// that panicked, since the location is recorded per thread, i.e. directly after `catch_unwind`
+/// returned and before awaiting anything else.
+pub(crate) fn capture_panic(payload: Box<dyn Any + Send>) -> TurboTasksPanic {
+    let message = match payload.downcast::<String>() {
+        Ok(owned) => Some(Cow::Owned(*owned)),
+        Err(any) => match any.downcast::<&'static str>() {
+            Ok(str) => Some(Cow::Borrowed(*str)),
+            Err(_) => None,
+        },
+    };
+    let location = LAST_PANIC_LOCATION.with(|last| last.borrow_mut().take());
+    TurboTasksPanic { message, location }
+}
@@ -50,6 +50,24 @@ impl TaskStatistics {
         self.with_task_type_statistics(function_id, |stats| stats.cache_miss += 1)
     }
 
+    /// Returns the statistics of all functions by their global name, the functions with the most
+    /// cache misses first. Functions with a low [`TaskFunctionStatistics::hit_ratio`] and many
+    /// misses likely receive arguments that defeat caching.
+    pub fn get(&self) -> Vec<(&'static str, TaskFunctionStatistics)> {
+        let mut stats = self
+            .inner
+            .iter()
+            .map(|entry| {
+                (
+                    registry::get_function_global_name(*entry.key()),
+                    *entry.value(),
+                )
+            })
+            .collect::<Vec<_>>();
+        stats.sort_by(|(_, a), (_, b)| b.cache_miss.cmp(&a.cache_miss));
+        stats
+    }
+
     fn with_task_type_statistics(
         &self,
         task_function_id: FunctionId,
@@ -60,10 +78,23 @@ impl TaskStatistics {
 }
 
 /// Statistics for an individual function.
-#[derive(Default, Serialize)]
-struct TaskFunctionStatistics {
-    cache_hit: u32,
-    cache_miss: u32,
+#[derive(Debug, Default, Clone, Copy, Serialize)]
+pub struct TaskFunctionStatistics {
+    /// The number of calls that found an existing task.
+    pub cache_hit: u32,
+    /// The number of calls that created a new task.
+    pub cache_miss: u32,
+}
+
+impl TaskFunctionStatistics {
+    /// Returns the share of calls that found an existing task, from 0.0 to 1.0.
+    pub fn hit_ratio(&self) -> f64 {
+        let calls = self.cache_hit + self.cache_miss;
+        if calls == 0 {
+            return 0.0;
+        }
+        self.cache_hit as f64 / calls as f64
+    }
 }
 
 impl Serialize for TaskStatistics {
@@ -24,6 +24,7 @@ use crate::{
 type MagicSerializationFn = fn(&dyn MagicAny) -> &dyn erased_serde::Serialize;
 type AnySerializationFn = fn(&(dyn Any + Sync + Send)) -> &dyn erased_serde::Serialize;
 type RawCellFactoryFn = fn(TypedSharedReference) -> RawVc;
+type AnyEqFn = fn(&(dyn Any + Sync + Send), &(dyn Any + Sync + Send)) -> bool;
 
 // TODO this type need some refactoring when multiple languages are added to
 // turbo-task In this case a trait_method might be of a different function type.
@@ -45,6 +46,13 @@ pub struct ValueType {
     /// Functors for serialization
     magic_serialization: Option<(MagicSerializationFn, MagicAnyDeserializeSeed)>,
     any_serialization: Option<(AnySerializationFn, AnyDeserializeSeed)>,
+    /// The version of the serialized layout, see
+    /// [`#[turbo_tasks::value(serialization_version = ...)]`][macro@crate::value].
+    serialization_version: u32,
+
+    /// Compares two values of this type with their `PartialEq` implementation, if the type is
+    /// known to implement it.
+    eq: Option<AnyEqFn>,
 
     /// An implementation of
     /// [`VcCellMode::raw_cell`][crate::vc::cell_mode::VcCellMode::raw_cell].
@@ -101,6 +109,13 @@ pub fn any_as_serialize<T: Any + Serialize + Send + Sync + 'static>(
     );
 }
 
+fn any_eq<T: Any + PartialEq>(a: &(dyn Any + Sync + Send), b: &(dyn Any + Sync + Send)) -> bool {
+    match (a.downcast_ref::<T>(), b.downcast_ref::<T>()) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
 impl ValueType {
     /// This is internally used by `#[turbo_tasks::value]`
     pub fn new<T: VcValueType>() -> Self {
@@ -110,6 +125,8 @@ impl ValueType {
             trait_methods: AutoMap::new(),
             magic_serialization: None,
             any_serialization: None,
+            serialization_version: 0,
+            eq: None,
             raw_cell: <T::CellMode as VcCellMode<T>>::raw_cell,
         }
     }
@@ -127,6 +144,8 @@ impl ValueType {
                 MagicAnyDeserializeSeed::new::<T>(),
             )),
             any_serialization: Some((any_as_serialize::<T>, AnyDeserializeSeed::new::<T>())),
+            serialization_version: 0,
+            eq: None,
             raw_cell: <T::CellMode as VcCellMode<T>>::raw_cell,
         }
     }
@@ -141,6 +160,8 @@ impl ValueType {
             trait_methods: AutoMap::new(),
             magic_serialization: None,
             any_serialization: Some((any_as_serialize::<T>, AnyDeserializeSeed::new::<T>())),
+            serialization_version: 0,
+            eq: None,
             raw_cell: <T::CellMode as VcCellMode<T>>::raw_cell,
         }
     }
@@ -168,6 +189,33 @@ impl ValueType {
         }
     }
 
+    /// This is internally used by `#[turbo_tasks::value]`
+    pub fn with_serialization_version(mut self, version: u32) -> Self {
+        self.serialization_version = version;
+        self
+    }
+
+    pub fn serialization_version(&self) -> u32 {
+        self.serialization_version
+    }
+
+    /// This is internally used by `#[turbo_tasks::value]`
+    pub fn with_eq<T: VcValueType + PartialEq>(mut self) -> Self {
+        self.eq = Some(any_eq::<T>);
+        self
+    }
+
+    pub fn has_eq(&self) -> bool {
+        self.eq.is_some()
+    }
+
+    /// Compares two values of this type with their `PartialEq` implementation. Returns `None`
+    /// when the type doesn't implement `PartialEq`, e.g. for `#[turbo_tasks::value(eq =
+    /// "manual")]`.
+    pub fn is_eq(&self, a: &(dyn Any + Sync + Send), b: &(dyn Any + Sync + Send)) -> Option<bool> {
+        self.eq.map(|eq| eq(a, b))
+    }
+
     pub fn is_serializable(&self) -> bool {
         self.any_serialization.is_some()
     }
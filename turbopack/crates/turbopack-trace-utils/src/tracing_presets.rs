@@ -3,6 +3,7 @@ use once_cell::sync::Lazy;
 pub static TRACING_OVERVIEW_TARGETS: Lazy<Vec<&str>> = Lazy::new(|| {
     vec![
         "turbo_tasks=info",
+        "turbo_tasks_backend=info",
         "turbo_tasks_fs=info",
         "turbopack=info",
         "turbopack_binding=info",